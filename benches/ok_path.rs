@@ -0,0 +1,27 @@
+#[macro_use]
+extern crate criterion;
+extern crate problem;
+
+use criterion::{black_box, Criterion};
+use problem::prelude::*;
+use std::num::ParseIntError;
+
+fn plain(input: &str) -> Result<u32, ParseIntError> {
+    let number = input.parse::<u32>()?;
+    Ok(number)
+}
+
+fn with_context(input: &str) -> Result<u32, Problem> {
+    let number = input.parse::<u32>().problem_while("parsing number")?;
+    Ok(number)
+}
+
+fn ok_path(c: &mut Criterion) {
+    c.bench_function("ok_path_plain", |b| b.iter(|| plain(black_box("42"))));
+    c.bench_function("ok_path_problem_while", |b| {
+        b.iter(|| with_context(black_box("42")))
+    });
+}
+
+criterion_group!(benches, ok_path);
+criterion_main!(benches);
@@ -0,0 +1,59 @@
+#[macro_use]
+extern crate criterion;
+extern crate problem;
+
+use criterion::{black_box, Criterion};
+use problem::prelude::*;
+use problem::ProblemPool;
+
+/// Every 20th record is invalid
+fn records() -> Vec<String> {
+    (0..10_000)
+        .map(|no| {
+            if no % 20 == 0 {
+                format!("{}x", no)
+            } else {
+                no.to_string()
+            }
+        })
+        .collect()
+}
+
+fn parse(c: &mut Criterion) {
+    let records = records();
+
+    c.bench_function("problem_while", |b| {
+        b.iter(|| {
+            let mut failed = 0;
+            for (line, record) in records.iter().enumerate() {
+                if let Err(problem) = record
+                    .parse::<u32>()
+                    .problem_while_with(|| format!("parsing record {}", line))
+                {
+                    black_box(&problem);
+                    failed += 1;
+                }
+            }
+            failed
+        })
+    });
+
+    c.bench_function("pool_problem_while", |b| {
+        let pool = ProblemPool::new(16);
+        b.iter(|| {
+            let mut failed = 0;
+            for (line, record) in records.iter().enumerate() {
+                if let Err(err) = record.parse::<u32>() {
+                    let problem = pool.problem_while(err, format_args!("parsing record {}", line));
+                    black_box(&problem);
+                    pool.recycle(problem);
+                    failed += 1;
+                }
+            }
+            failed
+        })
+    });
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
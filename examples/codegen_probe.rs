@@ -0,0 +1,23 @@
+//! Functions with known symbol names for `tests/codegen.rs` to inspect in generated assembly.
+extern crate problem;
+
+use problem::prelude::*;
+use std::hint::black_box;
+use std::num::ParseIntError;
+
+/// `Ok` known at compile time: `problem_while` must be inlined and its error handling optimised out
+#[no_mangle]
+pub fn probe_ok_path(number: u32) -> Result<u32, Problem> {
+    Ok::<_, ParseIntError>(number).problem_while("parsing number")
+}
+
+/// Result known only at run time: error handling must be left to the cold function
+#[no_mangle]
+pub fn probe_parse(input: &str) -> Result<u32, Problem> {
+    input.parse::<u32>().problem_while("parsing number")
+}
+
+fn main() {
+    black_box(probe_ok_path(black_box(42))).unwrap();
+    black_box(probe_parse(black_box("42"))).unwrap();
+}
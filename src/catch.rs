@@ -0,0 +1,337 @@
+//! Panic boundaries converting panics to `Problem`s.
+//!
+//! Panic payloads are converted with `Problem::from_panic_payload`. Note that the panic hook is still called when the panic
+//! occurs, before it is caught.
+use super::{
+    context_stack, truncate_context_stack, Problem, ProblemError, ProblemWhile, ToProblem,
+};
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::thread::{self, JoinHandle};
+
+/// Run closure converting its panic to `Problem` with messages of `in_context_of` calls that were executing when it panicked
+///
+/// Result is converted to `ProblemError` so it can be sent back to the thread that spawned the work.
+pub(crate) fn run_compute<T, E, F>(body: F) -> Result<T, ProblemError>
+where
+    F: FnOnce() -> Result<T, E>,
+    E: ToProblem,
+{
+    let depth = context_stack().len();
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(result) => result.map_err(|error| error.into_problem().into()),
+        Err(payload) => {
+            // `in_context_of` messages of unwound calls are still on the stack
+            let stack = context_stack();
+            truncate_context_stack(depth);
+            let problem = stack
+                .iter()
+                .skip(depth)
+                .rev()
+                .fold(Problem::from_panic_payload(payload), |problem, message| {
+                    problem.problem_while(message.as_str())
+                });
+            Err(problem.into())
+        }
+    }
+}
+
+/// Handle of work started with `spawn_compute`
+#[derive(Debug)]
+pub struct ComputeHandle<T> {
+    thread: Result<JoinHandle<Result<T, ProblemError>>, io::Error>,
+}
+
+impl<T> ComputeHandle<T> {
+    /// Wait for the work to finish and get its result
+    pub fn join(self) -> Result<T, Problem> {
+        match self.thread {
+            Ok(thread) => match thread.join() {
+                Ok(result) => result.map_err(ProblemError::into_problem),
+                Err(payload) => Err(Problem::from_panic_payload(payload)),
+            },
+            Err(error) => Err(error).problem_while("spawning compute thread"),
+        }
+    }
+
+    /// Check if the work has finished without blocking
+    pub fn is_finished(&self) -> bool {
+        match &self.thread {
+            Ok(thread) => thread.is_finished(),
+            Err(_) => true,
+        }
+    }
+}
+
+/// Run closure on a new thread; its `Err` or panic is returned as `Problem` by `ComputeHandle::join`
+///
+/// If the closure panics, messages of `in_context_of` calls executing on the worker thread at the time are added to the
+/// `Problem` as its context when context stacks are enabled (see `set_context_stack`).
+///
+/// ```rust
+/// use problem::prelude::*;
+/// use problem::spawn_compute;
+///
+/// problem::set_context_stack(true);
+/// let handle = spawn_compute(|| in_context_of("hashing chunk 3", || -> Result<u64, Problem> {
+///     panic!("index out of bounds")
+/// }));
+///
+/// assert_eq!(handle.join().unwrap_err().to_string(), "while hashing chunk 3 got error caused by: index out of bounds");
+/// ```
+pub fn spawn_compute<T, E, F>(body: F) -> ComputeHandle<T>
+where
+    F: FnOnce() -> Result<T, E> + Send + 'static,
+    E: ToProblem,
+    T: Send + 'static,
+{
+    ComputeHandle {
+        thread: thread::Builder::new()
+            .name("problem-compute".to_owned())
+            .spawn(move || run_compute(body)),
+    }
+}
+
+#[cfg(feature = "futures")]
+pub use self::future::{catch_async, CatchAsync};
+
+#[cfg(feature = "futures")]
+mod future {
+    use super::*;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    /// Future returned by `catch_async`
+    pub struct CatchAsync<F> {
+        future: Pin<Box<F>>,
+    }
+
+    // the inner future is pinned on the heap
+    impl<F> Unpin for CatchAsync<F> {}
+
+    impl<F, T, E> Future for CatchAsync<F>
+    where
+        F: Future<Output = Result<T, E>>,
+        E: ToProblem,
+    {
+        type Output = Result<T, Problem>;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+            let future = self.future.as_mut();
+            match panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+                Ok(Poll::Pending) => Poll::Pending,
+                Ok(Poll::Ready(result)) => Poll::Ready(result.map_err(ToProblem::into_problem)),
+                Err(payload) => Poll::Ready(Err(Problem::from_panic_payload(payload))),
+            }
+        }
+    }
+
+    /// Wrap future returning `Result` so that if it panics while polled, the panic is returned as `Err` `Problem`
+    ///
+    /// The future is treated as unwind safe; it should not be polled again after it panicked.
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    /// use problem::catch_async;
+    /// use std::future::Future;
+    /// use std::pin::pin;
+    /// use std::task::{Context, Poll, Waker};
+    ///
+    /// let future = catch_async(std::future::poll_fn(|_| {
+    ///     Err::<(), _>("bad state").or_failed_to("process task");
+    ///     Poll::Ready(Ok::<_, Problem>(()))
+    /// }));
+    ///
+    /// let mut cx = Context::from_waker(Waker::noop());
+    /// match pin!(future).poll(&mut cx) {
+    ///     Poll::Ready(Err(problem)) => assert_eq!(problem.to_string(), "Failed to process task due to: bad state"),
+    ///     _ => panic!("expected caught panic"),
+    /// }
+    /// ```
+    pub fn catch_async<F, T, E>(future: F) -> CatchAsync<F>
+    where
+        F: Future<Output = Result<T, E>>,
+        E: ToProblem,
+    {
+        CatchAsync {
+            future: Box::pin(future),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::task::Waker;
+
+        struct Panicking;
+
+        impl Future for Panicking {
+            type Output = Result<(), Problem>;
+
+            fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Self::Output> {
+                panic!("boom!")
+            }
+        }
+
+        fn poll<F: Future>(future: F) -> Poll<F::Output> {
+            Box::pin(future)
+                .as_mut()
+                .poll(&mut Context::from_waker(Waker::noop()))
+        }
+
+        #[test]
+        fn results() {
+            crate::disable_backtraces();
+            match poll(catch_async(std::future::ready(Ok::<_, Problem>(4)))) {
+                Poll::Ready(Ok(value)) => assert_eq!(value, 4),
+                _ => panic!("expected value"),
+            }
+            match poll(catch_async(std::future::ready(Err::<(), _>("boom!")))) {
+                Poll::Ready(Err(problem)) => assert_eq!(problem.to_string(), "boom!"),
+                _ => panic!("expected problem"),
+            }
+        }
+
+        #[test]
+        fn panicked() {
+            crate::disable_backtraces();
+            match poll(catch_async(Panicking)) {
+                Poll::Ready(Err(problem)) => assert_eq!(problem.to_string(), "boom!"),
+                _ => panic!("expected caught panic"),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+pub use self::tokio_task::{spawn_compute_async, ComputeTask};
+
+#[cfg(feature = "tokio")]
+mod tokio_task {
+    use super::*;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use tokio::task;
+
+    /// Future returned by `spawn_compute_async`
+    #[derive(Debug)]
+    pub struct ComputeTask<T> {
+        handle: task::JoinHandle<Result<T, ProblemError>>,
+    }
+
+    impl<T> Future for ComputeTask<T> {
+        type Output = Result<T, Problem>;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+            Pin::new(&mut self.handle)
+                .poll(cx)
+                .map(|result| match result {
+                    Ok(result) => result.map_err(ProblemError::into_problem),
+                    Err(error) => Err(error).problem_while("running compute task"),
+                })
+        }
+    }
+
+    /// Same as `spawn_compute` but runs the closure on `tokio` blocking thread pool (see `tokio::task::spawn_blocking`)
+    ///
+    /// This has to be called from within `tokio` runtime.
+    ///
+    /// ```rust
+    /// # extern crate tokio;
+    /// use problem::prelude::*;
+    /// use problem::spawn_compute_async;
+    ///
+    /// let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    /// let _guard = runtime.enter();
+    /// let result = runtime.block_on(spawn_compute_async(|| Err::<u64, _>("checksum mismatch")));
+    ///
+    /// assert_eq!(result.unwrap_err().to_string(), "checksum mismatch");
+    /// ```
+    pub fn spawn_compute_async<T, E, F>(body: F) -> ComputeTask<T>
+    where
+        F: FnOnce() -> Result<T, E> + Send + 'static,
+        E: ToProblem,
+        T: Send + 'static,
+    {
+        ComputeTask {
+            handle: task::spawn_blocking(move || run_compute(body)),
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::in_context_of;
+
+        #[test]
+        fn panicked() {
+            crate::set_context_stack(true);
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap();
+            let _guard = runtime.enter();
+            let problem = runtime
+                .block_on(spawn_compute_async(|| {
+                    in_context_of("decoding frame", || -> Result<(), Problem> {
+                        panic!("boom!")
+                    })
+                }))
+                .unwrap_err();
+            // `Problem` is created on a blocking thread which may capture backtrace
+            assert_eq!(problem.contexts().collect::<Vec<_>>(), ["decoding frame"]);
+            assert_eq!(problem.causes().last().unwrap().to_string(), "boom!");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::in_context_of;
+
+    #[test]
+    fn joined() {
+        assert_eq!(spawn_compute(|| Ok::<_, Problem>(2 + 2)).join().unwrap(), 4);
+
+        let problem = spawn_compute(|| {
+            in_context_of("parsing", || -> Result<(), Problem> {
+                Err(Problem::from_error("boom!"))
+            })
+        })
+        .join()
+        .unwrap_err();
+        assert_eq!(problem.contexts().collect::<Vec<_>>(), ["parsing"]);
+    }
+
+    #[test]
+    fn panicked() {
+        crate::set_context_stack(true);
+        let problem = spawn_compute(|| {
+            in_context_of("processing batch", || {
+                in_context_of("hashing chunk 3", || -> Result<(), Problem> {
+                    panic!("boom!")
+                })
+            })
+        })
+        .join()
+        .unwrap_err();
+        assert_eq!(
+            problem.contexts().collect::<Vec<_>>(),
+            ["hashing chunk 3", "processing batch"]
+        );
+
+        // unwound messages are removed from the stack
+        let problem = in_context_of("testing", || {
+            run_compute(|| in_context_of("parsing", || -> Result<(), Problem> { panic!("boom!") }))
+                .map_err(ProblemError::into_problem)
+        })
+        .unwrap_err();
+        assert_eq!(
+            problem.contexts().collect::<Vec<_>>(),
+            ["parsing", "testing"]
+        );
+        assert!(context_stack().is_empty());
+    }
+}
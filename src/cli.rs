@@ -0,0 +1,71 @@
+//! Integration with `clap` command line argument parser.
+use super::Problem;
+use clap::Parser;
+use std::ffi::OsString;
+
+/// Exit status used for `Problem`s caused by invalid command line arguments
+pub const USAGE_EXIT_CODE: i32 = 2;
+
+/// Parse program arguments with `clap` converting parse errors to `Problem` with exit code 2
+///
+/// If user requested help or version information it will be printed and program will exit as it would with `Parser::parse`.
+///
+/// ```rust,no_run
+/// # extern crate clap;
+/// # extern crate problem;
+/// use problem::prelude::*;
+/// use clap::Parser;
+///
+/// #[derive(Parser)]
+/// struct Cli {
+///     name: String,
+/// }
+///
+/// fn main() -> FinalResult {
+///     let cli: Cli = problem::parse_args_or_problem()?;
+///     println!("Hello {}!", cli.name);
+///     Ok(())
+/// }
+/// ```
+pub fn parse_args_or_problem<P: Parser>() -> Result<P, Problem> {
+    parse_args_from_or_problem(std::env::args_os())
+}
+
+/// Same as `parse_args_or_problem` but parses given arguments
+pub fn parse_args_from_or_problem<P, I, T>(args: I) -> Result<P, Problem>
+where
+    P: Parser,
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    P::try_parse_from(args).map_err(|err| {
+        if !err.use_stderr() {
+            // help or version requested
+            err.exit()
+        }
+        Problem::from_error(err).with_exit_code(USAGE_EXIT_CODE)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser, Debug)]
+    struct Cli {
+        #[arg(long)]
+        count: u32,
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let cli: Cli = parse_args_from_or_problem(["test", "--count", "4"]).unwrap();
+        assert_eq!(cli.count, 4);
+
+        let problem =
+            parse_args_from_or_problem::<Cli, _, _>(["test", "--count", "four"]).unwrap_err();
+        assert_eq!(problem.exit_code(), Some(2));
+        assert!(problem.to_string().contains("invalid value 'four'"));
+    }
+}
@@ -0,0 +1,205 @@
+//! Colored reports on terminals.
+//!
+//! Whether colors are used is decided automatically in the same way as other modern command line tools do:
+//! * colors are disabled if `NO_COLOR` environment variable is set to non-empty value,
+//! * colors are enabled if `CLICOLOR_FORCE` environment variable is set to value other than `0`,
+//! * colors are disabled if `CLICOLOR` environment variable is set to `0`,
+//! * otherwise colors are used only if `stderr` is a terminal.
+//!
+//! This decision can be overridden with `set_color_choice` (e.g. from `--color` command line option).
+//!
+//! On Windows virtual terminal processing is enabled on the console before colored output is written; if this fails
+//! (e.g. on old versions of Windows) plain text is written instead.
+use super::{format_panic, format_panic_backtrace, record_panic_exit_code};
+use std::io::{self, IsTerminal};
+use std::panic;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Whether colors should be used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorChoice {
+    /// Decide based on environment variables and whether `stderr` is a terminal (default)
+    #[default]
+    Auto,
+    /// Always use colors
+    Always,
+    /// Never use colors
+    Never,
+}
+
+static COLOR_CHOICE: AtomicU8 = AtomicU8::new(0);
+
+/// Override automatic decision whether colors are used
+pub fn set_color_choice(choice: ColorChoice) {
+    COLOR_CHOICE.store(
+        match choice {
+            ColorChoice::Auto => 0,
+            ColorChoice::Always => 1,
+            ColorChoice::Never => 2,
+        },
+        Ordering::Relaxed,
+    )
+}
+
+/// Get color choice as set with `set_color_choice`
+pub fn color_choice() -> ColorChoice {
+    match COLOR_CHOICE.load(Ordering::Relaxed) {
+        1 => ColorChoice::Always,
+        2 => ColorChoice::Never,
+        _ => ColorChoice::Auto,
+    }
+}
+
+/// Check if colors should be used for output written to `stderr`
+pub fn use_color() -> bool {
+    decide(
+        color_choice(),
+        |name| std::env::var(name).ok(),
+        io::stderr().is_terminal(),
+    )
+}
+
+fn decide(choice: ColorChoice, env: impl Fn(&str) -> Option<String>, is_terminal: bool) -> bool {
+    match choice {
+        ColorChoice::Always => return true,
+        ColorChoice::Never => return false,
+        ColorChoice::Auto => (),
+    }
+    if env("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        return false;
+    }
+    if env("CLICOLOR_FORCE").is_some_and(|value| !value.is_empty() && value != "0") {
+        return true;
+    }
+    if env("CLICOLOR").is_some_and(|value| value == "0") {
+        return false;
+    }
+    is_terminal
+}
+
+/// Make sure that ANSI escape sequences written to `stderr` will be interpreted by the terminal
+#[cfg(not(windows))]
+fn enable_ansi_support() -> bool {
+    true
+}
+
+#[cfg(windows)]
+fn enable_ansi_support() -> bool {
+    use std::os::raw::c_void;
+
+    const STD_ERROR_HANDLE: u32 = -12i32 as u32;
+    const INVALID_HANDLE_VALUE: *mut c_void = -1isize as *mut c_void;
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x0004;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetStdHandle(std_handle: u32) -> *mut c_void;
+        fn GetConsoleMode(console: *mut c_void, mode: *mut u32) -> i32;
+        fn SetConsoleMode(console: *mut c_void, mode: u32) -> i32;
+    }
+
+    unsafe {
+        let console = GetStdHandle(STD_ERROR_HANDLE);
+        if console.is_null() || console == INVALID_HANDLE_VALUE {
+            return false;
+        }
+        let mut mode = 0;
+        if GetConsoleMode(console, &mut mode) == 0 {
+            // not a console (e.g. MSYS terminal pipe) - assume it understands ANSI escape sequences
+            return true;
+        }
+        mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0
+            || SetConsoleMode(console, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+    }
+}
+
+const BOLD_RED: &str = "\x1b[1;31m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Highlight header and section titles of fatal error report
+fn paint_report(report: &str) -> String {
+    let mut out = format!("{}Fatal error:{} ", BOLD_RED, RESET);
+    for (no, line) in report.split('\n').enumerate() {
+        if no > 0 {
+            out.push('\n');
+        }
+        if line.starts_with("--- ") {
+            out.push_str(BOLD);
+            out.push_str(line);
+            out.push_str(RESET);
+        } else {
+            out.push_str(line);
+        }
+    }
+    out
+}
+
+/// Same as `format_panic_to_stderr` but the report is colored if colors should be used (see `use_color`)
+pub fn format_panic_to_stderr_colored() {
+    panic::set_hook(Box::new(|panic_info| {
+        record_panic_exit_code(panic_info.payload());
+        let backtrace = format_panic_backtrace();
+        let report = format_panic(panic_info, backtrace);
+        if use_color() && enable_ansi_support() {
+            eprintln!("{}", paint_report(&report));
+        } else {
+            eprintln!("Fatal error: {}", report);
+        }
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'e>(vars: &'e [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'e {
+        move |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn decision() {
+        assert!(decide(ColorChoice::Auto, env(&[]), true));
+        assert!(!decide(ColorChoice::Auto, env(&[]), false));
+        assert!(!decide(ColorChoice::Auto, env(&[("NO_COLOR", "1")]), true));
+        assert!(decide(ColorChoice::Auto, env(&[("NO_COLOR", "")]), true));
+        assert!(decide(
+            ColorChoice::Auto,
+            env(&[("CLICOLOR_FORCE", "1")]),
+            false
+        ));
+        assert!(!decide(
+            ColorChoice::Auto,
+            env(&[("CLICOLOR_FORCE", "0")]),
+            false
+        ));
+        assert!(!decide(
+            ColorChoice::Auto,
+            env(&[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")]),
+            true
+        ));
+        assert!(!decide(ColorChoice::Auto, env(&[("CLICOLOR", "0")]), true));
+        assert!(decide(
+            ColorChoice::Always,
+            env(&[("NO_COLOR", "1")]),
+            false
+        ));
+        assert!(!decide(
+            ColorChoice::Never,
+            env(&[("CLICOLOR_FORCE", "1")]),
+            true
+        ));
+    }
+
+    #[test]
+    fn paint() {
+        assert_eq!(
+            paint_report("boom!\n--- Panicked\n   0: main"),
+            "\x1b[1;31mFatal error:\x1b[0m boom!\n\x1b[1m--- Panicked\x1b[0m\n   0: main"
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! Fixed layout binary encoding of `Problem`s for constrained targets.
+//!
+//! `Problem::encode_compact` writes small record suitable for forwarding errors over MQTT or serial links where JSON or
+//! `wire` encoding is too heavy. The record has fixed layout (integers are little endian):
+//!
+//! | offset | size | field |
+//! |--------|------|-------|
+//! | 0 | 1 | `COMPACT_VERSION` |
+//! | 1 | 4 | `message_hash` of error message |
+//! | 5 | 4 | exit code (see `Problem::with_exit_code`) or 0 |
+//! | 9 | 1 | length of context text |
+//! | 10 | length | context text (see `Problem::context_string`) truncated to fit the buffer |
+//!
+//! Receivers can map message hashes back to messages with table built with `message_hash`.
+//!
+//! ```rust
+//! use problem::prelude::*;
+//! use problem::compact::{message_hash, CompactRecord};
+//!
+//! let problem = Problem::from_error("sensor timeout").problem_while("reading temperature").with_exit_code(7);
+//!
+//! let mut buf = [0; 24];
+//! let len = problem.encode_compact(&mut buf).unwrap();
+//!
+//! let record = CompactRecord::decode(&buf[..len]).unwrap();
+//! assert_eq!(record.message_hash, message_hash("sensor timeout"));
+//! assert_eq!(record.code, 7);
+//! assert_eq!(record.context, "while reading ");
+//! ```
+use super::Problem;
+
+/// Version of the layout written by `Problem::encode_compact`
+pub const COMPACT_VERSION: u8 = 1;
+
+/// Length of the record without context text; smallest buffer `Problem::encode_compact` can write to
+pub const COMPACT_HEADER_LEN: usize = 10;
+
+/// Hash of error message as written by `Problem::encode_compact` (32 bit FNV-1a)
+pub fn message_hash(message: &str) -> u32 {
+    message.bytes().fold(0x811c_9dc5, |hash, byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
+/// Record decoded from bytes written by `Problem::encode_compact`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactRecord<'b> {
+    /// Hash of error message (see `message_hash`)
+    pub message_hash: u32,
+    /// Exit code or 0 if not set
+    pub code: i32,
+    /// Possibly truncated context messages
+    pub context: &'b str,
+}
+
+impl<'b> CompactRecord<'b> {
+    /// Decode record; returns `None` if bytes are not valid record of `COMPACT_VERSION`
+    pub fn decode(bytes: &'b [u8]) -> Option<CompactRecord<'b>> {
+        if bytes.len() < COMPACT_HEADER_LEN {
+            return None;
+        }
+        let (header, rest) = bytes.split_at(COMPACT_HEADER_LEN);
+        if header[0] != COMPACT_VERSION {
+            return None;
+        }
+        let context = rest.get(..usize::from(header[9]))?;
+        Some(CompactRecord {
+            message_hash: u32::from_le_bytes([header[1], header[2], header[3], header[4]]),
+            code: i32::from_le_bytes([header[5], header[6], header[7], header[8]]),
+            context: std::str::from_utf8(context).ok()?,
+        })
+    }
+}
+
+/// Longest prefix of given text not longer than `max` bytes that ends at character boundary
+fn truncate(text: &str, max: usize) -> &str {
+    if text.len() <= max {
+        return text;
+    }
+    let end = (0..=max)
+        .rev()
+        .find(|&end| text.is_char_boundary(end))
+        .unwrap_or(0);
+    &text[..end]
+}
+
+impl Problem {
+    /// Encode this `Problem` into given buffer in fixed layout (see module documentation)
+    ///
+    /// Returns number of bytes written or `None` if the buffer is shorter than `COMPACT_HEADER_LEN`. Context text is truncated to
+    /// fit the buffer and 255 bytes.
+    pub fn encode_compact(&self, buf: &mut [u8]) -> Option<usize> {
+        if buf.len() < COMPACT_HEADER_LEN {
+            return None;
+        }
+        let (header, rest) = buf.split_at_mut(COMPACT_HEADER_LEN);
+        let context = self.context_string();
+        let context = truncate(&context, rest.len().min(u8::MAX.into()));
+
+        header[0] = COMPACT_VERSION;
+        header[1..5].copy_from_slice(&message_hash(&self.error.to_string()).to_le_bytes());
+        header[5..9].copy_from_slice(&self.exit_code().unwrap_or(0).to_le_bytes());
+        header[9] = context.len() as u8;
+        rest[..context.len()].copy_from_slice(context.as_bytes());
+
+        Some(COMPACT_HEADER_LEN + context.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn round_trip() {
+        let problem = Problem::from_error("boom!")
+            .problem_while("parsing")
+            .with_exit_code(-2);
+
+        let mut buf = [0xff; 64];
+        let len = problem.encode_compact(&mut buf).unwrap();
+        assert_eq!(len, COMPACT_HEADER_LEN + "while parsing".len());
+
+        let record = CompactRecord::decode(&buf[..len]).unwrap();
+        assert_eq!(
+            record,
+            CompactRecord {
+                message_hash: message_hash("boom!"),
+                code: -2,
+                context: "while parsing",
+            }
+        );
+
+        assert_eq!(
+            Problem::from_error("boom!").encode_compact(&mut buf),
+            Some(10)
+        );
+        assert_eq!(CompactRecord::decode(&buf[..10]).unwrap().context, "");
+        assert_eq!(problem.encode_compact(&mut buf[..9]), None);
+        assert_eq!(CompactRecord::decode(&buf[..9]), None);
+    }
+
+    #[test]
+    fn truncated() {
+        let problem = Problem::from_error("boom!").problem_while("zapisywanie żółwia");
+        let mut buf = [0; COMPACT_HEADER_LEN + 19];
+        let len = problem.encode_compact(&mut buf).unwrap();
+        // `ż` does not fit
+        assert_eq!(
+            CompactRecord::decode(&buf[..len]).unwrap().context,
+            "while zapisywanie "
+        );
+    }
+
+    #[test]
+    fn hash() {
+        assert_eq!(message_hash(""), 0x811c_9dc5);
+        assert_eq!(message_hash("a"), 0xe40c_292c);
+    }
+}
@@ -0,0 +1,163 @@
+//! Scoped threads (see `crossbeam-utils` crate) reporting their failures as `Problems`.
+//!
+//! Threads are spawned with `ProblemScope::spawn` inside `scope` and are all joined before `scope` returns. Each thread
+//! has a name; `Err` results and panics of the threads are collected into `Problems` with `while running thread '<name>'`
+//! context. Panics are converted as with `spawn_compute`.
+//!
+//! ```rust
+//! use problem::prelude::*;
+//! use problem::crossbeam::scope;
+//!
+//! let chunks = vec![vec![1u8, 2], vec![], vec![3]];
+//!
+//! let problems = scope(|scope| {
+//!     for (no, chunk) in chunks.iter().enumerate() {
+//!         scope.spawn(format!("chunk-{}", no), move || {
+//!             if chunk.is_empty() {
+//!                 return Err(Problem::from_error("empty chunk"));
+//!             }
+//!             Ok(())
+//!         });
+//!     }
+//! }).unwrap_err();
+//!
+//! assert_eq!(problems.to_string(), "1 problem occurred: while running thread 'chunk-1' got error caused by: empty chunk");
+//! ```
+use super::catch::run_compute;
+use super::{Problem, ProblemError, ProblemWhile, Problems, ToProblem};
+use crossbeam_utils::thread::Scope;
+use std::cell::Cell;
+use std::panic;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Failures of threads with their spawn order and names
+type Failures = Arc<Mutex<Vec<(usize, String, ProblemError)>>>;
+
+/// Scope for spawning threads reporting their failures; see `scope`
+pub struct ProblemScope<'scope, 'env: 'scope> {
+    scope: &'scope Scope<'env>,
+    failures: Failures,
+    spawned: Cell<usize>,
+}
+
+impl<'env> ProblemScope<'_, 'env> {
+    /// Spawn named thread running given closure
+    ///
+    /// `Err` returned by the closure or its panic is reported by `scope` when all threads are joined.
+    pub fn spawn<F, E>(&self, name: impl Into<String>, body: F)
+    where
+        F: FnOnce() -> Result<(), E> + Send + 'env,
+        E: ToProblem,
+    {
+        let name = name.into();
+        let no = self.spawned.get();
+        self.spawned.set(no + 1);
+
+        let failures = self.failures.clone();
+        let thread_name = name.clone();
+        let spawned = self.scope.builder().name(name.clone()).spawn(move |_| {
+            if let Err(problem) = run_compute(body) {
+                lock(&failures).push((no, thread_name, problem));
+            }
+        });
+
+        if let Err(error) = spawned {
+            let problem = Problem::from_error(error).problem_while("spawning thread");
+            lock(&self.failures).push((no, name, problem.into()));
+        }
+    }
+}
+
+fn lock(failures: &Failures) -> MutexGuard<'_, Vec<(usize, String, ProblemError)>> {
+    failures
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Run closure that can spawn threads with `ProblemScope::spawn` and join them
+///
+/// Returns result of the closure if all threads succeeded or `Problems` with failures of the threads in order they were spawned.
+pub fn scope<'env, F, R>(body: F) -> Result<R, Problems>
+where
+    F: FnOnce(&ProblemScope<'_, 'env>) -> R,
+{
+    let failures = Failures::default();
+    let result = crossbeam_utils::thread::scope(|scope| {
+        body(&ProblemScope {
+            scope,
+            failures: failures.clone(),
+            spawned: Cell::new(0),
+        })
+    });
+    // threads catch their panics so this can only be propagated panic of the closure
+    let result = result.unwrap_or_else(|payload| panic::resume_unwind(payload));
+
+    let mut failures = std::mem::take(&mut *lock(&failures));
+    if failures.is_empty() {
+        return Ok(result);
+    }
+    failures.sort_by_key(|(no, _, _)| *no);
+    Err(failures
+        .into_iter()
+        .map(|(_, name, problem)| {
+            problem
+                .into_problem()
+                .problem_while(format!("running thread '{}'", name))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::in_context_of;
+
+    #[test]
+    fn succeeded() {
+        let mut sums = vec![0u32; 3];
+        let total = scope(|scope| {
+            for (no, sum) in sums.iter_mut().enumerate() {
+                scope.spawn(format!("sum-{}", no), move || {
+                    *sum = (0..=no as u32).sum();
+                    Ok::<_, Problem>(())
+                });
+            }
+            3
+        })
+        .unwrap();
+        assert_eq!(total, 3);
+        assert_eq!(sums, [0, 1, 3]);
+    }
+
+    #[test]
+    fn failed() {
+        crate::set_context_stack(true);
+        let problems = scope(|scope| {
+            scope.spawn("parser", || {
+                in_context_of("parsing header", || -> Result<(), Problem> {
+                    panic!("boom!")
+                })
+            });
+            scope.spawn("writer", || Ok::<_, Problem>(()));
+            scope.spawn("reader", || Err("connection reset"));
+        })
+        .unwrap_err();
+
+        // `Problem`s are created on the spawned threads which may capture backtraces
+        let problems = problems.into_iter().collect::<Vec<_>>();
+        assert_eq!(problems.len(), 2);
+        assert_eq!(
+            problems[0].contexts().collect::<Vec<_>>(),
+            ["parsing header", "running thread 'parser'"]
+        );
+        assert_eq!(problems[0].causes().last().unwrap().to_string(), "boom!");
+        assert_eq!(
+            problems[1].contexts().collect::<Vec<_>>(),
+            ["running thread 'reader'"]
+        );
+        assert_eq!(
+            problems[1].causes().last().unwrap().to_string(),
+            "connection reset"
+        );
+    }
+}
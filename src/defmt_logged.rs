@@ -0,0 +1,82 @@
+//! Logging of `Problem`s with `defmt` for embedded targets.
+//!
+//! `Problem` implements `defmt::Format` displaying context messages and error message (as with `Problem::display_short`);
+//! backtraces are never included. `ok_or_defmt_warn` and `ok_or_defmt_error` log `Problem` with `defmt::warn!` and
+//! `defmt::error!` macros on `Err` and continue, like `ok_or_log_warn` and `ok_or_log_error` do with `log` crate.
+//!
+//! ```rust,ignore
+//! use problem::prelude::*;
+//!
+//! // Logs over RTT: WARN  while reading config got error caused by: invalid digit found in string
+//! let port = "80x".parse::<u16>().problem_while("reading config").ok_or_defmt_warn().unwrap_or(80);
+//! ```
+use super::sink::{OkOrReport, OkOrReportIter, ProblemSink, Severity};
+use super::{Problem, ToProblem};
+
+/// Displays context messages and error message
+impl defmt::Format for Problem {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{}", defmt::Display2Format(&self.display_short()))
+    }
+}
+
+/// Logs `Problem`s with `defmt::warn!` and `defmt::error!` macros
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefmtSink;
+
+impl ProblemSink for DefmtSink {
+    fn report(&mut self, severity: Severity, problem: &Problem) {
+        match severity {
+            Severity::Warning => defmt::warn!("{}", problem),
+            Severity::Error => defmt::error!("{}", problem),
+        }
+    }
+
+    fn panics_in_strict_mode(&self) -> bool {
+        true
+    }
+}
+
+/// Extension of `Result` that allows program to log `Problem` with `defmt` on `Err` and continue
+pub trait OkOrDefmt<O> {
+    fn ok_or_defmt_warn(self) -> Option<O>;
+    fn ok_or_defmt_error(self) -> Option<O>;
+}
+
+impl<O, E> OkOrDefmt<O> for Result<O, E>
+where
+    E: ToProblem,
+{
+    fn ok_or_defmt_warn(self) -> Option<O> {
+        self.ok_or_report(DefmtSink, Severity::Warning)
+    }
+
+    fn ok_or_defmt_error(self) -> Option<O> {
+        self.ok_or_report(DefmtSink, Severity::Error)
+    }
+}
+
+reporting_iter! {
+    /// Iterator that will log `Problem` with `defmt` on `Err` and skip to next item; it can be flattened to skip failed items
+    pub struct ProblemDefmtIter<I>(DefmtSink);
+}
+
+/// Convert `Iterator` of `Result<O, E>` to iterator of `Option<O>` and log any `Err` variants with `defmt`
+pub trait OkOrDefmtIter<O, E>: Sized {
+    fn ok_or_defmt_warn(self) -> ProblemDefmtIter<Self>;
+    fn ok_or_defmt_error(self) -> ProblemDefmtIter<Self>;
+}
+
+impl<I, O, E> OkOrDefmtIter<O, E> for I
+where
+    I: Iterator<Item = Result<O, E>>,
+    E: ToProblem,
+{
+    fn ok_or_defmt_warn(self) -> ProblemDefmtIter<Self> {
+        ProblemDefmtIter(self.ok_or_report(DefmtSink, Severity::Warning))
+    }
+
+    fn ok_or_defmt_error(self) -> ProblemDefmtIter<Self> {
+        ProblemDefmtIter(self.ok_or_report(DefmtSink, Severity::Error))
+    }
+}
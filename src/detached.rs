@@ -0,0 +1,283 @@
+//! `Send` and `Sync` snapshot of `Problem`.
+//!
+//! `Problem` keeps original error which may not be `Send` or `Sync` and lazily computed sections, so it cannot be passed
+//! between threads or stored in `io::Error`. `ProblemError` keeps the same information as text (error and its causes
+//! as with `Problem::from_dyn_error_static`, sections with computed bodies) and converts back to equivalent `Problem`.
+//!
+//! `Problem` converted to `io::Error` embeds `ProblemError` so that libraries working with `io::Error` (e.g. `Read` and `Write`
+//! implementations) can pass it through. When such `io::Error` is converted back to `Problem` the original `Problem` is
+//! restored instead of being nested in a new one:
+//!
+//! ```rust
+//! use problem::prelude::*;
+//! use std::io;
+//!
+//! fn read_config() -> io::Result<String> {
+//!     Err(Problem::from_error("unexpected token").problem_while("parsing config").into())
+//! }
+//!
+//! let problem = read_config().problem_while("starting up").unwrap_err();
+//! assert_eq!(problem.to_string(), "while starting up, while parsing config got error caused by: unexpected token");
+//! ```
+use super::{
+    detach_error, Details, Problem, ProblemKind, Section, SectionVisibility, StoredBacktrace,
+};
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::time::{Duration, Instant};
+
+/// `Problem` with all its information kept in `Send` and `Sync` form; see module documentation
+#[derive(Debug)]
+pub struct ProblemError {
+    message: String,
+    error: Box<dyn Error + Send + Sync>,
+    context: Vec<String>,
+    backtrace: Option<String>,
+    details: Option<Box<DetachedDetails>>,
+}
+
+/// `Details` of `Problem` with section bodies computed
+///
+/// This is the only place `Details` are converted from and to, so that `ProblemError` and encodings of `Problem` (see `wire`
+/// and `serialized` modules) carry all of them.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DetachedDetails {
+    pub(crate) url: Option<String>,
+    pub(crate) status: Option<u16>,
+    pub(crate) exit_code: Option<i32>,
+    pub(crate) sections: Vec<(String, String, SectionVisibility)>,
+    pub(crate) dropped_contexts: usize,
+    pub(crate) started: Option<Instant>,
+    pub(crate) context_times: Vec<Option<Duration>>,
+    pub(crate) trace_id: Option<String>,
+    pub(crate) tags: Vec<String>,
+    pub(crate) kind: Option<ProblemKind>,
+}
+
+impl DetachedDetails {
+    /// Copy details of given `Problem`; lazily computed sections are computed
+    pub(crate) fn of(problem: &Problem) -> DetachedDetails {
+        let details = match problem.details.as_deref() {
+            Some(details) => details,
+            None => return DetachedDetails::default(),
+        };
+        DetachedDetails {
+            url: details.url.clone(),
+            status: details.status,
+            exit_code: details.exit_code,
+            sections: details
+                .sections
+                .iter()
+                .map(|section| {
+                    (
+                        section.title.clone(),
+                        section.body().to_owned(),
+                        section.visibility,
+                    )
+                })
+                .collect(),
+            dropped_contexts: details.dropped_contexts,
+            started: details.started,
+            context_times: details.context_times.clone(),
+            trace_id: details.trace_id.clone(),
+            tags: details.tags.clone(),
+            kind: details.kind,
+        }
+    }
+
+    /// Set as details of given `Problem` keeping count of context messages it already dropped (see `Problem::from_parts`)
+    pub(crate) fn attach_to(mut self, problem: &mut Problem) {
+        self.dropped_contexts += problem
+            .details
+            .as_ref()
+            .map_or(0, |details| details.dropped_contexts);
+        problem.details = self.into_details();
+    }
+
+    /// Convert to `Details` of `Problem`; `None` if no details are set
+    pub(crate) fn into_details(self) -> Option<Box<Details>> {
+        let empty = self.url.is_none()
+            && self.status.is_none()
+            && self.exit_code.is_none()
+            && self.sections.is_empty()
+            && self.dropped_contexts == 0
+            && self.started.is_none()
+            && self.context_times.is_empty()
+            && self.trace_id.is_none()
+            && self.tags.is_empty()
+            && self.kind.is_none();
+        if empty {
+            return None;
+        }
+        Some(Box::new(Details {
+            url: self.url,
+            status: self.status,
+            exit_code: self.exit_code,
+            sections: self
+                .sections
+                .into_iter()
+                .map(|(title, body, visibility)| Section {
+                    visibility,
+                    ..Section::new(title, body)
+                })
+                .collect(),
+            dropped_contexts: self.dropped_contexts,
+            started: self.started,
+            context_times: self.context_times,
+            trace_id: self.trace_id,
+            tags: self.tags,
+            kind: self.kind,
+            // error is detached as messages only
+            error_type: None,
+        }))
+    }
+}
+
+impl ProblemError {
+    /// Convert copy of this `ProblemError` to `Problem`
+    pub(crate) fn to_problem(&self) -> Problem {
+        ProblemError {
+            message: self.message.clone(),
+            error: detach_error(self.error.as_ref()),
+            context: self.context.clone(),
+            backtrace: self.backtrace.clone(),
+            details: self.details.clone(),
+        }
+        .into_problem()
+    }
+
+    /// Convert back to `Problem`
+    pub fn into_problem(self) -> Problem {
+        let mut problem =
+            Problem::from_parts(self.error as Box<dyn Error>, self.context, self.backtrace);
+        if let Some(details) = self.details {
+            details.attach_to(&mut problem);
+        }
+        problem
+    }
+}
+
+impl ProblemError {
+    fn detach(problem: &Problem, backtrace: Option<String>) -> ProblemError {
+        ProblemError {
+            message: problem.to_string(),
+            error: detach_error(problem.error.as_ref()),
+            context: problem.context.clone(),
+            backtrace,
+            details: problem
+                .details
+                .as_ref()
+                .map(|_| Box::new(DetachedDetails::of(problem))),
+        }
+    }
+}
+
+impl From<Problem> for ProblemError {
+    fn from(mut problem: Problem) -> ProblemError {
+        let backtrace = problem.backtrace.take().map(StoredBacktrace::into_string);
+        ProblemError::detach(&problem, backtrace)
+    }
+}
+
+/// Copies information of the `Problem`; lazily computed sections are computed
+impl From<&Problem> for ProblemError {
+    fn from(problem: &Problem) -> ProblemError {
+        ProblemError::detach(
+            problem,
+            problem.backtrace().map(|backtrace| backtrace.to_owned()),
+        )
+    }
+}
+
+/// Displays the same message as the `Problem` it was created from
+impl fmt::Display for ProblemError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Error causes are already part of the message so no source is provided
+impl Error for ProblemError {}
+
+/// `io::Error` of the same kind as `io::Error` found in the error cause chain or `io::ErrorKind::Other`
+impl From<Problem> for io::Error {
+    fn from(problem: Problem) -> io::Error {
+        let kind = problem.io_kind().unwrap_or(io::ErrorKind::Other);
+        io::Error::new(kind, ProblemError::from(problem))
+    }
+}
+
+/// Get back `Problem` if given error is `ProblemError` or `io::Error` wrapping it
+pub(crate) fn restore_problem(error: Box<dyn Error>) -> Result<Problem, Box<dyn Error>> {
+    let error = match error.downcast::<ProblemError>() {
+        Ok(problem) => return Ok(problem.into_problem()),
+        Err(error) => error,
+    };
+    let error = error.downcast::<io::Error>()?;
+    if !error
+        .get_ref()
+        .is_some_and(|inner| inner.is::<ProblemError>())
+    {
+        return Err(error);
+    }
+    let inner = error.into_inner().expect("io::Error with inner error");
+    Ok(inner
+        .downcast::<ProblemError>()
+        .expect("inner error is ProblemError")
+        .into_problem())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use std::thread;
+
+    #[test]
+    fn send_between_threads() {
+        let problem = Problem::from_error("boom!")
+            .problem_while("processing job 42")
+            .with_url("https://docs.example.com/errors/E42")
+            .with_exit_code(3)
+            .with_tag("jobs")
+            .with_section_lazy("Job", || "id=42");
+        let message = problem.to_string();
+
+        let problem = ProblemError::from(problem);
+        let problem = thread::spawn(move || problem)
+            .join()
+            .unwrap()
+            .into_problem();
+
+        assert_eq!(problem.to_string(), message);
+        assert_eq!(problem.exit_code(), Some(3));
+        assert!(problem.has_tag("jobs"));
+        assert_eq!(problem.sections().collect::<Vec<_>>(), [("Job", "id=42")]);
+    }
+
+    #[test]
+    fn io_error_round_trip() {
+        crate::disable_backtraces();
+        let problem = Problem::from_error(io::Error::new(io::ErrorKind::NotFound, "config.toml"))
+            .problem_while("loading config");
+        let message = problem.to_string();
+
+        let error = io::Error::from(problem);
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        assert_eq!(error.to_string(), message);
+
+        let problem = Problem::from(error);
+        assert_eq!(problem.to_string(), message);
+        assert_eq!(problem.contexts().collect::<Vec<_>>(), ["loading config"]);
+        assert!(problem.is_not_found());
+
+        let error = io::Error::from(Problem::from_error("boom!"));
+        assert_eq!(error.kind(), io::ErrorKind::Other);
+        let error: Box<dyn Error> = Box::new(error);
+        assert_eq!(Problem::from_error(error).to_string(), "boom!");
+
+        let problem = Problem::from_error(io::Error::other("boom!"));
+        assert!(problem.find_cause::<io::Error>().is_some());
+    }
+}
@@ -0,0 +1,665 @@
+//! Formatting of backtrace frames.
+use backtrace::SymbolName;
+use std::borrow::Cow;
+use std::ffi::c_void;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Mutex;
+
+/// Style of rendered backtraces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BacktraceStyle {
+    /// Application frames only, one line per frame, e.g. `12: myapp::loader::load at src/loader.rs:88`
+    Short,
+    /// All frames with symbol name and location on separate lines
+    Full,
+}
+
+static BACKTRACE_STYLE: AtomicU8 = AtomicU8::new(0);
+
+/// Set style of rendered backtraces
+///
+/// By default `RUST_BACKTRACE=1` renders backtraces in `BacktraceStyle::Short` style and `RUST_BACKTRACE=full`
+/// in `BacktraceStyle::Full` style. Note that backtraces are still only collected if `RUST_BACKTRACE` is set.
+pub fn set_backtrace_style(style: BacktraceStyle) {
+    BACKTRACE_STYLE.store(
+        match style {
+            BacktraceStyle::Short => 1,
+            BacktraceStyle::Full => 2,
+        },
+        Ordering::Relaxed,
+    )
+}
+
+/// Style selected with `RUST_BACKTRACE` environment variable: 0 - not read yet, 1 - disabled, 2 - short, 3 - full
+static ENV_STYLE: AtomicU8 = AtomicU8::new(0);
+
+/// Re-read `RUST_BACKTRACE` environment variable
+///
+/// The variable is read once, when the first `Problem` is created, so that error-heavy workloads don't pay for the lookup.
+/// Call this function after changing the variable (e.g. with `std::env::set_var`) for the change to take effect.
+pub fn refresh_backtrace_policy() {
+    ENV_STYLE.store(0, Ordering::Relaxed)
+}
+
+fn env_style() -> Option<BacktraceStyle> {
+    let mut style = ENV_STYLE.load(Ordering::Relaxed);
+    if style == 0 {
+        style = match std::env::var("RUST_BACKTRACE").as_ref().map(String::as_str) {
+            Ok("1") => 2,
+            Ok("full") => 3,
+            _ => 1,
+        };
+        ENV_STYLE.store(style, Ordering::Relaxed);
+    }
+    match style {
+        2 => Some(BacktraceStyle::Short),
+        3 => Some(BacktraceStyle::Full),
+        _ => None,
+    }
+}
+
+/// Get style in which backtraces should be rendered or `None` if backtraces are not enabled
+pub(crate) fn backtrace_style() -> Option<BacktraceStyle> {
+    let style = env_style()?;
+    Some(match BACKTRACE_STYLE.load(Ordering::Relaxed) {
+        1 => BacktraceStyle::Short,
+        2 => BacktraceStyle::Full,
+        _ => style,
+    })
+}
+
+/// How backtraces of `Problem`s are captured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BacktraceCapture {
+    /// Frames are found with unwind tables and resolved to symbols when `Problem` is created (default)
+    Resolved,
+    /// Only return addresses of frames are recorded by following frame pointers; they are resolved to symbols when
+    /// the backtrace is displayed for the first time
+    ///
+    /// This is much cheaper than `BacktraceCapture::Resolved` but frames of functions compiled without frame pointers
+    /// are missing, so build with `-C force-frame-pointers=yes` for complete backtraces. Falls back to
+    /// `BacktraceCapture::Resolved` on platforms where frame pointers can't be followed (other than Linux on x86_64 or
+    /// aarch64).
+    FramePointers,
+}
+
+static BACKTRACE_CAPTURE: AtomicU8 = AtomicU8::new(0);
+
+/// Set how backtraces of `Problem`s are captured (default `BacktraceCapture::Resolved`)
+///
+/// Note that backtraces are still only captured if `RUST_BACKTRACE` is set.
+pub fn set_backtrace_capture(capture: BacktraceCapture) {
+    BACKTRACE_CAPTURE.store(
+        match capture {
+            BacktraceCapture::Resolved => 0,
+            BacktraceCapture::FramePointers => 1,
+        },
+        Ordering::Relaxed,
+    )
+}
+
+pub(crate) fn backtrace_capture() -> BacktraceCapture {
+    match BACKTRACE_CAPTURE.load(Ordering::Relaxed) {
+        1 => BacktraceCapture::FramePointers,
+        _ => BacktraceCapture::Resolved,
+    }
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64")
+))]
+mod frame_pointers {
+    use std::arch::asm;
+    use std::cell::Cell;
+    use std::mem::{size_of, MaybeUninit};
+
+    /// Maximum number of frames recorded with `BacktraceCapture::FramePointers`
+    pub(crate) const MAX_FRAMES: usize = 256;
+
+    thread_local! {
+        /// Upper bound of stack of the current thread
+        static STACK_END: Cell<Option<usize>> = const { Cell::new(None) };
+    }
+
+    fn stack_end() -> Option<usize> {
+        if let Some(end) = STACK_END.with(Cell::get) {
+            return Some(end);
+        }
+        let end = unsafe {
+            let mut attr = MaybeUninit::<libc::pthread_attr_t>::uninit();
+            if libc::pthread_getattr_np(libc::pthread_self(), attr.as_mut_ptr()) != 0 {
+                return None;
+            }
+            let mut attr = attr.assume_init();
+            let mut addr = std::ptr::null_mut();
+            let mut size = 0;
+            let ret = libc::pthread_attr_getstack(&attr, &mut addr, &mut size);
+            libc::pthread_attr_destroy(&mut attr);
+            if ret != 0 {
+                return None;
+            }
+            addr as usize + size
+        };
+        STACK_END.with(|cell| cell.set(Some(end)));
+        Some(end)
+    }
+
+    /// Record return addresses by following frame pointers starting from the caller of this function
+    #[inline(never)]
+    pub(crate) fn capture() -> Option<Vec<usize>> {
+        let end = stack_end()?;
+        let fp: usize;
+        unsafe {
+            #[cfg(target_arch = "x86_64")]
+            asm!("mov {}, rbp", out(reg) fp, options(nomem, nostack, preserves_flags));
+            #[cfg(target_arch = "aarch64")]
+            asm!("mov {}, x29", out(reg) fp, options(nomem, nostack, preserves_flags));
+        }
+
+        // frame record: previous frame pointer followed by return address
+        let record = 2 * size_of::<usize>();
+        let mut start = &end as *const usize as usize;
+        let mut fp = fp;
+        let mut frames = Vec::new();
+        // Note: frame pointer may be garbage if code was compiled without frame pointers so it is only followed within the
+        // stack of the current thread and towards its end
+        while frames.len() < MAX_FRAMES
+            && fp >= start
+            && fp % size_of::<usize>() == 0
+            && fp + record <= end
+        {
+            let (next, ip) = unsafe {
+                let record = fp as *const usize;
+                (*record, *record.add(1))
+            };
+            if ip == 0 {
+                break;
+            }
+            frames.push(ip);
+            start = fp + record;
+            fp = next;
+        }
+        Some(frames)
+    }
+}
+
+/// Record return addresses of frames of the current thread stack by following frame pointers
+///
+/// Returns `None` if not supported on this platform.
+#[inline(always)]
+pub(crate) fn capture_frame_pointers() -> Option<Vec<usize>> {
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    return frame_pointers::capture();
+    #[cfg(not(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    )))]
+    return None;
+}
+
+/// Resolve return addresses recorded with `capture_frame_pointers` to backtrace text
+pub(crate) fn resolve_frames(frames: &[usize]) -> String {
+    let mut writer = BacktraceWriter::new(backtrace_style().unwrap_or(BacktraceStyle::Short));
+    for &ip in frames {
+        if !writer.write_frame(ip as *mut c_void) {
+            break;
+        }
+    }
+    writer.finish()
+}
+
+/// Note appended to backtraces with frames omitted in `BacktraceStyle::Short` style (same as used by `std`)
+const SHORT_BACKTRACE_NOTE: &str =
+    "note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.";
+
+/// Kind of backtrace frame used to trim backtraces in `BacktraceStyle::Short` style like `std` does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum FrameKind {
+    /// Frame of backtrace capturing or `Problem` construction
+    Internal,
+    Application,
+    /// Frames up to and including this frame are omitted (e.g. panic machinery)
+    EndShort,
+    /// This and following frames are omitted (e.g. runtime startup)
+    BeginShort,
+}
+
+impl FrameKind {
+    fn of(name: &str) -> FrameKind {
+        if name.contains("__rust_begin_short_backtrace") {
+            FrameKind::BeginShort
+        } else if name.contains("__rust_end_short_backtrace") {
+            FrameKind::EndShort
+        } else if name.starts_with("backtrace::")
+            || name.starts_with("problem::")
+            || name.starts_with("<problem::")
+            || (name.starts_with('<') && name.contains(" as problem::"))
+        {
+            FrameKind::Internal
+        } else {
+            FrameKind::Application
+        }
+    }
+}
+
+/// Writes frames of a backtrace; in `BacktraceStyle::Short` style only application frames are written
+pub(crate) struct BacktraceWriter {
+    formatter: FrameFormatter,
+    backtrace: String,
+    frame_no: u32,
+    /// Still skipping internal frames at the top of the backtrace
+    leading: bool,
+    trimmed: bool,
+}
+
+impl BacktraceWriter {
+    pub(crate) fn new(style: BacktraceStyle) -> BacktraceWriter {
+        BacktraceWriter {
+            formatter: FrameFormatter::new(style),
+            backtrace: String::new(),
+            frame_no: 0,
+            leading: true,
+            trimmed: false,
+        }
+    }
+
+    /// Write frame with given instruction pointer; returns `false` if following frames should not be written
+    pub(crate) fn write_frame(&mut self, ip: *mut c_void) -> bool {
+        let mut frame = String::new();
+        let kind = self.formatter.write_frame(&mut frame, self.frame_no, ip);
+
+        if self.formatter.style == BacktraceStyle::Short {
+            match kind {
+                FrameKind::BeginShort => {
+                    self.trimmed = true;
+                    return false;
+                }
+                FrameKind::EndShort => {
+                    self.backtrace.clear();
+                    self.frame_no = 0;
+                    self.leading = false;
+                    self.trimmed = true;
+                    return true;
+                }
+                FrameKind::Internal if self.leading => {
+                    self.trimmed = true;
+                    return true;
+                }
+                _ => self.leading = false,
+            }
+        }
+
+        if !self.backtrace.is_empty() {
+            self.backtrace.push('\n');
+        }
+        self.backtrace.push_str(&frame);
+        self.frame_no += 1;
+        true
+    }
+
+    pub(crate) fn finish(mut self) -> String {
+        if self.trimmed {
+            if !self.backtrace.is_empty() {
+                self.backtrace.push('\n');
+            }
+            self.backtrace.push_str(SHORT_BACKTRACE_NOTE);
+        }
+        self.backtrace
+    }
+}
+
+static KEEP_FULL_PATHS: AtomicBool = AtomicBool::new(false);
+
+/// Set whether file paths of backtrace frames are shortened (default `true`)
+///
+/// Shortened paths of dependencies start with crate name and version (e.g. `serde-1.0.100/src/de/mod.rs`) instead of full
+/// path to cargo registry and paths within current directory are relative to it (e.g. `src/lib.rs`).
+pub fn set_shorten_backtrace_paths(enabled: bool) {
+    KEEP_FULL_PATHS.store(!enabled, Ordering::Relaxed)
+}
+
+static RAW_SYMBOLS: AtomicBool = AtomicBool::new(false);
+
+/// Set whether symbol names of backtrace frames are shown raw as mangled by compiler (default `false`)
+///
+/// By default symbol names are demangled and without hash suffix (e.g. `problem::Problem::from_error` instead of
+/// `problem::Problem::from_error::hfdbc5afef77017de`). Raw symbols can also be enabled with `PROBLEM_RAW_SYMBOLS=1`
+/// environment variable.
+pub fn set_raw_backtrace_symbols(enabled: bool) {
+    RAW_SYMBOLS.store(enabled, Ordering::Relaxed)
+}
+
+static PATH_MAP: Mutex<Vec<(PathBuf, PathBuf)>> = Mutex::new(Vec::new());
+
+/// Map file paths of backtrace frames starting with `from` prefix to start with `to` prefix instead
+///
+/// This is useful for binaries built in CI so that their reports point at paths that exist on developer machines.
+/// Mappings can also be provided with `PROBLEM_PATH_MAP` environment variable as `;` separated list of `from=>to` pairs
+/// (e.g. `PROBLEM_PATH_MAP=/build/src=>src`); mappings added with this function take precedence.
+///
+/// ```rust
+/// problem::add_backtrace_path_map("/build/src", "src");
+/// ```
+pub fn add_backtrace_path_map(from: impl Into<PathBuf>, to: impl Into<PathBuf>) {
+    PATH_MAP
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .push((from.into(), to.into()))
+}
+
+fn parse_path_map(value: &str) -> impl Iterator<Item = (PathBuf, PathBuf)> + '_ {
+    value.split(';').filter_map(|mapping| {
+        let (from, to) = mapping.split_once("=>")?;
+        Some((PathBuf::from(from.trim()), PathBuf::from(to.trim())))
+    })
+}
+
+fn map_path<'p>(path: &'p Path, path_map: &[(PathBuf, PathBuf)]) -> Cow<'p, Path> {
+    path_map
+        .iter()
+        .find_map(|(from, to)| path.strip_prefix(from).ok().map(|rest| to.join(rest)))
+        .map_or(Cow::Borrowed(path), Cow::Owned)
+}
+
+/// Formats symbol names and file paths of frames of single backtrace
+pub(crate) struct FrameFormatter {
+    style: BacktraceStyle,
+    raw_symbols: bool,
+    shorten: bool,
+    workspace: Option<PathBuf>,
+    path_map: Vec<(PathBuf, PathBuf)>,
+}
+
+impl FrameFormatter {
+    pub(crate) fn new(style: BacktraceStyle) -> FrameFormatter {
+        let shorten = !KEEP_FULL_PATHS.load(Ordering::Relaxed);
+        FrameFormatter {
+            style,
+            raw_symbols: RAW_SYMBOLS.load(Ordering::Relaxed)
+                || std::env::var("PROBLEM_RAW_SYMBOLS").is_ok_and(|value| value == "1"),
+            shorten,
+            workspace: std::env::current_dir().ok().filter(|_| shorten),
+            path_map: PATH_MAP
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .iter()
+                .cloned()
+                .chain(
+                    std::env::var("PROBLEM_PATH_MAP")
+                        .ok()
+                        .iter()
+                        .flat_map(|value| parse_path_map(value)),
+                )
+                .collect(),
+        }
+    }
+
+    /// Write all symbols of a frame with given instruction pointer (return address) and classify the frame
+    fn write_frame(&self, out: &mut String, frame_no: u32, ip: *mut c_void) -> FrameKind {
+        let mut first_symbol = true;
+        let mut kind = FrameKind::Internal;
+        backtrace::resolve(ip, |symbol| {
+            if !first_symbol {
+                out.push('\n');
+            }
+            let name = symbol.name();
+            if let Some(name) = name.as_ref() {
+                kind = kind.max(FrameKind::of(&format!("{:#}", name)));
+            }
+            let location = symbol.filename().zip(symbol.lineno());
+            self.write_symbol(
+                out,
+                Some(frame_no).filter(|_| first_symbol),
+                name.as_ref(),
+                location,
+            );
+            first_symbol = false;
+        });
+        if first_symbol {
+            self.write_symbol(out, Some(frame_no), None, None);
+            kind = FrameKind::Application;
+        }
+        kind
+    }
+
+    /// Write single symbol of a frame; inlined symbols following the first symbol of a frame have no frame number
+    pub(crate) fn write_symbol(
+        &self,
+        out: &mut String,
+        frame_no: Option<u32>,
+        name: Option<&SymbolName>,
+        location: Option<(&Path, u32)>,
+    ) {
+        match frame_no {
+            Some(frame_no) => write!(out, "{:4}: ", frame_no),
+            None => write!(out, "      "),
+        }
+        .unwrap();
+        match name {
+            Some(name) => out.push_str(&self.symbol(name)),
+            None => out.push_str("<unknown>"),
+        }
+        if let Some((path, line)) = location {
+            let separator = match self.style {
+                BacktraceStyle::Short => " at ",
+                BacktraceStyle::Full => "\n             at ",
+            };
+            write!(out, "{}{}:{}", separator, self.path(path), line).unwrap();
+        }
+    }
+
+    fn symbol(&self, name: &SymbolName) -> String {
+        if self.raw_symbols {
+            String::from_utf8_lossy(name.as_bytes()).into_owned()
+        } else {
+            format!("{:#}", name)
+        }
+    }
+
+    fn path(&self, path: &Path) -> String {
+        let path = map_path(path, &self.path_map);
+        let path = path.as_ref();
+        if self.shorten {
+            shorten_path(path, self.workspace.as_deref())
+        } else {
+            path.display().to_string()
+        }
+    }
+}
+
+fn shorten_path(path: &Path, workspace: Option<&Path>) -> String {
+    if let Some(path) = workspace.and_then(|workspace| path.strip_prefix(workspace).ok()) {
+        return path.display().to_string();
+    }
+
+    // $CARGO_HOME/registry/src/<index>/<crate>-<version>/...
+    let path = path.display().to_string();
+    for marker in &["/registry/src/", "\\registry\\src\\"] {
+        if let Some(pos) = path.find(marker) {
+            let index = &path[pos + marker.len()..];
+            if let Some(end) = index.find(&marker[..1]) {
+                return index[end + 1..].to_owned();
+            }
+        }
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formatter(raw_symbols: bool) -> FrameFormatter {
+        FrameFormatter {
+            style: BacktraceStyle::Full,
+            raw_symbols,
+            shorten: false,
+            workspace: None,
+            path_map: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn env_policy_cached() {
+        let style = env_style();
+        assert_ne!(ENV_STYLE.load(Ordering::Relaxed), 0);
+        assert_eq!(env_style(), style);
+    }
+
+    #[test]
+    #[cfg(all(
+        target_os = "linux",
+        any(target_arch = "x86_64", target_arch = "aarch64")
+    ))]
+    fn frame_pointers() {
+        #[inline(never)]
+        fn capture() -> Option<Vec<usize>> {
+            capture_frame_pointers()
+        }
+
+        let frames = capture().unwrap();
+        assert!(frames.len() <= frame_pointers::MAX_FRAMES);
+        assert!(!frames.is_empty());
+        assert!(!resolve_frames(&frames).is_empty());
+    }
+
+    #[test]
+    fn frame_kinds() {
+        assert_eq!(
+            FrameKind::of("backtrace::backtrace::trace_unsynchronized"),
+            FrameKind::Internal
+        );
+        assert_eq!(
+            FrameKind::of("problem::Problem::from_error"),
+            FrameKind::Internal
+        );
+        assert_eq!(
+            FrameKind::of("<problem::Problem as core::convert::From<E>>::from"),
+            FrameKind::Internal
+        );
+        assert_eq!(
+            FrameKind::of("<core::result::Result<O, E> as problem::ProblemWhile>::problem_while"),
+            FrameKind::Internal
+        );
+        assert_eq!(FrameKind::of("myapp::loader::load"), FrameKind::Application);
+        assert_eq!(
+            FrameKind::of("std::panicking::begin_panic::__rust_end_short_backtrace"),
+            FrameKind::EndShort
+        );
+        assert_eq!(
+            FrameKind::of("std::sys::backtrace::__rust_begin_short_backtrace"),
+            FrameKind::BeginShort
+        );
+    }
+
+    #[test]
+    fn short_backtrace() {
+        #[inline(never)]
+        fn capture(style: BacktraceStyle) -> String {
+            let mut writer = BacktraceWriter::new(style);
+            backtrace::trace(|frame| writer.write_frame(frame.ip()));
+            writer.finish()
+        }
+
+        let short = capture(BacktraceStyle::Short);
+        assert!(!short.contains("backtrace::"), "{}", short);
+        assert!(short.ends_with(SHORT_BACKTRACE_NOTE), "{}", short);
+        let full = capture(BacktraceStyle::Full);
+        assert!(full.contains("backtrace::"), "{}", full);
+        assert!(!full.contains(SHORT_BACKTRACE_NOTE), "{}", full);
+    }
+
+    #[test]
+    fn symbols() {
+        let name = SymbolName::new(b"_ZN7problem7Problem10from_error17hfdbc5afef77017deE");
+        assert_eq!(
+            formatter(false).symbol(&name),
+            "problem::Problem::from_error"
+        );
+        assert_eq!(
+            formatter(true).symbol(&name),
+            "_ZN7problem7Problem10from_error17hfdbc5afef77017deE"
+        );
+    }
+
+    #[test]
+    fn styles() {
+        let load = SymbolName::new(b"_ZN5myapp6loader4load17h0123456789abcdefE");
+        let parse = SymbolName::new(b"_ZN5myapp6loader5parse17h0123456789abcdefE");
+        let path = Path::new("src/loader.rs");
+
+        let mut formatter = formatter(false);
+        let mut out = String::new();
+        formatter.write_symbol(&mut out, Some(12), Some(&load), Some((path, 88)));
+        out.push('\n');
+        formatter.write_symbol(&mut out, None, Some(&parse), Some((path, 42)));
+        out.push('\n');
+        formatter.write_symbol(&mut out, Some(13), None, None);
+        assert_eq!(
+            out,
+            "  12: myapp::loader::load\n             at src/loader.rs:88\n      myapp::loader::parse\n             at src/loader.rs:42\n  13: <unknown>"
+        );
+
+        formatter.style = BacktraceStyle::Short;
+        let mut out = String::new();
+        formatter.write_symbol(&mut out, Some(12), Some(&load), Some((path, 88)));
+        out.push('\n');
+        formatter.write_symbol(&mut out, None, Some(&parse), Some((path, 42)));
+        assert_eq!(
+            out,
+            "  12: myapp::loader::load at src/loader.rs:88\n      myapp::loader::parse at src/loader.rs:42"
+        );
+    }
+
+    #[test]
+    fn map_paths() {
+        let path_map: Vec<_> =
+            parse_path_map("/build/src=>src; /build/deps => /home/user/deps;bogus").collect();
+        assert_eq!(path_map.len(), 2);
+        assert_eq!(
+            map_path(Path::new("/build/src/loader.rs"), &path_map),
+            Path::new("src/loader.rs")
+        );
+        assert_eq!(
+            map_path(Path::new("/build/deps/foo/lib.rs"), &path_map),
+            Path::new("/home/user/deps/foo/lib.rs")
+        );
+        assert_eq!(
+            map_path(Path::new("/build/srcs/lib.rs"), &path_map),
+            Path::new("/build/srcs/lib.rs")
+        );
+    }
+
+    #[test]
+    fn shorten_paths() {
+        let workspace = Path::new("/home/user/myapp");
+        assert_eq!(
+            shorten_path(Path::new("/home/user/myapp/src/lib.rs"), Some(workspace)),
+            "src/lib.rs"
+        );
+        assert_eq!(
+            shorten_path(
+                Path::new("/home/user/.cargo/registry/src/index.crates.io-6f17d22bba15001f/serde-1.0.100/src/de/mod.rs"),
+                Some(workspace)
+            ),
+            "serde-1.0.100/src/de/mod.rs"
+        );
+        assert_eq!(
+            shorten_path(
+                Path::new("/rustc/90b35a6239c3d8bdabc530a6a0816f7ff89a0aaf/library/std/src/rt.rs"),
+                Some(workspace)
+            ),
+            "/rustc/90b35a6239c3d8bdabc530a6a0816f7ff89a0aaf/library/std/src/rt.rs"
+        );
+        assert_eq!(
+            shorten_path(Path::new("/home/user/other/src/main.rs"), None),
+            "/home/user/other/src/main.rs"
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! Rendering of `Problem`s and panic reports as GELF (Graylog Extended Log Format) messages.
+use super::{
+    format_panic, format_panic_backtrace, record_panic_exit_code, DisplayOptions, Problem,
+    ReportStyle,
+};
+use serde_json::{Map, Value};
+use std::io::{self, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::panic;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Syslog severity level of critical conditions; used for panic reports
+pub const LEVEL_CRITICAL: u8 = 2;
+/// Syslog severity level of error conditions
+pub const LEVEL_ERROR: u8 = 3;
+/// Syslog severity level of warning conditions
+pub const LEVEL_WARNING: u8 = 4;
+
+/// GELF 1.1 message
+#[derive(Debug, Clone, PartialEq)]
+pub struct GelfMessage {
+    fields: Map<String, Value>,
+}
+
+impl GelfMessage {
+    /// Create message with given short message and syslog severity level timestamped with current time
+    pub fn new(short_message: impl Into<String>, level: u8) -> GelfMessage {
+        let mut fields = Map::new();
+        fields.insert("version".to_owned(), "1.1".into());
+        fields.insert("host".to_owned(), hostname().into());
+        fields.insert("short_message".to_owned(), short_message.into().into());
+        if let Ok(timestamp) = SystemTime::now().duration_since(UNIX_EPOCH) {
+            fields.insert("timestamp".to_owned(), timestamp.as_secs_f64().into());
+        }
+        fields.insert("level".to_owned(), level.into());
+        GelfMessage { fields }
+    }
+
+    /// Set full message
+    pub fn with_full_message(mut self, full_message: impl Into<String>) -> GelfMessage {
+        self.fields
+            .insert("full_message".to_owned(), full_message.into().into());
+        self
+    }
+
+    /// Set additional field; `_` prefix will be added to the name if missing
+    pub fn with_field(mut self, name: &str, value: impl Into<Value>) -> GelfMessage {
+        let name = if name.starts_with('_') {
+            name.to_owned()
+        } else {
+            format!("_{}", name)
+        };
+        self.fields.insert(name, value.into());
+        self
+    }
+
+    /// Get value of given field
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        self.fields.get(name)
+    }
+
+    /// Serialize message to JSON
+    pub fn to_json(&self) -> String {
+        Value::Object(self.fields.clone()).to_string()
+    }
+}
+
+impl Problem {
+    /// Render this `Problem` as GELF message with given syslog severity level
+    ///
+    /// Short message contains the one line `Problem` message and full message contains the long report.
+    /// Context messages (outermost first) are provided in `_context_N` fields, URL in `_url` field and trace ID in `_trace_id` field.
+    pub fn to_gelf(&self, level: u8) -> GelfMessage {
+        let mut short_message = String::new();
+        self.write(
+            &mut short_message,
+            &DisplayOptions::default(),
+            1,
+            ReportStyle::Short,
+        )
+        .ok();
+        let mut full_message = String::new();
+        self.write(
+            &mut full_message,
+            &DisplayOptions::default(),
+            2,
+            ReportStyle::Log,
+        )
+        .ok();
+
+        let mut message = GelfMessage::new(short_message, level).with_full_message(full_message);
+        for (no, context) in self.context.iter().rev().enumerate() {
+            message = message.with_field(&format!("context_{}", no), context.as_str());
+        }
+        if let Some(url) = self.url() {
+            message = message.with_field("url", url);
+        }
+        if let Some(trace_id) = self.trace_id() {
+            message = message.with_field("trace_id", trace_id);
+        }
+        message
+    }
+}
+
+const UDP_CHUNK_SIZE: usize = 8192;
+const UDP_CHUNK_HEADER_SIZE: usize = 12;
+const UDP_MAX_CHUNKS: usize = 128;
+
+/// Sends GELF messages to Graylog input
+#[derive(Debug)]
+pub enum GelfSender {
+    /// GELF UDP input; large messages are chunked
+    Udp(UdpSocket),
+    /// GELF TCP input; messages are null byte delimited
+    Tcp(Mutex<TcpStream>),
+}
+
+impl GelfSender {
+    /// Create sender for GELF UDP input at given address
+    ///
+    /// Each address the input resolves to is tried in turn with socket bound to unspecified address of its family (IPv4 or
+    /// IPv6).
+    pub fn udp(addr: impl ToSocketAddrs) -> io::Result<GelfSender> {
+        let mut last_error = None;
+        for addr in addr.to_socket_addrs()? {
+            let local: SocketAddr = match addr {
+                SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+                SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+            };
+            match UdpSocket::bind(local).and_then(|socket| socket.connect(addr).map(|()| socket)) {
+                Ok(socket) => return Ok(GelfSender::Udp(socket)),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "could not resolve to any addresses",
+            )
+        }))
+    }
+
+    /// Create sender for GELF TCP input at given address
+    pub fn tcp(addr: impl ToSocketAddrs) -> io::Result<GelfSender> {
+        Ok(GelfSender::Tcp(Mutex::new(TcpStream::connect(addr)?)))
+    }
+
+    /// Send message
+    pub fn send(&self, message: &GelfMessage) -> io::Result<()> {
+        let json = message.to_json();
+        match self {
+            GelfSender::Udp(socket) => {
+                if json.len() <= UDP_CHUNK_SIZE {
+                    socket.send(json.as_bytes())?;
+                    return Ok(());
+                }
+
+                let chunks = json
+                    .as_bytes()
+                    .chunks(UDP_CHUNK_SIZE - UDP_CHUNK_HEADER_SIZE);
+                let count = chunks.len();
+                if count > UDP_MAX_CHUNKS {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "GELF message too large to be sent over UDP",
+                    ));
+                }
+
+                let id = message_id();
+                for (seq, chunk) in chunks.enumerate() {
+                    let mut datagram = Vec::with_capacity(UDP_CHUNK_HEADER_SIZE + chunk.len());
+                    datagram.extend_from_slice(&[0x1e, 0x0f]);
+                    datagram.extend_from_slice(&id);
+                    datagram.push(seq as u8);
+                    datagram.push(count as u8);
+                    datagram.extend_from_slice(chunk);
+                    socket.send(&datagram)?;
+                }
+                Ok(())
+            }
+            GelfSender::Tcp(stream) => {
+                let mut stream = stream
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                stream.write_all(json.as_bytes())?;
+                stream.write_all(b"\0")?;
+                stream.flush()
+            }
+        }
+    }
+}
+
+fn message_id() -> [u8; 8] {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+
+    let mut hasher = RandomState::new().build_hasher();
+    if let Ok(timestamp) = SystemTime::now().duration_since(UNIX_EPOCH) {
+        hasher.write_u128(timestamp.as_nanos());
+    }
+    hasher.finish().to_be_bytes()
+}
+
+fn hostname() -> String {
+    #[cfg(unix)]
+    {
+        let mut buf = [0u8; 256];
+        if unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) } == 0 {
+            let len = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+            return String::from_utf8_lossy(&buf[..len]).into_owned();
+        }
+    }
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .unwrap_or_else(|_| "localhost".to_owned())
+}
+
+/// Set panic hook so that panic report is sent as GELF message with critical level using given sender
+///
+/// The report is also printed to `stderr`, which is also used to report failures to send the message.
+pub fn format_panic_to_gelf(sender: GelfSender) {
+    panic::set_hook(Box::new(move |panic_info| {
+        record_panic_exit_code(panic_info.payload());
+        let backtrace = format_panic_backtrace();
+        let report = format_panic(panic_info, backtrace);
+        eprintln!("Fatal error: {}", report);
+
+        let short_message = report.lines().next().unwrap_or_default().to_owned();
+        let mut message = GelfMessage::new(short_message, LEVEL_CRITICAL).with_full_message(report);
+        if let Some(location) = panic_info.location() {
+            message = message
+                .with_field("file", location.file())
+                .with_field("line", location.line());
+        }
+        if let Err(err) = sender.send(&message) {
+            eprintln!("Failed to send panic report as GELF message: {}", err);
+        }
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_problem_to_gelf() {
+        let message = Problem::from_error("boom!")
+            .problem_while("parsing input")
+            .problem_while("processing object")
+            .with_url("https://example.com")
+            .to_gelf(LEVEL_ERROR);
+
+        assert_eq!(message.field("version"), Some(&"1.1".into()));
+        assert_eq!(message.field("level"), Some(&3.into()));
+        assert_eq!(
+            message.field("short_message"),
+            Some(&"while processing object, while parsing input got error caused by: boom!".into())
+        );
+        assert_eq!(
+            message.field("_context_0"),
+            Some(&"processing object".into())
+        );
+        assert_eq!(message.field("_context_1"), Some(&"parsing input".into()));
+        assert_eq!(message.field("_url"), Some(&"https://example.com".into()));
+    }
+
+    #[test]
+    fn test_udp_chunking() {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sender = GelfSender::udp(receiver.local_addr().unwrap()).unwrap();
+
+        let message = GelfMessage::new("x".repeat(UDP_CHUNK_SIZE * 2), LEVEL_WARNING);
+        sender.send(&message).unwrap();
+
+        let mut json = Vec::new();
+        let mut buf = [0u8; UDP_CHUNK_SIZE];
+        for seq in 0..3 {
+            let len = receiver.recv(&mut buf).unwrap();
+            assert_eq!(&buf[..2], &[0x1e, 0x0f]);
+            assert_eq!(buf[10], seq);
+            assert_eq!(buf[11], 3);
+            json.extend_from_slice(&buf[UDP_CHUNK_HEADER_SIZE..len]);
+        }
+        assert_eq!(String::from_utf8(json).unwrap(), message.to_json());
+    }
+
+    #[test]
+    fn test_udp_ipv6() {
+        // IPv6 may not be available in test environment
+        let receiver = match UdpSocket::bind("[::1]:0") {
+            Ok(receiver) => receiver,
+            Err(_) => return,
+        };
+        let sender = GelfSender::udp(receiver.local_addr().unwrap()).unwrap();
+
+        let message = GelfMessage::new("boom!", LEVEL_WARNING);
+        sender.send(&message).unwrap();
+
+        let mut buf = [0u8; UDP_CHUNK_SIZE];
+        let len = receiver.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], message.to_json().as_bytes());
+    }
+}
@@ -0,0 +1,338 @@
+//! Error condition of long-running programs for health or status endpoints.
+//!
+//! The most recent `Problem` can be kept in a global slot with `record_last_problem` and retrieved with `take_last_problem`.
+//! When enabled with `track_last_problem`, `Problem`s reported to sinks (e.g. with `ok_or_log_warn` or `ok_or_print_error`)
+//! are recorded automatically.
+//!
+//! ```rust
+//! use problem::prelude::*;
+//! use problem::health::{take_last_problem, track_last_problem};
+//! use problem::sink::Severity;
+//!
+//! track_last_problem(true);
+//!
+//! let _ = "80x".parse::<u16>().problem_while("parsing port").ok_or_print_warn();
+//!
+//! let last = take_last_problem().unwrap();
+//! assert_eq!(last.severity(), Severity::Warning);
+//! assert_eq!(last.problem().to_string(), "while parsing port got error caused by: invalid digit found in string");
+//! assert!(take_last_problem().is_none());
+//! ```
+//!
+//! `HealthState` keeps a number of recent `Problem`s and tells if the program is degraded, that is if errors were reported
+//! recently. It is also a `ProblemSink` so it can be fed with `ok_or_report`:
+//!
+//! ```rust
+//! use problem::prelude::*;
+//! use problem::health::HealthState;
+//! use problem::sink::Severity;
+//! use std::time::Duration;
+//!
+//! let health = HealthState::new(16).with_window(Duration::from_secs(300));
+//! assert_eq!(health.summary(), "ok");
+//!
+//! let _ = "80x".parse::<u16>().problem_while("parsing port").ok_or_report(&health, Severity::Error);
+//!
+//! assert!(health.is_degraded());
+//! assert_eq!(health.summary(), "degraded: 1 error, 0 warnings; last: while parsing port got error caused by: invalid digit found in string");
+//! ```
+use super::sink::{ProblemSink, Severity};
+use super::{Problem, ProblemError};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime};
+
+/// `Problem` recorded with `record_last_problem` with its severity and time it was recorded
+#[derive(Debug)]
+pub struct LastProblem {
+    problem: ProblemError,
+    severity: Severity,
+    time: SystemTime,
+}
+
+impl LastProblem {
+    /// Recorded `Problem` in `Send` and `Sync` form
+    pub fn problem(&self) -> &ProblemError {
+        &self.problem
+    }
+
+    /// Severity the `Problem` was reported with
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Time the `Problem` was recorded at
+    pub fn time(&self) -> SystemTime {
+        self.time
+    }
+
+    /// Convert back to `Problem`
+    pub fn into_problem(self) -> Problem {
+        self.problem.into_problem()
+    }
+}
+
+struct Slot {
+    tracking: AtomicBool,
+    last: Mutex<Option<LastProblem>>,
+}
+
+impl Slot {
+    const fn new() -> Slot {
+        Slot {
+            tracking: AtomicBool::new(false),
+            last: Mutex::new(None),
+        }
+    }
+
+    fn record(&self, severity: Severity, problem: &Problem) {
+        let last = LastProblem {
+            problem: problem.into(),
+            severity,
+            time: SystemTime::now(),
+        };
+        *self
+            .last
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(last);
+    }
+
+    fn take(&self) -> Option<LastProblem> {
+        self.last
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take()
+    }
+
+    fn reported(&self, severity: Severity, problem: &Problem) {
+        if self.tracking.load(Ordering::Relaxed) {
+            self.record(severity, problem)
+        }
+    }
+}
+
+static SLOT: Slot = Slot::new();
+
+/// Record `Problem`s reported to sinks in the last problem slot (disabled by default)
+pub fn track_last_problem(enabled: bool) {
+    SLOT.tracking.store(enabled, Ordering::Relaxed)
+}
+
+/// Record given `Problem` in the last problem slot replacing previously recorded one
+pub fn record_last_problem(severity: Severity, problem: &Problem) {
+    SLOT.record(severity, problem)
+}
+
+/// Take the most recently recorded `Problem` out of the last problem slot
+pub fn take_last_problem() -> Option<LastProblem> {
+    SLOT.take()
+}
+
+/// Returns `true` if `Problem`s reported to sinks are recorded in the last problem slot
+pub(crate) fn is_tracking() -> bool {
+    SLOT.tracking.load(Ordering::Relaxed)
+}
+
+/// Called for every `Problem` reported to a sink
+pub(crate) fn problem_reported(severity: Severity, problem: &Problem) {
+    SLOT.reported(severity, problem)
+}
+
+/// `Problem` recorded in `HealthState`
+#[derive(Debug, Clone)]
+pub struct HealthEntry {
+    message: String,
+    severity: Severity,
+    time: SystemTime,
+    recorded: Instant,
+}
+
+impl HealthEntry {
+    /// One line message of the `Problem` (see `Problem::brief`)
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Severity the `Problem` was reported with
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    /// Time the `Problem` was recorded at
+    pub fn time(&self) -> SystemTime {
+        self.time
+    }
+}
+
+/// Recent `Problem`s of a long-running program with their severities and times (see module documentation)
+///
+/// Only given number of most recent `Problem`s is kept. Program is degraded if any of them is an error that was recorded within
+/// time window (if set with `with_window`).
+#[derive(Debug)]
+pub struct HealthState {
+    capacity: usize,
+    window: Option<Duration>,
+    entries: Mutex<VecDeque<HealthEntry>>,
+}
+
+impl HealthState {
+    /// Create state keeping at most given number of recent `Problem`s
+    pub fn new(capacity: usize) -> HealthState {
+        HealthState {
+            capacity,
+            window: None,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Consider only `Problem`s recorded within given time
+    pub fn with_window(mut self, window: Duration) -> HealthState {
+        self.window = Some(window);
+        self
+    }
+
+    fn entries(&self) -> MutexGuard<'_, VecDeque<HealthEntry>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Record given `Problem` dropping the oldest one if capacity is reached
+    pub fn record(&self, severity: Severity, problem: &Problem) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries();
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(HealthEntry {
+            message: problem.brief(),
+            severity,
+            time: SystemTime::now(),
+            recorded: Instant::now(),
+        });
+    }
+
+    fn recent_at(&self, now: Instant) -> Vec<HealthEntry> {
+        self.entries()
+            .iter()
+            .filter(|entry| match self.window {
+                Some(window) => now.saturating_duration_since(entry.recorded) <= window,
+                None => true,
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Get recorded `Problem`s within time window, oldest first
+    pub fn recent(&self) -> Vec<HealthEntry> {
+        self.recent_at(Instant::now())
+    }
+
+    /// Returns `true` if error was recorded within time window
+    pub fn is_degraded(&self) -> bool {
+        self.recent()
+            .iter()
+            .any(|entry| entry.severity == Severity::Error)
+    }
+
+    fn summary_of(recent: &[HealthEntry]) -> String {
+        let errors = recent
+            .iter()
+            .filter(|entry| entry.severity == Severity::Error)
+            .count();
+        let warnings = recent.len() - errors;
+        let plural = |count: usize| if count == 1 { "" } else { "s" };
+        match recent.last() {
+            None => "ok".to_owned(),
+            Some(last) => format!(
+                "{}: {} error{}, {} warning{}; last: {}",
+                if errors > 0 { "degraded" } else { "ok" },
+                errors,
+                plural(errors),
+                warnings,
+                plural(warnings),
+                last.message
+            ),
+        }
+    }
+
+    /// One line description of current error condition, e.g. `degraded: 2 errors, 1 warning; last: ...` or `ok`
+    pub fn summary(&self) -> String {
+        HealthState::summary_of(&self.recent())
+    }
+
+    /// Forget all recorded `Problem`s
+    pub fn clear(&self) {
+        self.entries().clear()
+    }
+}
+
+impl ProblemSink for &HealthState {
+    fn report(&mut self, severity: Severity, problem: &Problem) {
+        self.record(severity, problem)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn slot() {
+        let slot = Slot::new();
+        slot.reported(Severity::Error, &Problem::from_error("ignored"));
+        assert!(slot.take().is_none());
+
+        slot.tracking.store(true, Ordering::Relaxed);
+        slot.reported(Severity::Error, &Problem::from_error("first"));
+        slot.reported(
+            Severity::Warning,
+            &Problem::from_error("second").problem_while("testing"),
+        );
+
+        let last = slot.take().unwrap();
+        assert_eq!(last.severity(), Severity::Warning);
+        assert!(last.time() <= SystemTime::now());
+        assert_eq!(
+            last.into_problem().contexts().collect::<Vec<_>>(),
+            ["testing"]
+        );
+        assert!(slot.take().is_none());
+    }
+
+    #[test]
+    fn health_state() {
+        let health = HealthState::new(2).with_window(Duration::from_secs(60));
+        assert!(!health.is_degraded());
+
+        health.record(Severity::Error, &Problem::from_error("first"));
+        health.record(Severity::Warning, &Problem::from_error("second"));
+        assert!(health.is_degraded());
+        assert_eq!(
+            health.summary(),
+            "degraded: 1 error, 1 warning; last: second"
+        );
+
+        health.record(Severity::Warning, &Problem::from_error("third"));
+        assert!(!health.is_degraded());
+        assert_eq!(
+            health
+                .recent()
+                .iter()
+                .map(HealthEntry::message)
+                .collect::<Vec<_>>(),
+            ["second", "third"]
+        );
+        assert_eq!(health.summary(), "ok: 0 errors, 2 warnings; last: third");
+
+        let later = Instant::now() + Duration::from_secs(61);
+        assert!(health.recent_at(later).is_empty());
+
+        health.clear();
+        assert_eq!(health.summary(), "ok");
+    }
+}
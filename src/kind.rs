@@ -0,0 +1,225 @@
+//! Classification of `Problem`s by kind of failure.
+//!
+//! `Problem` can be assigned `ProblemKind` (e.g. I/O, configuration, network or user error) with `Problem::with_kind` or when it
+//! is converted from error type that classifies itself with `IntoProblem::problem_kind`. `io::Error` is classified as I/O or
+//! network error by `Problem::from_error`. Applications define their own kinds with `ProblemKind::new` and register `KindPolicy` for them with
+//! `register_kind`. The policy is consulted for exit code when program terminates with the `Problem` (see `exit_codes`), by
+//! `Problem::is_retryable` and by `Problem::severity` which selects log level of `ok_or_log`.
+//!
+//! ```rust
+//! use problem::prelude::*;
+//! use problem::kind::{register_kind, KindPolicy, ProblemKind};
+//! use problem::sink::Severity;
+//!
+//! const DATABASE: ProblemKind = ProblemKind::new("database");
+//!
+//! register_kind(DATABASE, KindPolicy { exit_code: Some(69), retry: true, severity: Severity::Warning });
+//!
+//! let problem = Problem::from_error("connection lost").with_kind(DATABASE);
+//! assert_eq!(problem.kind(), Some(DATABASE));
+//! assert!(problem.is_retryable());
+//! assert_eq!(problem.severity(), Severity::Warning);
+//!
+//! let problem = Problem::from_error("bad query");
+//! assert!(!problem.is_retryable());
+//! assert_eq!(problem.severity(), Severity::Error);
+//! ```
+use super::sink::Severity;
+use super::Problem;
+use std::fmt;
+use std::io;
+use std::sync::Mutex;
+
+/// Kind of failure a `Problem` represents; see module documentation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProblemKind(&'static str);
+
+impl ProblemKind {
+    /// Failure of I/O operation (e.g. file system access)
+    pub const IO: ProblemKind = ProblemKind("io");
+    /// Invalid or missing configuration
+    pub const CONFIG: ProblemKind = ProblemKind("config");
+    /// Failure of network communication
+    pub const NETWORK: ProblemKind = ProblemKind("network");
+    /// Invalid input or usage by the user
+    pub const USER: ProblemKind = ProblemKind("user");
+
+    /// Create kind with given name; kinds with the same name are equal
+    pub const fn new(name: &'static str) -> ProblemKind {
+        ProblemKind(name)
+    }
+
+    /// Name of this kind
+    pub fn name(&self) -> &'static str {
+        self.0
+    }
+
+    /// Find built-in or registered (see `register_kind`) kind with given name
+    #[cfg(feature = "serde")]
+    pub(crate) fn from_name(name: &str) -> Option<ProblemKind> {
+        [
+            ProblemKind::IO,
+            ProblemKind::CONFIG,
+            ProblemKind::NETWORK,
+            ProblemKind::USER,
+        ]
+        .iter()
+        .copied()
+        .chain(
+            POLICIES
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .iter()
+                .map(|&(kind, _)| kind),
+        )
+        .find(|kind| kind.name() == name)
+    }
+
+    /// Classify `io::Error` as network or I/O failure
+    pub(crate) fn of_io_error(error: &io::Error) -> ProblemKind {
+        match error.kind() {
+            io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::AddrInUse
+            | io::ErrorKind::AddrNotAvailable
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::TimedOut => ProblemKind::NETWORK,
+            _ => ProblemKind::IO,
+        }
+    }
+}
+
+impl fmt::Display for ProblemKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+/// How `Problem`s of given kind are handled; see `register_kind`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KindPolicy {
+    /// Exit code used when program terminates with the `Problem` unless it has exit code set with `Problem::with_exit_code`
+    pub exit_code: Option<i32>,
+    /// Failed operation may succeed if retried (see `Problem::is_retryable`)
+    pub retry: bool,
+    /// Severity the `Problem` is reported with by `ok_or_log` (see `Problem::severity`)
+    pub severity: Severity,
+}
+
+/// Used for `Problem`s without kind or with kind that was not registered
+impl Default for KindPolicy {
+    fn default() -> KindPolicy {
+        KindPolicy {
+            exit_code: None,
+            retry: false,
+            severity: Severity::Error,
+        }
+    }
+}
+
+static POLICIES: Mutex<Vec<(ProblemKind, KindPolicy)>> = Mutex::new(Vec::new());
+
+/// Register policy for `Problem`s of given kind; replaces previously registered policy of the kind
+pub fn register_kind(kind: ProblemKind, policy: KindPolicy) {
+    let mut policies = POLICIES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    match policies
+        .iter_mut()
+        .find(|(registered, _)| *registered == kind)
+    {
+        Some(registered) => registered.1 = policy,
+        None => policies.push((kind, policy)),
+    }
+}
+
+/// Get policy registered for given kind
+pub fn kind_policy(kind: ProblemKind) -> Option<KindPolicy> {
+    policy_in(
+        &POLICIES
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        kind,
+    )
+}
+
+fn policy_in(policies: &[(ProblemKind, KindPolicy)], kind: ProblemKind) -> Option<KindPolicy> {
+    policies
+        .iter()
+        .find(|(registered, _)| *registered == kind)
+        .map(|&(_, policy)| policy)
+}
+
+impl Problem {
+    /// Classify this `Problem` with given kind replacing kind assigned on conversion
+    pub fn with_kind(mut self, kind: ProblemKind) -> Problem {
+        self.details_mut().kind = Some(kind);
+        self
+    }
+
+    /// Get kind of this `Problem` if it was classified
+    pub fn kind(&self) -> Option<ProblemKind> {
+        self.details.as_ref().and_then(|details| details.kind)
+    }
+
+    /// Policy registered for kind of this `Problem` or default policy
+    pub(crate) fn kind_policy(&self) -> KindPolicy {
+        self.kind().and_then(kind_policy).unwrap_or_default()
+    }
+
+    /// Returns `true` if kind of this `Problem` was registered with `retry` policy
+    pub fn is_retryable(&self) -> bool {
+        self.kind_policy().retry
+    }
+
+    /// Severity registered for kind of this `Problem`; `Severity::Error` by default
+    pub fn severity(&self) -> Severity {
+        self.kind_policy().severity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn conversion() {
+        let problem: Problem = io::Error::new(io::ErrorKind::NotFound, "config.toml").into();
+        assert_eq!(problem.kind(), Some(ProblemKind::IO));
+
+        let problem = Err::<(), _>(io::Error::from(io::ErrorKind::ConnectionRefused))
+            .problem_while("connecting")
+            .unwrap_err();
+        assert_eq!(problem.kind(), Some(ProblemKind::NETWORK));
+        assert_eq!(
+            problem.with_kind(ProblemKind::CONFIG).kind(),
+            Some(ProblemKind::CONFIG)
+        );
+
+        let problem = Problem::from_error(io::Error::from(io::ErrorKind::TimedOut));
+        assert_eq!(problem.kind(), Some(ProblemKind::NETWORK));
+
+        let problem: Problem = "boom!".into();
+        assert_eq!(problem.kind(), None);
+    }
+
+    #[test]
+    fn policies() {
+        const RETRY: KindPolicy = KindPolicy {
+            exit_code: Some(75),
+            retry: true,
+            severity: Severity::Warning,
+        };
+        let policies = [(ProblemKind::new("network"), RETRY)];
+        assert_eq!(policy_in(&policies, ProblemKind::NETWORK), Some(RETRY));
+        assert_eq!(policy_in(&policies, ProblemKind::IO), None);
+
+        let problem = Problem::from_error("boom!").with_kind(ProblemKind::new("unregistered"));
+        assert!(!problem.is_retryable());
+        assert_eq!(problem.severity(), Severity::Error);
+        assert_eq!(problem.fatal_exit_code(), None);
+    }
+}
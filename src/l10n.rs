@@ -0,0 +1,160 @@
+//! Localized error messages with Fluent.
+//!
+//! Fluent bundles (see `fluent-bundle` crate) registered with `add_l10n_bundle` are used by `Problem::from_l10n` to
+//! format error message from message ID and arguments. Bundles are searched in order of registration so later bundles can
+//! provide fallback translations. If no bundle has the message, the message ID is used instead.
+//!
+//! The original message ID is kept in `L10nMessage` error so it can be inspected regardless of the language used.
+//!
+//! ```rust
+//! # extern crate fluent_bundle;
+//! use fluent_bundle::concurrent::FluentBundle;
+//! use fluent_bundle::{FluentArgs, FluentResource};
+//! use problem::prelude::*;
+//! use problem::l10n::L10nMessage;
+//!
+//! let resource = FluentResource::try_new("err-open-file = Nie można otworzyć pliku { $path }".to_owned()).unwrap();
+//! let mut bundle = FluentBundle::new_concurrent(vec!["pl-PL".parse().unwrap()]);
+//! bundle.set_use_isolating(false);
+//! bundle.add_resource(resource).unwrap();
+//! problem::l10n::add_l10n_bundle(bundle);
+//!
+//! let mut args = FluentArgs::new();
+//! args.set("path", "config.toml");
+//! let problem = Problem::from_l10n("err-open-file", Some(&args));
+//!
+//! assert_eq!(problem.to_string(), "Nie można otworzyć pliku config.toml");
+//! assert_eq!(problem.find_cause::<L10nMessage>().unwrap().id(), "err-open-file");
+//! ```
+use super::{IntoProblem, Problem};
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use std::error::Error;
+use std::fmt;
+use std::sync::Mutex;
+
+static BUNDLES: Mutex<Vec<FluentBundle<FluentResource>>> = Mutex::new(Vec::new());
+
+/// Register Fluent bundle used to format messages of `Problem::from_l10n`
+///
+/// Bundles are searched for messages in order of registration.
+///
+/// Note that by default Fluent wraps placeables in Unicode isolation marks; use `FluentBundle::set_use_isolating` to disable this
+/// for terminal output.
+pub fn add_l10n_bundle(bundle: FluentBundle<FluentResource>) {
+    BUNDLES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .push(bundle);
+}
+
+/// Error with message formatted from Fluent message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct L10nMessage {
+    id: String,
+    message: String,
+}
+
+impl L10nMessage {
+    /// Fluent message ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Formatted message
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for L10nMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for L10nMessage {}
+
+impl IntoProblem for L10nMessage {}
+
+/// Format message with first bundle that has it; formatting errors are ignored as Fluent substitutes missing values
+fn localize(
+    bundles: &[FluentBundle<FluentResource>],
+    id: &str,
+    args: Option<&FluentArgs<'_>>,
+) -> L10nMessage {
+    let message = bundles
+        .iter()
+        .find_map(|bundle| {
+            let pattern = bundle.get_message(id)?.value()?;
+            let mut errors = Vec::new();
+            Some(
+                bundle
+                    .format_pattern(pattern, args, &mut errors)
+                    .into_owned(),
+            )
+        })
+        .unwrap_or_else(|| id.to_owned());
+
+    L10nMessage {
+        id: id.to_owned(),
+        message,
+    }
+}
+
+impl Problem {
+    /// Create `Problem` with error message formatted from Fluent message of given ID (see module documentation)
+    pub fn from_l10n(id: &str, args: Option<&FluentArgs<'_>>) -> Problem {
+        let message = localize(
+            &BUNDLES
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            id,
+            args,
+        );
+        Problem::from_error(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle(locale: &str, source: &str) -> FluentBundle<FluentResource> {
+        let mut bundle = FluentBundle::new_concurrent(vec![locale.parse().unwrap()]);
+        bundle.set_use_isolating(false);
+        bundle
+            .add_resource(FluentResource::try_new(source.to_owned()).unwrap())
+            .unwrap();
+        bundle
+    }
+
+    #[test]
+    fn fallback() {
+        let bundles = [
+            bundle(
+                "de-DE",
+                "err-open-file = Datei { $path } kann nicht geöffnet werden",
+            ),
+            bundle(
+                "en-US",
+                "err-open-file = Cannot open { $path }\nerr-timeout = Timed out",
+            ),
+        ];
+        let mut args = FluentArgs::new();
+        args.set("path", "a.toml");
+
+        assert_eq!(
+            localize(&bundles, "err-open-file", Some(&args)).message(),
+            "Datei a.toml kann nicht geöffnet werden"
+        );
+        assert_eq!(
+            localize(&bundles, "err-timeout", None).message(),
+            "Timed out"
+        );
+
+        let missing = localize(&bundles, "err-unknown", None);
+        assert_eq!(missing.id(), "err-unknown");
+        assert_eq!(missing.message(), "err-unknown");
+    }
+}
@@ -17,6 +17,8 @@ It is recommended to import all the types and traits via perlude module: `use pr
 Additionally `Problem` can also store backtrace `String` (if enabled) and a chain of additional context messages as `Vec<String>`.
 
 In order to support conversion from arbitary types implementing `Error` trait, `Problem` does not implement this trait.
+Use `Problem::as_std_error` where a reference to `Error` is needed or convert it to `ProblemError` where owned `'static` error is
+required.
 
 # Creating `Problem`
 There are multiple ways to crate `Problem` value.
@@ -34,7 +36,8 @@ Problem::from_error(Box::new(io::Error::new(io::ErrorKind::InvalidInput, "boom!"
 Problem::from_error("boom!");
 ```
 
-Use `Problem::from_error_message(error)` if you don't want to give up ownership of error or only want to keep final message string in memory.
+Use `Problem::from_error_message(error)` if you don't want to give up ownership of error or only want to keep error messages in memory.
+Use `Problem::from_dyn_error(error)` if the error is only available as `&dyn Error` trait object.
 
 ```rust
 use problem::prelude::*;
@@ -62,7 +65,10 @@ assert_eq!(foo().unwrap_err().to_string(), "Can't count to 4");
 ```
 
 ## Implicitly
-Types implementing `Into<Box<dyn Error>>` trait can be converted to `Problem` via `From` trait. `?` will automatically convert types implementing `Error` to `Problem`.
+Types implementing `IntoProblem` marker trait can be converted to `Problem` via `From` trait. `?` will automatically convert standard library errors,
+errors of this crate and other error types implementing `IntoProblem` to `Problem`.
+Crates can instead implement `From<TheirError> for Problem` to customize the conversion (see `IntoProblem`).
+Extension traits of this crate (e.g. `.problem_while(message)`) accept any error type (see `ToProblem`).
 
 ```rust
 use problem::prelude::*;
@@ -79,6 +85,7 @@ If `Error::cause` or `Error::source` is available, the error message from cause
 
 ```rust
 use problem::prelude::*;
+use problem::IntoProblem;
 use std::fmt;
 use std::error::Error;
 
@@ -97,6 +104,8 @@ impl Error for ErrorWithCause {
     }
 }
 
+impl IntoProblem for ErrorWithCause {}
+
 fn foo() -> Result<String, Problem> {
     let str = String::from_utf8(vec![0, 123, 255]).map_err(ErrorWithCause)?;
     Ok(str)
@@ -118,7 +127,7 @@ assert_eq!(problem.unwrap_err().to_string(), "oops");
 ```
 
 ## By conversion of `Option` into `Result`
-`Option<T>` can be converted into `Result<T, Problem>` with `.ok_or_problem(problem)` function where `problem` implements `Into<Problem>`.
+`Option<T>` can be converted into `Result<T, Problem>` with `.ok_or_problem(problem)` function where `problem` implements `ToProblem`.
 
 ```rust
 use problem::prelude::*;
@@ -181,6 +190,17 @@ let res = String::from_utf8(vec![0, 123, 255]);
 assert_eq!(res.problem_while_with(|| "creating string").unwrap_err().to_string(), "while creating string got error caused by: invalid utf-8 sequence of 1 bytes from index 2");
 ```
 
+Method `.problem_while_with_err(|error| message)` passes reference to the error to the function so that the message can
+include information extracted from it.
+
+```rust
+use problem::prelude::*;
+
+let res = String::from_utf8(vec![0, 123, 255]);
+
+assert_eq!(res.problem_while_with_err(|err| format!("creating string from {} bytes", err.as_bytes().len())).unwrap_err().to_string(), "while creating string from 3 bytes got error caused by: invalid utf-8 sequence of 1 bytes from index 2");
+```
+
 ## Using scope and `?`
 Function `in_context_of(message, closure)` can be used to wrap block of code in a closure that returns `Result`.
 
@@ -199,6 +219,20 @@ let res = in_context_of("processing string", || {
 assert_eq!(res.unwrap_err().to_string(), "while processing string got error caused by: invalid utf-8 sequence of 1 bytes from index 2");
 ```
 
+The message can also be built with `format!`:
+
+```rust
+use problem::prelude::*;
+
+let file = "data.bin";
+let res = in_context_of(format!("processing file {}", file), || {
+    let _s = String::from_utf8(vec![0, 123, 255])?;
+    Ok(())
+});
+
+assert_eq!(res.unwrap_err().to_string(), "while processing file data.bin got error caused by: invalid utf-8 sequence of 1 bytes from index 2");
+```
+
 There is also `in_context_of_with(message_function, closure)` variant provided that can be used to defer construction of context message to error path.
 
 ```rust
@@ -233,15 +267,79 @@ let res = in_context_of("doing stuff", || {
 assert_eq!(res.unwrap_err().to_string(), "while doing stuff, while running foo got error caused by: invalid utf-8 sequence of 1 bytes from index 2");
 ```
 
+# Attaching additional information
+## Documentation URL
+Method `.with_url(url)` can be used to point user to documentation describing the error and how to remediate it.
+The URL is displayed in a separate line after the error message in long report (alternate `Display` format) and logs.
+
+```rust
+use problem::prelude::*;
+
+let problem = Problem::from_error("bad config").with_url("https://docs.example.com/errors/E42");
+
+assert_eq!(problem.url(), Some("https://docs.example.com/errors/E42"));
+# if problem.backtrace().is_none() {
+assert_eq!(problem.to_string(), "bad config");
+assert_eq!(format!("{:#}", problem), "bad config\nsee: https://docs.example.com/errors/E42");
+# }
+```
+
+## HTTP status
+Method `.with_status(status)` can be used to associate HTTP status code with `Problem`.
+If not set, `.status()` will derive the status from `io::ErrorKind` of the errors in the cause chain or return 500.
+
+```rust
+use problem::prelude::*;
+use std::io;
+
+assert_eq!(Problem::from_error("boom!").with_status(503).status(), 503);
+assert_eq!(Problem::from_error(io::Error::new(io::ErrorKind::NotFound, "no file")).status(), 404);
+assert_eq!(Problem::from_error("boom!").status(), 500);
+```
+
+## Environment variables
+Method `.with_env(names)` will capture values of given environment variables.
+They are displayed only in long report produced with alternate `Display` format (`{:#}`) or `Problem::report` which is used when program aborts
+or logs `Problem` at debug level.
+
+```rust
+use problem::prelude::*;
+
+let problem = Problem::from_error("connection failed").with_env(&["HTTP_PROXY"]);
+
+# if problem.backtrace().is_none() {
+assert_eq!(problem.to_string(), "connection failed");
+assert!(format!("{:#}", problem).starts_with("connection failed\n--- Environment\nHTTP_PROXY"));
+# }
+```
+
+## Sections
+Any other information (e.g. failing query, request body or output of a tool) can be attached with `.with_section(title, body)` and displayed
+in the long report in the same way. Use `.with_section_lazy(title, || body)` if producing the body is expensive; it will be called only if
+the report is rendered.
+
 # Aborting program on `Problem`
 `panic!(message, problem)` macro can be used directly to abort program execution but error message printed on the screen will be formatted with `Debug` implementation.
 
 This library provides function `format_panic_to_stderr()` to set up hook that will use `eprintln!("{}", message)` to report panics.
 Alternatively if `log` feature is enabled (default), function `format_panic_to_error_log()` will set up hook that will log with `error!("{}", message)` to report panics.
+If `color` feature is enabled, function `color::format_panic_to_stderr_colored()` will set up hook that highlights the report when
+`stderr` is a terminal, respecting `NO_COLOR`, `CLICOLOR` and `CLICOLOR_FORCE` environment variables.
+Function `setup()` will choose one of the above depending on whether logger was initialized and `color` feature is enabled; it also
+reads `PROBLEM_VERBOSITY` and `RUST_BACKTRACE` environment variables and maps common tags to exit codes (see `DEFAULT_EXIT_CODES`).
 
 Panic hooks will produce backtrace of panic site if enabled via `RUST_BACKTRACE=1` environment variable along of the `Problem` object backtrace collected
 at object construction site.
 
+Wrapping body of `main` with `run_main(body)` terminates the program with exit code of the `Problem` the panic was raised with by
+`or_failed_to` (see `exit_codes`).
+
+Function `on_fatal(cleanup)` can be used to register cleanup functions (e.g. restoring terminal mode or removing PID file) that will be called when
+panic reaches `run_main` and before exiting with `FatalProblem`. Panics that are caught (e.g. by `spawn_compute`) do not run them.
+
+If `signals` feature is enabled, function `report_on_signal()` will install `SIGINT` and `SIGTERM` handlers that print messages of `in_context_of` calls
+that were executing when program got interrupted (e.g. `Interrupted while: syncing repo, while: downloading chunk 85`).
+
 ```noformat
 ERROR: Panicked in libcore/slice/mod.rs:2334:5: index 18492 out of range for slice of length 512
 ```
@@ -291,6 +389,24 @@ let _ok: Vec<u32> = results.into_iter()
     .collect(); // Failed to collect numbers due to: oops
 ```
 
+Use `.or_failed_to_with(|| message)` to build the message only when an `Err` is encountered.
+
+## Stopping iteration on first `Err`
+Method `.take_until_problem()` yields `Ok` values until first `Err` is encountered; the error converted to `Problem` can then be retrieved
+with `.first_problem()` together with partial results.
+
+```rust
+use problem::prelude::*;
+
+let results = vec![Ok(1u32), Ok(2u32), Err("oops"), Ok(3u32)];
+
+let mut numbers = results.into_iter().take_until_problem();
+let ok: Vec<u32> = numbers.by_ref().collect();
+
+assert_eq!(ok, vec![1, 2]);
+assert_eq!(numbers.first_problem().unwrap().to_string(), "oops");
+```
+
 # Main function exit with error message and custom status
 `FatalProblem` and `result::FinalResult` types can be used on `main` function signature to allow programs to terminate with `Problem` formatted message and custom exit status.
 
@@ -335,9 +451,35 @@ assert_eq!(ok.as_slice(), [1, 2, 3, 4]);
 # test_with_log_feature();
 ```
 
+Use `.or_default_log_warn()` or `.or_default_log_error()` on `Result` to log the `Err` variant and continue with default value instead.
+
+Programs that don't use logger can use `.ok_or_print_warn()` or `.ok_or_print_error()` instead to print the `Problem` to `stderr`
+prefixed with `WARNING:` or `ERROR:`, or `.ok_or_write(&mut sink)` to write it as a line into any `io::Write` sink (e.g. `errors.txt` file).
+All of these report `Problem`s to a `sink::ProblemSink`; `.ok_or_report(sink, severity)` can be used with custom sinks (e.g. syslog).
+Pipelines can use `.ok_or_send(&sender)` to forward `Problem`s as `ProblemError`s into a channel handled by a dedicated consumer thread.
+
+In tests and debug builds `strict_mode(true)` can be used to make reporting `Problem`s to logging or printing sinks panic
+instead, so that swallowed errors are not missed. Alternatively `sink::record_problems()` records `Problem`s reported on current thread so that tests
+can assert that none were reported with `no_problems_logged()`.
+
+Use `.ok_discarded()` instead of `.ok()` when ignoring errors intentionally; discarded `Problem`s are reported to a sink set with
+`sink::set_discarded_sink` (if any) so that they can be audited.
+
 # Backtraces
 When compiled with `backtrace` feature (default) formatting of backtraces for `Problem` cause and `panic!` locations can be enabled via
-`RUST_BACKTRACE=1` environment variable.
+`RUST_BACKTRACE=1` environment variable. Like with `std`, `RUST_BACKTRACE=1` renders short backtrace with application frames
+only, one per line (frames of backtrace capture, `Problem` construction, panic handling and runtime startup are omitted) and
+`RUST_BACKTRACE=full` renders all frames with location on separate line (as shown below); use `problem::set_backtrace_style` to
+choose the style regardless.
+The variable is read once; call `problem::refresh_backtrace_policy` after changing it at runtime.
+For services that want backtraces of every `Problem` at minimal cost use
+`problem::set_backtrace_capture(BacktraceCapture::FramePointers)` so that only return addresses are recorded and symbols are
+resolved when the backtrace is displayed.
+
+File paths of frames are shown relative to current directory and cargo registry; use `problem::set_shorten_backtrace_paths(false)`
+to show full paths. Paths of binaries built elsewhere (e.g. in CI) can be remapped with `problem::add_backtrace_path_map` or
+`PROBLEM_PATH_MAP=/build/src=>src` environment variable. Symbol names are shown without hash suffix unless
+`problem::set_raw_backtrace_symbols(true)` is called or `PROBLEM_RAW_SYMBOLS=1` environment variable is set.
 
 ```noformat
 Fatal error: thread 'tests::test_panic_format_stderr_problem' panicked at src/lib.rs:657:35 with: Failed to complete processing task due to: while processing object, while processing input data, while parsing input got error caused by: boom!
@@ -420,8 +562,8 @@ Fatal error: thread 'tests::test_panic_format_stderr_problem' panicked at src/li
 ```
 
 ## Access
-Formatted backtrace `&str` can be accessed via `Problem::backtrace` function that will return `Some` if `backtrace` feature is enabled and `RUST_BACKTRACE=1`
-environment variable is set.
+Formatted backtrace can be accessed via `Problem::backtrace` function that will return `Some` if `backtrace` feature is enabled and `RUST_BACKTRACE=1`
+(or `RUST_BACKTRACE=full`) environment variable is set.
 
 ```rust
 use problem::prelude::*;
@@ -432,700 +574,5311 @@ Problem::from_error("foo").backtrace(); // Some("   0: backtrace...")
 #[cfg(feature = "log")]
 #[macro_use]
 extern crate log;
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary;
+#[cfg(feature = "backtrace")]
+extern crate backtrace;
+#[cfg(feature = "clap")]
+extern crate clap;
+#[cfg(feature = "crossbeam-channel")]
+extern crate crossbeam_channel;
+#[cfg(feature = "crossbeam-utils")]
+extern crate crossbeam_utils;
+#[cfg(feature = "defmt")]
+extern crate defmt;
+#[cfg(feature = "fluent-bundle")]
+extern crate fluent_bundle;
+#[cfg(any(unix, windows))]
+extern crate libc;
+#[cfg(feature = "postcard")]
+extern crate postcard;
+#[cfg(feature = "predicates-core")]
+extern crate predicates_core;
+#[cfg(feature = "proptest")]
+extern crate proptest;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "serde_json")]
+extern crate serde_json;
+#[cfg(all(feature = "signals", unix))]
+extern crate signal_hook;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[cfg(feature = "uuid")]
+extern crate uuid;
+use std::any::Any;
+use std::cell::{Cell, OnceCell};
 use std::error::Error;
 use std::fmt::{self, Display, Write};
+use std::io;
+use std::iter::FusedIterator;
 use std::panic;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, Weak};
+use std::time::{Duration, Instant};
 
 const DEFAULT_FATAL_STATUS: i32 = 1;
 
 /// Includes `Problem` type and related conversion traits and `in_context_of*` functions
 pub mod prelude {
     pub use super::{
-        in_context_of, in_context_of_with, problem, FailedTo, FailedToIter, Fatal, FatalProblem,
-        MapProblem, MapProblemOr, OkOrProblem, Problem, ProblemWhile,
+        in_context_of, in_context_of_with, problem, try_for_each_problem, FailedTo, FailedToIter,
+        Fatal, FatalProblem, FlattenFailedTo, MapProblem, MapProblemOr, OkOrCount, OkOrProblem,
+        OrExit, Problem, ProblemWhile, ProblemWhileWithErr, Problems, RecoverProblem,
+        TakeUntilProblemIter,
     };
 
     pub use super::result::FinalResult;
+    pub use super::typed::ProblemWhileTyped;
     // Note that `result::Result` is not part of prelude as it may conflict with standard library or
     // custom library result types.
 
     #[cfg(feature = "log")]
     pub use super::logged::{OkOrLog, OkOrLogIter};
+
+    #[cfg(feature = "defmt")]
+    pub use super::defmt_logged::{OkOrDefmt, OkOrDefmtIter};
+
+    pub use super::printed::{OkOrPrint, OkOrPrintIter, OkOrWrite, OkOrWriteIter};
+    pub use super::sink::{OkDiscarded, OkOrReport, OkOrReportIter, OkOrSend, OkOrSendIter};
+
+    #[cfg(feature = "process")]
+    pub use super::process::SuccessOrProblem;
 }
 
 /// Wraps error, context and backtrace information and formats it for display.
 /// Data is heap allocated to avoid type parameters or lifetimes.
 #[derive(Debug)]
 pub struct Problem {
-    error: Box<dyn Error>,
+    error: StoredError,
     context: Vec<String>,
-    backtrace: Option<String>,
+    backtrace: Option<StoredBacktrace>,
+    details: Option<Box<Details>>,
 }
 
-impl Problem {
-    /// Create `Problem` from types implementing `Into<Box<dyn Error>>` (including `String` and `&str`) so that `Error::cause`
-    /// chain is followed through in the `Display` message
-    pub fn from_error(error: impl Into<Box<dyn Error>>) -> Problem {
-        Problem {
-            error: error.into(),
-            context: Vec::new(),
-            backtrace: format_backtrace(),
+/// Error of `Problem`; static messages are stored inline so that `Problem::static_msg` can be `const`
+///
+/// Note that the enum discriminant makes `Problem` one word larger than storing `Box<dyn Error>` alone.
+enum StoredError {
+    Static(StaticMessage),
+    Boxed(Box<dyn Error>),
+}
+
+impl StoredError {
+    fn as_ref(&self) -> &(dyn Error + 'static) {
+        match self {
+            StoredError::Static(message) => message,
+            StoredError::Boxed(error) => error.as_ref(),
         }
     }
 
-    /// Same as `Problem::from_error` but stores only final message as `String` and does not take ownership of the error
-    pub fn from_error_message(error: &impl Error) -> Problem {
-        let mut message = String::new();
-        write_error_message(error, &mut message).unwrap();
-
-        Problem {
-            error: message.into(),
-            context: Vec::new(),
-            backtrace: format_backtrace(),
+    fn downcast<E: Error + 'static>(self) -> Result<Box<E>, StoredError> {
+        match self {
+            StoredError::Boxed(error) => error.downcast::<E>().map_err(StoredError::Boxed),
+            StoredError::Static(message) => {
+                let error: Box<dyn Error> = Box::new(message);
+                error.downcast::<E>().map_err(StoredError::Boxed)
+            }
         }
     }
+}
 
-    /// Get backtrace associated with this `Problem` instance if available
-    pub fn backtrace(&self) -> Option<&str> {
-        self.backtrace.as_ref().map(String::as_str)
+impl std::ops::Deref for StoredError {
+    type Target = dyn Error;
+
+    fn deref(&self) -> &(dyn Error + 'static) {
+        self.as_ref()
     }
 }
 
-#[allow(deprecated)]
-fn write_error_message(error: &dyn Error, w: &mut impl Write) -> fmt::Result {
-    write!(w, "{}", error)?;
-
-    let mut error_cause = error;
-    loop {
-        // Note: using Error::cause() here to be backward compatible with older errors
-        if let Some(cause) = error_cause.cause() {
-            write!(w, "; caused by: {}", cause)?;
-            error_cause = cause;
-        } else {
-            break;
-        }
+impl fmt::Debug for StoredError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_ref(), f)
     }
-    Ok(())
 }
 
-impl Display for Problem {
+impl Display for StoredError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if let Some(context) = self.context.last() {
-            write!(f, "while {}", context)?;
-        }
-        for context in self.context.iter().rev().skip(1) {
-            write!(f, ", while {}", context)?;
-        }
-        if !self.context.is_empty() {
-            write!(f, " got error caused by: ")?;
-        }
-
-        write_error_message(self.error.as_ref(), f)?;
+        Display::fmt(self.as_ref(), f)
+    }
+}
 
-        if let Some(backtrace) = self.backtrace.as_ref() {
-            write!(f, "\n--- Cause\n{}", backtrace)?;
-        }
+/// Error message of `Problem` created with `Problem::static_msg`
+#[derive(Debug)]
+struct StaticMessage(&'static str);
 
-        Ok(())
+impl Display for StaticMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.0)
     }
 }
 
-/// Every type implementing `Into<Box<dyn Error>>` trait (including `String` and `&str` types) can be converted to `Problem` via `?` operator
-impl<E> From<E> for Problem
-where
-    E: Into<Box<dyn Error>>,
-{
-    fn from(error: E) -> Problem {
-        Problem::from_error(error)
-    }
+impl Error for StaticMessage {}
+
+/// Additional, less often used information kept out of line to keep `Result<T, Problem>` small
+#[derive(Debug, Default)]
+struct Details {
+    url: Option<String>,
+    status: Option<u16>,
+    exit_code: Option<i32>,
+    sections: Vec<Section>,
+    dropped_contexts: usize,
+    /// Start of the operation the `Problem` was created in; set when context timestamps are enabled (see
+    /// `set_context_timestamps`)
+    started: Option<Instant>,
+    /// Time elapsed since start of the operation when context message with the same index was added
+    context_times: Vec<Option<Duration>>,
+    trace_id: Option<String>,
+    tags: Vec<String>,
+    kind: Option<ProblemKind>,
+    /// Name of the type of the error `Problem` was created from; used to group `Problem`s by root cause
+    error_type: Option<&'static str>,
 }
 
-/// Construct `Err` variant of `Result` containing `Problem` with given message formatted with
-/// `format!` macro.
-#[macro_export]
-macro_rules! problem {
-    ($ ($ arg : tt) *) => { Err(Problem::from_error(format!($($arg)*))) };
+/// Titled block of additional information displayed in long `Problem` report
+struct Section {
+    title: String,
+    body: OnceCell<String>,
+    /// Produces body when it is needed for the first time
+    lazy_body: Cell<Option<Box<dyn FnOnce() -> String>>>,
+    visibility: SectionVisibility,
 }
 
-/// This error type is meant to be used as `main()` result error. It implements `Debug` display so
-/// that the program can terminate with nice message formatted with `Problem` and custom exit
-/// status.
-pub struct FatalProblem {
-    status: i32,
-    problem: Problem,
+/// When section attached to `Problem` is displayed in long report
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum SectionVisibility {
+    /// Always displayed in long report (default)
+    #[default]
+    Always,
+    /// Displayed only if verbosity level is 2 or more (see `set_verbosity`)
+    Verbose,
+    /// Not displayed when `Problem` is logged (e.g. by `ok_or_log_*` methods); useful for large attachments
+    NotInLogs,
 }
 
-impl From<Problem> for FatalProblem {
-    fn from(problem: Problem) -> FatalProblem {
-        FatalProblem {
-            status: DEFAULT_FATAL_STATUS,
-            problem,
-        }
-    }
+/// How much of `Problem` is rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReportStyle {
+    /// One line message
+    Short,
+    /// Long report with sections
+    Long,
+    /// Long report for logging
+    Log,
 }
 
-impl<E> From<E> for FatalProblem
-where
-    E: Into<Box<dyn std::error::Error>>,
-{
-    fn from(error: E) -> FatalProblem {
-        FatalProblem {
-            status: DEFAULT_FATAL_STATUS,
-            problem: Problem::from_error(error),
+impl ReportStyle {
+    fn from_alternate(f: &fmt::Formatter) -> ReportStyle {
+        if f.alternate() {
+            ReportStyle::Long
+        } else {
+            ReportStyle::Short
         }
     }
 }
 
-impl fmt::Debug for FatalProblem {
-    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        eprintln!("{}", self.problem);
-        std::process::exit(self.status)
+impl Section {
+    fn new(title: impl Into<String>, body: String) -> Section {
+        Section {
+            title: title.into(),
+            body: OnceCell::from(body),
+            lazy_body: Cell::new(None),
+            visibility: SectionVisibility::Always,
+        }
     }
-}
 
-/// Extension trait to map `Option` to `Result` with `Problem`
-pub trait Fatal<O> {
-    fn fatal(self) -> Result<O, FatalProblem>;
-    fn fatal_with_status(self, status: i32) -> Result<O, FatalProblem>;
-}
+    fn new_lazy(title: impl Into<String>, body: Box<dyn FnOnce() -> String>) -> Section {
+        Section {
+            title: title.into(),
+            body: OnceCell::new(),
+            lazy_body: Cell::new(Some(body)),
+            visibility: SectionVisibility::Always,
+        }
+    }
 
-/// Extension trait that allow to convert to `Result` with `FatalProblem`
-impl<O> Fatal<O> for Result<O, Problem> {
-    /// Converts to `Result` with `FatalProblem` and default exit status
-    fn fatal(self) -> Result<O, FatalProblem> {
-        self.fatal_with_status(DEFAULT_FATAL_STATUS)
+    fn body(&self) -> &str {
+        self.body
+            .get_or_init(|| self.lazy_body.take().map(|body| body()).unwrap_or_default())
     }
+}
 
-    /// Converts to `Result` with `FatalProblem` and given exit status
-    fn fatal_with_status(self, status: i32) -> Result<O, FatalProblem> {
-        self.map_err(|problem| FatalProblem { status, problem })
+impl fmt::Debug for Section {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Section")
+            .field("title", &self.title)
+            .field("body", &self.body.get())
+            .field("visibility", &self.visibility)
+            .finish()
     }
 }
 
-pub mod result {
-    //! Handy Result types using `Problem` as error.
-    use super::{FatalProblem, Problem};
+impl Problem {
+    /// Create `Problem` from types implementing `Into<Box<dyn Error>>` (including `String` and `&str`) so that `Error::cause`
+    /// chain is followed through in the `Display` message
+    ///
+    /// If the error is `ProblemError` or `io::Error` created from `Problem` the original `Problem` is restored instead. Other
+    /// `io::Error`s are classified as I/O or network failure (see `Problem::kind`).
+    pub fn from_error(error: impl Into<Box<dyn Error>>) -> Problem {
+        let error_type = stored_error_type(&error);
+        match detached::restore_problem(error.into()) {
+            Ok(problem) => problem,
+            Err(error) => {
+                let kind = error
+                    .downcast_ref::<io::Error>()
+                    .map(ProblemKind::of_io_error);
+                let mut problem = Problem::new(error);
+                if let Some(error_type) = error_type {
+                    problem.details_mut().error_type = Some(error_type);
+                }
+                match kind {
+                    Some(kind) => problem.with_kind(kind),
+                    None => problem,
+                }
+            }
+        }
+    }
 
-    /// `Result` with `Problem`
-    pub type Result<T> = std::result::Result<T, Problem>;
+    /// Same as `Problem::from_error` but stores only messages of the error and its causes and does not take ownership of the error
+    ///
+    /// Messages are kept as a chain of `ErrorMessage`s so that the layers of the cause chain can still be distinguished.
+    pub fn from_error_message(error: &impl Error) -> Problem {
+        Problem::from_dyn_error(error)
+    }
 
-    /// `Result` with `FatalProblem` to be used as `main()` return value
-    pub type FinalResult = std::result::Result<(), FatalProblem>;
-}
+    /// Same as `Problem::from_error_message` but for errors available only as trait objects (e.g. returned by other libraries)
+    pub fn from_dyn_error(error: &dyn Error) -> Problem {
+        Problem::new(Box::new(error_message_chain(error)))
+    }
 
-/// Map type containing error to type containing `Problem`
-pub trait MapProblem {
-    type ProblemCarrier;
-    fn map_problem(self) -> Self::ProblemCarrier;
-}
+    /// Same as `Problem::from_dyn_error` but retains `io::ErrorKind` of the first `io::Error` found in the cause chain
+    ///
+    /// This way `Problem::io_kind`, `Problem::is_not_found` and `Problem::status` work as if the error was owned.
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    /// use std::error::Error;
+    /// use std::io;
+    ///
+    /// let error: Box<dyn Error> = Box::new(io::Error::new(io::ErrorKind::NotFound, "config.toml"));
+    /// let problem = Problem::from_dyn_error_static(error.as_ref());
+    ///
+    /// assert!(problem.is_not_found());
+    /// assert_eq!(problem.to_string(), "config.toml");
+    /// ```
+    pub fn from_dyn_error_static(error: &(dyn Error + 'static)) -> Problem {
+        Problem::new(detach_error(error))
+    }
 
-/// Mapping `Result` with error to `Result` with `Problem`
-impl<O, E> MapProblem for Result<O, E>
-where
-    E: Into<Problem>,
-{
-    type ProblemCarrier = Result<O, Problem>;
-    fn map_problem(self) -> Result<O, Problem> {
-        self.map_err(|e| e.into())
+    /// Create `Problem` from error with multiple causes
+    ///
+    /// The error message is followed by the number of causes and in long report by a tree with one branch per cause
+    /// (see `Problems`). Causes are stored as with `Problem::from_dyn_error_static`.
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    /// use problem::MultiSource;
+    /// use std::error::Error;
+    /// use std::fmt;
+    ///
+    /// #[derive(Debug)]
+    /// struct JoinError(Vec<Box<dyn Error>>);
+    ///
+    /// impl fmt::Display for JoinError {
+    ///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    ///         write!(f, "tasks failed")
+    ///     }
+    /// }
+    ///
+    /// impl Error for JoinError {}
+    ///
+    /// impl MultiSource for JoinError {
+    ///     fn sources(&self) -> Vec<&(dyn Error + 'static)> {
+    ///         self.0.iter().map(|error| error.as_ref()).collect()
+    ///     }
+    /// }
+    ///
+    /// let error = JoinError(vec!["disk full".into(), "timeout".into()]);
+    /// let problem = Problem::from_multi_source(&error);
+    ///
+    /// assert_eq!(problem.to_string(), "tasks failed: 2 problems occurred: disk full; timeout");
+    /// ```
+    pub fn from_multi_source(error: &(impl MultiSource + ?Sized)) -> Problem {
+        let mut problems = Problems::new().with_message(error);
+        for source in error.sources() {
+            problems.push(Problem::from_dyn_error_static(source));
+        }
+        Problem::from_error(problems)
     }
-}
 
-/// Map type not containing any error to type containing given `Problem`
-pub trait MapProblemOr {
-    type ProblemCarrier;
-    fn map_problem_or(self, problem: impl Into<Problem>) -> Self::ProblemCarrier;
-    fn map_problem_or_else<F, P>(self, problem: F) -> Self::ProblemCarrier
-    where
-        F: FnOnce() -> P,
-        P: Into<Problem>;
-}
+    /// Create `Problem` from payload of panic caught with `std::panic::catch_unwind` or `JoinHandle::join`
+    ///
+    /// Payloads are converted as follows:
+    /// * `ProblemError` (e.g. raised with `resume_with`) is converted back to the original `Problem`,
+    /// * `Box<dyn Error + Send + Sync>` is used as the error,
+    /// * `&str` and `String` (raised by `panic!`) are used as the error message,
+    /// * other payloads are represented with `Box<dyn Any>` message as in standard library panic messages.
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    /// use std::panic;
+    ///
+    /// let payload = panic::catch_unwind(|| {
+    ///     let _: u8 = Err(Problem::from_error("boom!")).or_failed_to("compute");
+    /// }).unwrap_err();
+    ///
+    /// assert_eq!(Problem::from_panic_payload(payload).to_string(), "Failed to compute due to: boom!");
+    /// ```
+    pub fn from_panic_payload(payload: Box<dyn Any + Send>) -> Problem {
+        let payload = match payload.downcast::<ProblemError>() {
+            Ok(problem) => return ProblemError::into_problem(*problem),
+            Err(payload) => payload,
+        };
+        let payload = match payload.downcast::<Box<dyn Error + Send + Sync>>() {
+            Ok(error) => return Problem::from_error(*error as Box<dyn Error>),
+            Err(payload) => payload,
+        };
+        let payload = match payload.downcast::<String>() {
+            Ok(message) => return Problem::from_error(*message),
+            Err(payload) => payload,
+        };
+        match payload.downcast::<&'static str>() {
+            Ok(message) => Problem::from_error(*message),
+            Err(_) => Problem::from_error("Box<dyn Any>"),
+        }
+    }
 
-/// Mapping `Result` with `Option<E>` to `Result` with `Problem` where `E` implements `Into<Problem>`
-impl<O, E> MapProblemOr for Result<O, Option<E>>
-where
-    E: Into<Problem>,
-{
-    type ProblemCarrier = Result<O, Problem>;
+    /// Create `Problem` with static error message without capturing backtrace
+    ///
+    /// This can be used in `const` items to predefine sentinel `Problem`s (e.g. for table-driven error handling); every use of
+    /// the item creates new `Problem`. No memory is allocated until context or other information is added. `Problem`s created
+    /// this way are not counted by `problems_created` and are not passed to creation hook.
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    ///
+    /// const NOT_READY: Problem = Problem::static_msg("device not ready");
+    /// const BAD_CHECKSUM: Problem = Problem::static_msg("checksum mismatch");
+    ///
+    /// fn check(status: u8) -> Result<(), Problem> {
+    ///     match status {
+    ///         0 => Ok(()),
+    ///         1 => Err(NOT_READY),
+    ///         _ => Err(BAD_CHECKSUM),
+    ///     }
+    /// }
+    ///
+    /// assert!(check(0).is_ok());
+    /// assert_eq!(check(2).problem_while("reading frame").unwrap_err().to_string(), "while reading frame got error caused by: checksum mismatch");
+    /// assert!(NOT_READY.backtrace().is_none());
+    /// ```
+    ///
+    /// `Problem` is not `Sync` so it cannot be stored in `static` items; use `const` items instead:
+    ///
+    /// ```rust,compile_fail
+    /// use problem::Problem;
+    ///
+    /// static NOT_READY: Problem = Problem::static_msg("device not ready");
+    /// ```
+    pub const fn static_msg(message: &'static str) -> Problem {
+        Problem {
+            error: StoredError::Static(StaticMessage(message)),
+            context: Vec::new(),
+            backtrace: None,
+            details: None,
+        }
+    }
 
-    fn map_problem_or(self, problem: impl Into<Problem>) -> Result<O, Problem> {
-        self.map_err(|e| e.map(Into::into).unwrap_or_else(|| problem.into()))
+    /// Create fully populated `Problem` from its parts without capturing backtrace
+    ///
+    /// Context messages are given in order they would be added with `problem_while` (innermost first, see `contexts`) and are
+    /// subject to maximum context depth (see `set_max_context_depth`).
+    pub fn from_parts(
+        error: impl Into<Box<dyn Error>>,
+        contexts: Vec<String>,
+        backtrace: Option<String>,
+    ) -> Problem {
+        let mut problem = Problem {
+            error: StoredError::Boxed(error.into()),
+            context: Vec::with_capacity(contexts.len().min(max_context_depth())),
+            backtrace: backtrace.map(StoredBacktrace::new),
+            details: None,
+        };
+        for context in contexts {
+            problem = problem.push_context(context);
+        }
+        problem_created(&problem);
+        problem
     }
 
-    fn map_problem_or_else<F, P>(self, problem: F) -> Self::ProblemCarrier
-    where
-        F: FnOnce() -> P,
-        P: Into<Problem>,
-    {
-        self.map_err(|e| e.map(Into::into).unwrap_or_else(|| problem().into()))
+    fn new(error: Box<dyn Error>) -> Problem {
+        let mut problem = Problem {
+            error: StoredError::Boxed(error),
+            context: Vec::new(),
+            backtrace: capture_backtrace(),
+            details: None,
+        };
+        if CONTEXT_TIMESTAMPS.load(Ordering::Relaxed) {
+            problem.details_mut().started = Some(operation_start());
+        }
+        if let Some(trace_id) = current_trace_id() {
+            problem.details_mut().trace_id = Some(trace_id);
+        }
+        problem_created(&problem);
+        problem
     }
-}
 
-/// Extension trait to map `Option` to `Result` with `Problem`
-pub trait OkOrProblem<O> {
-    fn ok_or_problem<P>(self, problem: P) -> Result<O, Problem>
-    where
-        P: Into<Problem>;
-    fn ok_or_problem_with<F, P>(self, problem: F) -> Result<O, Problem>
-    where
-        F: FnOnce() -> P,
-        P: Into<Problem>;
-}
+    /// Get backtrace associated with this `Problem` instance if available
+    ///
+    /// Backtrace that was not retained to stay within memory budget (see `set_backtrace_memory_budget`) is displayed as
+    /// `BACKTRACE_DROPPED`.
+    pub fn backtrace(&self) -> Option<&str> {
+        self.backtrace.as_ref().map(StoredBacktrace::get)
+    }
 
-impl<O> OkOrProblem<O> for Option<O> {
-    fn ok_or_problem<P>(self, problem: P) -> Result<O, Problem>
+    /// Get back error of type `E` this `Problem` was created from, otherwise return the `Problem` unchanged
+    ///
+    /// `io::Error`s wrapping other errors (e.g. created with `io::Error::other`) are unwrapped towards the root cause so that
+    /// their inner error can be recovered. Context and other information associated with the `Problem` are dropped when
+    /// recovered.
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    /// use std::io;
+    /// use std::num::ParseIntError;
+    ///
+    /// let error = io::Error::other("x".parse::<u8>().unwrap_err());
+    /// let problem = Problem::from_error(error).problem_while("reading count");
+    /// assert!(problem.recover_if::<ParseIntError>().is_ok());
+    /// ```
+    pub fn recover_if<E>(self) -> Result<E, Problem>
     where
-        P: Into<Problem>,
+        E: Error + 'static,
     {
-        self.ok_or_else(|| problem.into())
+        // Check first so that the `Problem` is kept intact if the error cannot be recovered
+        let mut depth = 0;
+        let mut error = self.error.as_ref();
+        while !error.is::<E>() {
+            match error
+                .downcast_ref::<io::Error>()
+                .and_then(io::Error::get_ref)
+            {
+                Some(inner) => error = inner,
+                None => return Err(self),
+            }
+            depth += 1;
+        }
+
+        let mut error = self.error;
+        for _ in 0..depth {
+            let inner = error
+                .downcast::<io::Error>()
+                .ok()
+                .and_then(|error| error.into_inner())
+                .expect("io::Error with inner error");
+            error = StoredError::Boxed(inner);
+        }
+        Ok(*error.downcast::<E>().expect("error of recovered type"))
     }
 
-    fn ok_or_problem_with<F, P>(self, problem: F) -> Result<O, Problem>
+    /// Get reference to the first error of type `E` found in the error cause chain (via `Error::source`)
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    /// use std::num::ParseIntError;
+    ///
+    /// let problem = "x".parse::<u8>().problem_while("parsing count").unwrap_err();
+    /// assert!(problem.find_cause::<ParseIntError>().is_some());
+    /// ```
+    pub fn find_cause<E>(&self) -> Option<&E>
     where
-        F: FnOnce() -> P,
-        P: Into<Problem>,
+        E: Error + 'static,
     {
-        self.ok_or_else(|| problem().into())
+        self.causes().find_map(|cause| cause.downcast_ref::<E>())
     }
-}
 
-/// Convert to `Problem` if needed and add context to it
-pub trait ProblemWhile {
-    type WithContext;
+    /// Associate documentation URL (e.g. describing remediation steps) with this `Problem`
+    ///
+    /// The URL is displayed as trailing `see: URL` line in long report and logs and is used as RFC 7807 `type` member (see
+    /// `serialized::ProblemDetails`).
+    pub fn with_url(mut self, url: impl ToString) -> Problem {
+        self.details_mut().url = Some(url.to_string());
+        self
+    }
 
-    /// Add context information
-    fn problem_while(self, message: impl ToString) -> Self::WithContext;
+    /// Get documentation URL associated with this `Problem` instance if available
+    pub fn url(&self) -> Option<&str> {
+        self.details
+            .as_ref()
+            .and_then(|details| details.url.as_deref())
+    }
 
-    /// Add context information from function call
-    fn problem_while_with<F, M>(self, message: F) -> Self::WithContext
-    where
-        F: FnOnce() -> M,
-        M: ToString;
-}
+    /// Associate trace or correlation ID with this `Problem` so it can be joined with distributed traces
+    ///
+    /// New `Problem`s get the current trace ID of the thread automatically (see `set_current_trace_id`).
+    /// The ID is displayed as `trace id: ID` line in long report.
+    pub fn with_trace_id(mut self, id: impl ToString) -> Problem {
+        self.details_mut().trace_id = Some(id.to_string());
+        self
+    }
 
-impl ProblemWhile for Problem {
-    type WithContext = Problem;
+    /// Get trace or correlation ID associated with this `Problem` instance if available
+    pub fn trace_id(&self) -> Option<&str> {
+        self.details
+            .as_ref()
+            .and_then(|details| details.trace_id.as_deref())
+    }
 
-    fn problem_while(mut self, message: impl ToString) -> Problem {
-        self.context.push(message.to_string());
+    /// Associate HTTP status code with this `Problem`
+    pub fn with_status(mut self, status: u16) -> Problem {
+        self.details_mut().status = Some(status);
         self
     }
 
-    fn problem_while_with<F, M>(self, message: F) -> Problem
-    where
-        F: FnOnce() -> M,
-        M: ToString,
-    {
-        self.problem_while(message())
+    /// Get HTTP status code associated with this `Problem`
+    ///
+    /// If status was not set with `Problem::with_status` it is derived from the cause chain:
+    /// * `io::ErrorKind::NotFound` - 404,
+    /// * `io::ErrorKind::PermissionDenied` - 403,
+    /// * `io::ErrorKind::TimedOut` - 504,
+    /// * `io::ErrorKind::AlreadyExists` - 409,
+    /// * `io::ErrorKind::InvalidInput` - 400,
+    /// * otherwise - 500.
+    pub fn status(&self) -> u16 {
+        let status = self.details.as_ref().and_then(|details| details.status);
+        status.unwrap_or_else(|| {
+            self.causes()
+                .filter_map(|cause| cause.downcast_ref::<io::Error>())
+                .find_map(|error| io_error_status(error.kind()))
+                .unwrap_or(500)
+        })
     }
-}
 
-impl<O, E> ProblemWhile for Result<O, E>
-where
-    E: Into<Problem>,
-{
-    type WithContext = Result<O, Problem>;
-
-    fn problem_while(self, message: impl ToString) -> Result<O, Problem> {
-        self.map_err(|err| err.into().problem_while(message))
+    /// Get kind of the first `io::Error` found in the error cause chain
+    pub fn io_kind(&self) -> Option<io::ErrorKind> {
+        self.find_cause::<io::Error>().map(io::Error::kind)
     }
 
-    fn problem_while_with<F, M>(self, message: F) -> Result<O, Problem>
-    where
-        F: FnOnce() -> M,
-        M: ToString,
-    {
-        self.map_err(|err| err.into().problem_while_with(message))
+    /// Key used to group `Problem`s by root cause; see `Problems::group_by_cause`
+    fn root_cause_key(&self) -> String {
+        if let Some(kind) = self.io_kind() {
+            return format!("{:?}", kind);
+        }
+        let root_cause = match self.causes().last() {
+            Some(root_cause) => root_cause,
+            None => return String::new(),
+        };
+        // Messages carry no type information
+        if root_cause.is::<ErrorMessage>() || root_cause.is::<StaticMessage>() {
+            return root_cause.to_string();
+        }
+        // Type of the stored error is known only if it was recorded when the `Problem` was created; types of its sources
+        // can only be found by downcasting
+        let stored_type = match self.error.source() {
+            None => self.details.as_ref().and_then(|details| details.error_type),
+            Some(_) => None,
+        };
+        match stored_type.or_else(|| std_error_type(root_cause)) {
+            Some(type_name) => short_type_name(type_name).to_owned(),
+            None => root_cause.to_string(),
+        }
     }
-}
 
-/// Executes closure with `problem_while` context
-pub fn in_context_of<O, B>(message: &str, body: B) -> Result<O, Problem>
-where
-    B: FnOnce() -> Result<O, Problem>,
-{
-    body().problem_while(message)
-}
+    /// Returns `true` if the error was caused by `io::Error` of `NotFound` kind
+    pub fn is_not_found(&self) -> bool {
+        self.io_kind() == Some(io::ErrorKind::NotFound)
+    }
 
-/// Executes closure with `problem_while_with` context
-pub fn in_context_of_with<O, F, M, B>(message: F, body: B) -> Result<O, Problem>
-where
-    F: FnOnce() -> M,
-    M: ToString,
-    B: FnOnce() -> Result<O, Problem>,
-{
-    body().problem_while_with(message)
-}
+    /// Returns `true` if the error was caused by `io::Error` of `PermissionDenied` kind
+    pub fn is_permission_denied(&self) -> bool {
+        self.io_kind() == Some(io::ErrorKind::PermissionDenied)
+    }
 
-/// Extension of `Result` that allows program to panic with `Display` message on `Err` for fatal application errors that are not bugs
-pub trait FailedTo<O> {
-    fn or_failed_to(self, message: impl Display) -> O;
-}
+    /// Associate program exit status with this `Problem` used when it is converted to `FatalProblem`
+    pub fn with_exit_code(mut self, exit_code: i32) -> Problem {
+        self.details_mut().exit_code = Some(exit_code);
+        self
+    }
 
-impl<O, E> FailedTo<O> for Result<O, E>
-where
-    E: Into<Problem>,
-{
-    fn or_failed_to(self, message: impl Display) -> O {
-        self.unwrap_or_else(|err| panic!("Failed to {} due to: {}", message, err.into()))
+    /// Get program exit status associated with this `Problem` if available
+    pub fn exit_code(&self) -> Option<i32> {
+        self.details.as_ref().and_then(|details| details.exit_code)
     }
-}
 
-impl<O> FailedTo<O> for Option<O> {
-    fn or_failed_to(self, message: impl Display) -> O {
-        self.unwrap_or_else(|| panic!("Failed to {}", message))
+    /// Tag this `Problem` with category (e.g. `config`) used to look up exit code in `exit_codes` table
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Problem {
+        self.details_mut().tags.push(tag.into());
+        self
     }
-}
 
-/// Iterator that will panic on first error with message displaying `Display` formatted message
-pub struct ProblemIter<I, M> {
-    inner: I,
-    message: M,
-}
+    /// Iterate tags of this `Problem` in order they were added
+    pub fn tags(&self) -> impl Iterator<Item = &str> {
+        self.details
+            .iter()
+            .flat_map(|details| &details.tags)
+            .map(String::as_str)
+    }
 
-impl<I, O, E, M> Iterator for ProblemIter<I, M>
-where
-    I: Iterator<Item = Result<O, E>>,
-    E: Into<Problem>,
-    M: Display,
-{
-    type Item = O;
+    /// Returns `true` if this `Problem` was tagged with given tag
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags().any(|candidate| candidate == tag)
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|res| res.or_failed_to(&self.message))
+    /// Exit code associated with this `Problem` or mapped from its first tag found in given table
+    fn map_exit_code(&self, table: &[(String, i32)]) -> Option<i32> {
+        self.exit_code().or_else(|| {
+            self.tags().find_map(|tag| {
+                table
+                    .iter()
+                    .find(|(mapped, _)| mapped == tag)
+                    .map(|&(_, code)| code)
+            })
+        })
     }
-}
 
-/// Convert `Iterator` of `Result<O, E>` to iterator of `O` and panic on first `E` with problem message
-pub trait FailedToIter<O, E, M>: Sized {
-    fn or_failed_to(self, message: M) -> ProblemIter<Self, M>;
-}
+    /// Exit code associated with this `Problem`, registered for its kind (see `register_kind`) or mapped from its tags with
+    /// `exit_codes` table
+    fn fatal_exit_code(&self) -> Option<i32> {
+        self.exit_code()
+            .or_else(|| self.kind_policy().exit_code)
+            .or_else(|| {
+                self.map_exit_code(
+                    &EXIT_CODES
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner()),
+                )
+            })
+    }
 
-impl<I, O, E, M> FailedToIter<O, E, M> for I
-where
-    I: Iterator<Item = Result<O, E>>,
-    E: Into<Problem>,
-    M: Display,
-{
-    fn or_failed_to(self, message: M) -> ProblemIter<Self, M> {
-        ProblemIter {
-            inner: self,
-            message: message,
+    /// Capture values of given environment variables to be displayed in long report (alternate `Display` format)
+    pub fn with_env(self, names: &[&str]) -> Problem {
+        let mut body = String::new();
+        for name in names {
+            if !body.is_empty() {
+                body.push('\n');
+            }
+            match std::env::var_os(name) {
+                Some(value) => write!(body, "{}={}", name, value.to_string_lossy()).unwrap(),
+                None => write!(body, "{} is not set", name).unwrap(),
+            }
         }
+
+        self.with_section("Environment", body)
     }
-}
 
-#[cfg(feature = "log")]
-pub mod logged {
-    use super::*;
-    use log::{error, warn};
+    /// Attach titled block of additional information (e.g. failing query or request body) to be displayed in long report
+    /// (alternate `Display` format)
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    ///
+    /// let problem = Problem::from_error("syntax error").with_section("SQL", "SELECT * FORM users");
+    /// # if problem.backtrace().is_none() {
+    /// assert_eq!(format!("{:#}", problem), "syntax error\n--- SQL\nSELECT * FORM users");
+    /// # }
+    /// ```
+    pub fn with_section(mut self, title: impl Into<String>, body: impl ToString) -> Problem {
+        self.details_mut()
+            .sections
+            .push(Section::new(title, body.to_string()));
+        self
+    }
 
-    /// Extension of `Result` that allows program to log on `Err` with `Display` message for application errors that are not critical
-    pub trait OkOrLog<O> {
-        fn ok_or_log_warn(self) -> Option<O>;
-        fn ok_or_log_error(self) -> Option<O>;
+    /// Attach hex dump of given bytes (e.g. offending network packet) as titled section
+    ///
+    /// The dump is formatted like `hexdump -C` output and limited to first `HEX_DUMP_MAX_BYTES` bytes.
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    ///
+    /// let problem = Problem::from_error("bad packet").with_hex_dump("Packet", b"\x01\x02GET /\r\n");
+    /// # if problem.backtrace().is_none() {
+    /// assert_eq!(format!("{:#}", problem), "bad packet\n--- Packet\n\
+    ///     00000000  01 02 47 45 54 20 2f 0d  0a                       |..GET /..|");
+    /// # }
+    /// ```
+    pub fn with_hex_dump(self, title: impl Into<String>, bytes: &[u8]) -> Problem {
+        self.with_section(title, format_hex_dump(bytes, HEX_DUMP_MAX_BYTES))
     }
 
-    impl<O, E> OkOrLog<O> for Result<O, E>
+    /// Same as `with_section` but the body is produced by given closure only when it is needed for the first time
+    pub fn with_section_lazy<F, B>(mut self, title: impl Into<String>, body: F) -> Problem
     where
-        E: Into<Problem>,
+        F: FnOnce() -> B + 'static,
+        B: ToString,
     {
-        fn ok_or_log_warn(self) -> Option<O> {
-            self.map_err(|err| warn!("Continuing with error: {}", err.into()))
-                .ok()
-        }
+        self.details_mut()
+            .sections
+            .push(Section::new_lazy(title, Box::new(|| body().to_string())));
+        self
+    }
 
-        fn ok_or_log_error(self) -> Option<O> {
-            self.map_err(|err| error!("Continuing with error: {}", err.into()))
-                .ok()
+    /// Set visibility of attached sections with given title (see `SectionVisibility`)
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    /// use problem::SectionVisibility;
+    ///
+    /// let problem = Problem::from_error("bad response")
+    ///     .with_section("Body", "<html>...</html>")
+    ///     .with_section_visibility("Body", SectionVisibility::NotInLogs);
+    /// ```
+    pub fn with_section_visibility(
+        mut self,
+        title: &str,
+        visibility: SectionVisibility,
+    ) -> Problem {
+        for section in self
+            .details_mut()
+            .sections
+            .iter_mut()
+            .filter(|section| section.title == title)
+        {
+            section.visibility = visibility;
         }
+        self
     }
 
-    /// Iterator that will log as warn `Display` formatted message on `Err` and skip to next item; it can be flattened to skip failed items
-    pub struct ProblemWarnLoggingIter<I> {
-        inner: I,
+    /// Iterate titles and bodies of attached sections in order they were added
+    pub fn sections(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.details
+            .iter()
+            .flat_map(|details| &details.sections)
+            .map(|section| (section.title.as_str(), section.body()))
     }
 
-    impl<I, O, E> Iterator for ProblemWarnLoggingIter<I>
-    where
-        I: Iterator<Item = Result<O, E>>,
-        E: Into<Problem>,
-    {
-        type Item = Option<O>;
+    /// Iterate context messages in order they were added (innermost first)
+    ///
+    /// If maximum context depth was reached (see `set_max_context_depth`) only innermost and outermost contexts are available.
+    ///
+    /// Note that when displayed, repeated adjacent context messages are collapsed (e.g. `while resolving symlink (x12)`).
+    pub fn contexts(&self) -> impl DoubleEndedIterator<Item = &str> + ExactSizeIterator {
+        self.context.iter().map(String::as_str)
+    }
 
-        fn next(&mut self) -> Option<Self::Item> {
-            self.inner.next().map(|res| res.ok_or_log_warn())
+    /// Format context messages as they are displayed before the error message, without the error message itself
+    ///
+    /// Returns empty string if this `Problem` has no context.
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    ///
+    /// let problem = Problem::from_error("boom!")
+    ///     .problem_while("parsing input")
+    ///     .problem_while("processing object");
+    ///
+    /// assert_eq!(problem.context_string(), "while processing object, while parsing input");
+    /// assert_eq!(Problem::from_error("boom!").context_string(), "");
+    /// ```
+    pub fn context_string(&self) -> String {
+        let mut out = String::new();
+        self.write_context_chain(&mut out, &DisplayOptions::default())
+            .ok();
+        out
+    }
+
+    /// Format this `Problem` as single line of `logfmt` key-value pairs
+    ///
+    /// Keys are: `msg` for error message, `context.N` for context messages (outermost first), `cause.N` for
+    /// messages of the error cause chain and `url` if available.
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    ///
+    /// let problem = Problem::from_error("boom!").problem_while("parsing \"input\"");
+    /// assert_eq!(problem.to_logfmt(), r#"msg="boom!" context.0="parsing \"input\"""#);
+    /// ```
+    #[allow(deprecated)]
+    pub fn to_logfmt(&self) -> String {
+        let mut out = String::new();
+        write_logfmt_pair(&mut out, "msg", &self.error);
+
+        for (no, context) in self.context.iter().rev().enumerate() {
+            write_logfmt_pair(&mut out, &format!("context.{}", no), context);
         }
+
+        for (no, cause) in error_chain(self.error.as_ref()).skip(1).enumerate() {
+            write_logfmt_pair(&mut out, &format!("cause.{}", no), cause);
+        }
+
+        if let Some(url) = self.url() {
+            write_logfmt_pair(&mut out, "url", &url);
+        }
+        if let Some(trace_id) = self.trace_id() {
+            write_logfmt_pair(&mut out, "trace_id", &trace_id);
+        }
+        out
     }
 
-    /// Iterator that will log as error `Display` formatted message on `Err` and skip to next item; it can be flattened to skip failed items
-    pub struct ProblemErrorLoggingIter<I> {
-        inner: I,
+    fn details_mut(&mut self) -> &mut Details {
+        self.details.get_or_insert_with(Default::default)
     }
 
-    impl<I, O, E> Iterator for ProblemErrorLoggingIter<I>
-    where
-        I: Iterator<Item = Result<O, E>>,
-        E: Into<Problem>,
-    {
-        type Item = Option<O>;
+    /// Iterate error and its causes (via `Error::source`); limited to `MAX_ERROR_CHAIN_DEPTH` errors in case of cycles
+    fn causes(&self) -> impl Iterator<Item = &(dyn Error + 'static)> {
+        std::iter::successors(Some(self.error.as_ref()), |&error| error.source())
+            .take(MAX_ERROR_CHAIN_DEPTH)
+    }
+}
 
-        fn next(&mut self) -> Option<Self::Item> {
-            self.inner.next().map(|res| res.ok_or_log_error())
+fn io_error_status(kind: io::ErrorKind) -> Option<u16> {
+    match kind {
+        io::ErrorKind::NotFound => Some(404),
+        io::ErrorKind::PermissionDenied => Some(403),
+        io::ErrorKind::TimedOut => Some(504),
+        io::ErrorKind::AlreadyExists => Some(409),
+        io::ErrorKind::InvalidInput => Some(400),
+        _ => None,
+    }
+}
+
+fn write_logfmt_pair(out: &mut String, key: &str, value: &dyn Display) {
+    if !out.is_empty() {
+        out.push(' ');
+    }
+    out.push_str(key);
+    out.push_str("=\"");
+    for c in value.to_string().chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
         }
     }
+    out.push('"');
+}
 
-    /// Convert `Iterator` of `Result<O, E>` to iterator of `Option<O>` and log any `Err` variants
-    pub trait OkOrLogIter<O, E>: Sized {
-        fn ok_or_log_warn(self) -> ProblemWarnLoggingIter<Self>;
-        fn ok_or_log_error(self) -> ProblemErrorLoggingIter<Self>;
+/// Error consisting of message and optional cause; used to represent error cause chains when original error types are not available
+/// (e.g. received from other process)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorMessage {
+    message: String,
+    source: Option<Box<ErrorMessage>>,
+}
+
+impl ErrorMessage {
+    /// Create error with given message and no cause
+    pub fn new(message: impl Into<String>) -> ErrorMessage {
+        ErrorMessage {
+            message: message.into(),
+            source: None,
+        }
     }
 
-    impl<I, O, E> OkOrLogIter<O, E> for I
+    /// Set cause of this error
+    pub fn with_source(mut self, source: ErrorMessage) -> ErrorMessage {
+        self.source = Some(Box::new(source));
+        self
+    }
+
+    /// Create error from messages of error followed by messages of its causes
+    ///
+    /// Returns `None` if there are no messages.
+    pub fn from_chain<I>(messages: I) -> Option<ErrorMessage>
     where
-        I: Iterator<Item = Result<O, E>>,
-        E: Into<Problem>,
+        I: IntoIterator,
+        I::IntoIter: DoubleEndedIterator,
+        I::Item: Into<String>,
     {
-        fn ok_or_log_warn(self) -> ProblemWarnLoggingIter<Self> {
-            ProblemWarnLoggingIter { inner: self }
-        }
+        messages.into_iter().rev().fold(None, |source, message| {
+            let error = ErrorMessage::new(message);
+            Some(match source {
+                Some(source) => error.with_source(source),
+                None => error,
+            })
+        })
+    }
 
-        fn ok_or_log_error(self) -> ProblemErrorLoggingIter<Self> {
-            ProblemErrorLoggingIter { inner: self }
-        }
+    /// Get message of this error (without causes)
+    pub fn message(&self) -> &str {
+        &self.message
     }
 }
 
-#[cfg(not(feature = "backtrace"))]
-fn format_backtrace() -> Option<String> {
-    None
+impl Display for ErrorMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
 }
 
-/* Use default Rust format like:
-   0: std::sys_common::backtrace::_print
-             at C:\projects\rust\src\libstd\sys_common\backtrace.rs:94
-   1: std::sys_common::backtrace::_print
-             at C:\projects\rust\src\libstd\sys_common\backtrace.rs:71
-*/
-#[cfg(feature = "backtrace")]
-#[inline(always)]
-fn format_backtrace() -> Option<String> {
-    if let Ok("1") = std::env::var("RUST_BACKTRACE").as_ref().map(String::as_str) {
-        let mut backtrace = String::new();
-        let mut frame_no: u32 = 0;
+impl Error for ErrorMessage {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|source| source.as_ref() as &(dyn Error + 'static))
+    }
+}
 
-        backtrace::trace(|frame| {
-            let ip = frame.ip();
+/// Maximum number of errors followed in error cause chain
+const MAX_ERROR_CHAIN_DEPTH: usize = 64;
 
-            if frame_no > 0 {
-                backtrace.push_str("\n");
-            }
+/// Iterate error and its causes following `Error::source`
+///
+/// Falls back to deprecated `Error::cause` to be backward compatible with older errors that implement only it.
+/// Iteration stops on error already seen in the chain (e.g. returning itself as source) or after `MAX_ERROR_CHAIN_DEPTH` errors.
+#[allow(deprecated)]
+pub(crate) fn error_chain(error: &dyn Error) -> impl Iterator<Item = &dyn Error> {
+    let mut seen: Vec<&dyn Error> = Vec::new();
+    std::iter::successors(Some(error), |&error| {
+        error
+            .source()
+            .map(|source| source as &dyn Error)
+            .or_else(|| error.cause())
+    })
+    .take(MAX_ERROR_CHAIN_DEPTH)
+    .take_while(move |&error| {
+        if seen.iter().any(|&seen| same_error(seen, error)) {
+            return false;
+        }
+        seen.push(error);
+        true
+    })
+}
 
-            backtrace::resolve(ip, |symbol| {
-                if let Some(name) = symbol.name() {
-                    write!(backtrace, "{:4}: {}", frame_no, name).unwrap();
-                }
-                if let (Some(filename), Some(lineno)) = (symbol.filename(), symbol.lineno()) {
-                    write!(
-                        backtrace,
-                        "\n             at {}:{}",
-                        filename.display(),
-                        lineno
-                    )
-                    .unwrap();
-                }
-            });
+/// Name of the type of error `Problem` is created from; `None` for messages and errors of erased type
+fn stored_error_type<E>(_error: &E) -> Option<&'static str> {
+    let type_name = std::any::type_name::<E>();
+    let untyped = [
+        std::any::type_name::<&str>(),
+        std::any::type_name::<String>(),
+        std::any::type_name::<std::borrow::Cow<str>>(),
+        std::any::type_name::<Box<dyn Error>>(),
+        std::any::type_name::<Box<dyn Error + Send + Sync>>(),
+    ];
+    if untyped.contains(&type_name) {
+        None
+    } else {
+        Some(type_name)
+    }
+}
 
-            frame_no += 1;
-            true // keep going to the next frame
-        });
+/// Name of the type of common standard library error found by downcasting
+fn std_error_type(error: &(dyn Error + 'static)) -> Option<&'static str> {
+    fn type_of<E: Error + 'static>(error: &(dyn Error + 'static)) -> Option<&'static str> {
+        error
+            .downcast_ref::<E>()
+            .map(|_| std::any::type_name::<E>())
+    }
+    type_of::<std::num::ParseIntError>(error)
+        .or_else(|| type_of::<std::num::ParseFloatError>(error))
+        .or_else(|| type_of::<std::num::TryFromIntError>(error))
+        .or_else(|| type_of::<std::str::ParseBoolError>(error))
+        .or_else(|| type_of::<std::str::Utf8Error>(error))
+        .or_else(|| type_of::<std::string::FromUtf8Error>(error))
+        .or_else(|| type_of::<std::net::AddrParseError>(error))
+        .or_else(|| type_of::<fmt::Error>(error))
+}
 
-        Some(backtrace)
-    } else {
-        None
+/// Type name without module path and type parameters (e.g. `PoisonError` for `std::sync::PoisonError<T>`)
+fn short_type_name(type_name: &str) -> &str {
+    let path = type_name.split('<').next().unwrap_or(type_name);
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+/// Check if both references point to the same error
+///
+/// Wrapping errors may share the address with their source (e.g. when source is the first field or they are zero sized)
+/// and vtables are not guaranteed to be unique, so messages are compared as well.
+fn same_error(a: &dyn Error, b: &dyn Error) -> bool {
+    std::ptr::addr_eq(a, b)
+        && std::mem::size_of_val(a) == std::mem::size_of_val(b)
+        && a.to_string() == b.to_string()
+}
+
+/// Copies messages of error and its causes into `ErrorMessage` chain wrapped in `io::Error` of the same kind as the first
+/// `io::Error` found in the cause chain (if any)
+fn detach_error(error: &(dyn Error + 'static)) -> Box<dyn Error + Send + Sync> {
+    let message = error_message_chain(error);
+    let io_kind = std::iter::successors(Some(error), |&error| error.source())
+        .take(MAX_ERROR_CHAIN_DEPTH)
+        .find_map(|cause| cause.downcast_ref::<io::Error>())
+        .map(io::Error::kind);
+    match io_kind {
+        Some(kind) => Box::new(io::Error::new(kind, message)),
+        None => Box::new(message),
     }
 }
 
-fn format_panic(panic: &std::panic::PanicInfo, backtrace: Option<String>) -> String {
-    let mut message = String::new();
+/// Copies messages of error and its causes into `ErrorMessage` chain
+fn error_message_chain(error: &dyn Error) -> ErrorMessage {
+    let messages: Vec<String> = error_chain(error).map(ToString::to_string).collect();
+    ErrorMessage::from_chain(messages).expect("error chain has at least one message")
+}
 
-    let thread = std::thread::current();
-    let name = thread.name().unwrap_or("<unnamed>");
+/// Writes error message followed by messages of its causes; messages are written verbatim if `render` is `None`
+fn write_error_message(
+    error: &dyn Error,
+    separator: &str,
+    render: Option<&DisplayOptions>,
+    w: &mut impl Write,
+) -> fmt::Result {
+    for (no, error) in error_chain(error).enumerate() {
+        if no > 0 {
+            w.write_str(separator)?;
+        }
+        match render {
+            Some(options) => write_message(w, error, options)?,
+            None => write!(w, "{}", error)?,
+        }
+    }
+    Ok(())
+}
 
-    // taken from libstd
-    let msg = match panic.payload().downcast_ref::<&'static str>() {
-        Some(s) => *s,
-        None => match panic.payload().downcast_ref::<String>() {
-            Some(s) => &s[..],
-            None => "Box<Any>",
-        },
-    };
+/// Maximum number of bytes included in hex dump attached with `Problem::with_hex_dump`
+pub const HEX_DUMP_MAX_BYTES: usize = 4096;
 
-    match (backtrace.is_some(), panic.location()) {
-        (true, Some(location)) => write!(
-            message,
-            "thread '{}' panicked at {} with: {}",
-            name, location, msg
+fn format_hex_dump(bytes: &[u8], max_bytes: usize) -> String {
+    let mut dump = String::new();
+    for (no, line) in bytes[..bytes.len().min(max_bytes)].chunks(16).enumerate() {
+        if no > 0 {
+            dump.push('\n');
+        }
+        write!(dump, "{:08x} ", no * 16).unwrap();
+        for column in 0..16 {
+            if column == 8 {
+                dump.push(' ');
+            }
+            match line.get(column) {
+                Some(byte) => write!(dump, " {:02x}", byte).unwrap(),
+                None => dump.push_str("   "),
+            }
+        }
+        dump.push_str("  |");
+        dump.extend(line.iter().map(|&byte| {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            }
+        }));
+        dump.push('|');
+    }
+    if bytes.len() > max_bytes {
+        write!(
+            dump,
+            "\n… ({} more bytes, {} total)",
+            bytes.len() - max_bytes,
+            bytes.len()
         )
-        .ok(),
-        (true, None) => write!(message, "thread '{}' panicked with: {}", name, msg).ok(),
-        (false, _) => write!(message, "{}", msg).ok(),
+        .unwrap();
+    }
+    dump
+}
+
+/// Writes single error or context message according to given options
+fn write_message(
+    w: &mut impl Write,
+    message: &dyn Display,
+    options: &DisplayOptions,
+) -> fmt::Result {
+    let mut w = IndentLines::new(w, options.sanitize);
+    let max_length = match options.max_message_length {
+        Some(max_length) => max_length,
+        None => return write!(w, "{}", message),
     };
 
-    if let Some(backtrace) = backtrace {
-        message.push_str("\n--- Panicked\n");
-        message.push_str(&backtrace);
+    let message = message.to_string();
+    if message.len() <= max_length {
+        return w.write_str(&message);
+    }
+    let mut end = max_length;
+    while !message.is_char_boundary(end) {
+        end -= 1;
     }
+    w.write_str(&message[..end])?;
+    write!(w, "… (truncated, {} total)", format_size(message.len()))
+}
 
-    message
+fn format_size(bytes: usize) -> String {
+    match bytes {
+        0..=1023 => format!("{}B", bytes),
+        1024..=1048575 => format!("{}KiB", bytes / 1024),
+        _ => format!("{}MiB", bytes / 1048576),
+    }
 }
 
-/// Set panic hook so that formats error message to `stderr` with more `Problem` friendly way
-pub fn format_panic_to_stderr() {
-    panic::set_hook(Box::new(|panic_info| {
-        let backtrace = format_backtrace();
-        eprintln!("Fatal error: {}", format_panic(panic_info, backtrace));
-    }));
+/// Writes given text escaping control characters other than new line and tab if `sanitize` is set so that messages
+/// (e.g. containing malicious file names) can't inject terminal escape sequences
+fn write_sanitized(w: &mut impl Write, text: &str, sanitize: bool) -> fmt::Result {
+    if !sanitize {
+        return w.write_str(text);
+    }
+    let mut rest = text;
+    while let Some(pos) = rest.find(|c: char| c.is_control() && c != '\n' && c != '\t') {
+        w.write_str(&rest[..pos])?;
+        let c = rest[pos..].chars().next().expect("found char");
+        write!(w, "{}", c.escape_unicode())?;
+        rest = &rest[pos + c.len_utf8()..];
+    }
+    w.write_str(rest)
 }
 
-/// Set panic hook so that when program panics it will log error massage with `error!` macro
-#[cfg(feature = "log")]
-pub fn format_panic_to_error_log() {
-    panic::set_hook(Box::new(|panic_info| {
-        let backtrace = format_backtrace();
-        error!("{}", format_panic(panic_info, backtrace));
-    }));
+/// Writer indenting continuation lines of multi-line messages and dropping trailing new lines so that they don't break
+/// the one line message format; control characters are escaped if `sanitize` is set
+struct IndentLines<'w, W: Write> {
+    inner: &'w mut W,
+    new_lines: usize,
+    sanitize: bool,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::prelude::*;
-    use super::{format_panic_to_stderr, in_context_of};
-    use std::error::Error;
-    use std::fmt::{self, Display};
-    use std::io;
+impl<'w, W: Write> IndentLines<'w, W> {
+    fn new(inner: &'w mut W, sanitize: bool) -> IndentLines<'w, W> {
+        IndentLines {
+            inner,
+            new_lines: 0,
+            sanitize,
+        }
+    }
+}
+
+impl<W: Write> Write for IndentLines<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for (no, line) in s.split('\n').enumerate() {
+            if no > 0 {
+                self.new_lines += 1;
+            }
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if !line.is_empty() {
+                for _ in 0..self.new_lines {
+                    self.inner.write_str("\n  ")?;
+                }
+                self.new_lines = 0;
+                write_sanitized(self.inner, line, self.sanitize)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+const DEFAULT_VERBOSITY: u8 = 2;
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(DEFAULT_VERBOSITY);
+
+/// Set how much information is displayed when `Problem` is formatted (e.g. on fatal errors and in logs)
+///
+/// Levels are:
+/// * 0 - context and error message,
+/// * 1 - context, error message and its cause chain,
+/// * 2 - as above and backtrace if available (default),
+/// * 3 and above - as above and panicking thread and location in panic hook messages even if backtrace is not available.
+///
+/// This can be used to map `-v` command line flags to `Problem` message verbosity.
+pub fn set_verbosity(level: u8) {
+    VERBOSITY.store(level, Ordering::Relaxed)
+}
+
+/// Get current verbosity level as set with `set_verbosity`
+pub fn verbosity() -> u8 {
+    VERBOSITY.load(Ordering::Relaxed)
+}
+
+impl Problem {
+    /// Get `Display` adapter formatting this `Problem` with given options
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    /// use problem::{DisplayOptions, ContextOrder};
+    ///
+    /// let problem = Problem::from_error("boom!")
+    ///     .problem_while("parsing input")
+    ///     .problem_while("processing object");
+    ///
+    /// let options = DisplayOptions::default()
+    ///     .with_context_separator(" / ")
+    ///     .with_order(ContextOrder::InnermostFirst);
+    ///
+    /// # if problem.backtrace().is_none() {
+    /// assert_eq!(
+    ///     problem.display_with(options).to_string(),
+    ///     "while parsing input / while processing object got error caused by: boom!"
+    /// );
+    /// # }
+    /// ```
+    pub fn display_with<'p, 's>(&'p self, options: DisplayOptions<'s>) -> ProblemDisplay<'p, 's> {
+        ProblemDisplay {
+            problem: self,
+            options,
+            verbosity: None,
+            style: None,
+        }
+    }
+
+    /// Get view of this `Problem` implementing `Error` trait so it can be passed where `E: Error` bound is required
+    ///
+    /// The view displays the same message as the `Problem` and has no `Error::source` as the message already includes error
+    /// causes. Use `ProblemError::from` to get an owned `Error` that is `Send`, `Sync` and `'static`.
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    /// use std::error::Error;
+    ///
+    /// fn describe(error: &impl Error) -> String {
+    ///     format!("error: {}", error)
+    /// }
+    ///
+    /// let problem = Problem::from_error("boom!").problem_while("processing job 42");
+    /// assert_eq!(describe(&problem.as_std_error()), "error: while processing job 42 got error caused by: boom!");
+    /// assert!(problem.as_std_error().source().is_none());
+    /// ```
+    pub fn as_std_error(&self) -> ProblemDisplay<'_, 'static> {
+        self.display_with(DisplayOptions::default())
+    }
+
+    /// Format one line message with context messages and error cause chain (depending on verbosity level, see `set_verbosity`)
+    ///
+    /// This is used as the log message by `ok_or_log_*` methods.
+    pub fn brief(&self) -> String {
+        let mut out = String::new();
+        self.write(
+            &mut out,
+            &DisplayOptions::default(),
+            verbosity().min(1),
+            ReportStyle::Short,
+        )
+        .ok();
+        out
+    }
+
+    /// Format long report with sections and backtrace (depending on verbosity level, see `set_verbosity`); same as alternate
+    /// `Display` format (`{:#}`)
+    ///
+    /// When `Problem` is logged by `ok_or_log_*` methods the report is logged at debug level, excluding sections not meant for
+    /// logs (see `SectionVisibility`).
+    pub fn report(&self) -> String {
+        format!("{:#}", self)
+    }
+
+    #[cfg(feature = "log")]
+    fn log_report(&self) -> String {
+        let mut out = String::new();
+        self.write(
+            &mut out,
+            &DisplayOptions::default(),
+            verbosity(),
+            ReportStyle::Log,
+        )
+        .ok();
+        out
+    }
+
+    /// Get `Display` adapter formatting context messages and error message only (without its causes, sections and backtrace)
+    /// regardless of verbosity level
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    ///
+    /// let problem = Problem::from_error_message(&std::str::from_utf8(&[0xff]).unwrap_err()).problem_while("parsing input");
+    /// assert_eq!(problem.display_short().to_string(), "while parsing input got error caused by: invalid utf-8 sequence of 1 bytes from index 0");
+    /// ```
+    pub fn display_short(&self) -> ProblemDisplay<'_, 'static> {
+        ProblemDisplay {
+            problem: self,
+            options: DisplayOptions::default(),
+            verbosity: Some(0),
+            style: Some(ReportStyle::Short),
+        }
+    }
+
+    /// Get `Display` adapter formatting long report with cause chain, all sections and backtrace (if available) regardless of
+    /// verbosity level
+    pub fn display_full(&self) -> ProblemDisplay<'_, 'static> {
+        ProblemDisplay {
+            problem: self,
+            options: DisplayOptions::default(),
+            verbosity: Some(2),
+            style: Some(ReportStyle::Long),
+        }
+    }
+
+    fn write(
+        &self,
+        f: &mut impl Write,
+        options: &DisplayOptions,
+        verbosity: u8,
+        style: ReportStyle,
+    ) -> fmt::Result {
+        self.write_contexts(f, options)?;
+
+        match self.error.downcast_ref::<Problems>() {
+            Some(problems) if style != ReportStyle::Short => {
+                problems.write_tree(f, options, verbosity, "")?
+            }
+            _ if verbosity >= 1 => write_error_message(
+                self.error.as_ref(),
+                options.cause_separator,
+                Some(options),
+                f,
+            )?,
+            _ => write_message(f, &self.error, options)?,
+        }
+
+        if let Some(url) = self.url().filter(|_| style != ReportStyle::Short) {
+            f.write_str("\nsee: ")?;
+            write_sanitized(f, url, options.sanitize)?;
+        }
+
+        if let Some(trace_id) = self.trace_id().filter(|_| style != ReportStyle::Short) {
+            f.write_str("\ntrace id: ")?;
+            write_sanitized(f, trace_id, options.sanitize)?;
+        }
+
+        if style != ReportStyle::Short && verbosity >= 1 {
+            for section in self.details.iter().flat_map(|details| &details.sections) {
+                let visible = match section.visibility {
+                    SectionVisibility::Always => true,
+                    SectionVisibility::Verbose => verbosity >= 2,
+                    SectionVisibility::NotInLogs => style != ReportStyle::Log,
+                };
+                if visible {
+                    f.write_str("\n--- ")?;
+                    write_sanitized(f, &section.title, options.sanitize)?;
+                    f.write_char('\n')?;
+                    write_sanitized(f, section.body(), options.sanitize)?;
+                }
+            }
+        }
+
+        if verbosity >= 2 {
+            if let Some(backtrace) = self.backtrace() {
+                write!(f, "\n--- Cause\n{}", backtrace)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_contexts(&self, f: &mut impl Write, options: &DisplayOptions) -> fmt::Result {
+        if self.write_context_chain(f, options)? {
+            write!(f, " got error caused by: ")?;
+        }
+        Ok(())
+    }
+
+    /// Write context messages only; returns `false` if there were none
+    fn write_context_chain(
+        &self,
+        f: &mut impl Write,
+        options: &DisplayOptions,
+    ) -> Result<bool, fmt::Error> {
+        let times = self
+            .details
+            .as_ref()
+            .map(|details| details.context_times.as_slice())
+            .unwrap_or_default();
+        // None marks position of contexts dropped due to maximum context depth
+        let mut contexts: Vec<Option<(&str, Option<Duration>)>> = self
+            .context
+            .iter()
+            .enumerate()
+            .map(|(no, context)| Some((context.as_str(), times.get(no).copied().flatten())))
+            .collect();
+        let dropped_contexts = self
+            .details
+            .as_ref()
+            .map(|details| details.dropped_contexts)
+            .unwrap_or(0);
+        if dropped_contexts > 0 {
+            contexts.insert(contexts.len().saturating_sub(1), None);
+        }
+        if options.order == ContextOrder::OutermostFirst {
+            contexts.reverse();
+        }
+
+        let mut no = 0;
+        while no < contexts.len() {
+            if no > 0 {
+                f.write_str(options.context_separator)?;
+            }
+            match contexts[no] {
+                Some((message, time)) => {
+                    f.write_str("while ")?;
+                    write_message(f, &message, options)?;
+
+                    // collapse repeated context messages
+                    let repeated = contexts[no + 1..]
+                        .iter()
+                        .take_while(|next| next.map(|(next, _)| next) == Some(message))
+                        .count();
+                    if repeated > 0 {
+                        write!(f, " (x{})", repeated + 1)?;
+                    }
+                    if let Some(time) = time {
+                        write!(f, " (after {:.1?})", time)?;
+                    }
+                    no += repeated + 1;
+                }
+                None => {
+                    write!(
+                        f,
+                        "… and {} more context{}",
+                        dropped_contexts,
+                        if dropped_contexts == 1 { "" } else { "s" }
+                    )?;
+                    no += 1;
+                }
+            }
+        }
+
+        Ok(!contexts.is_empty())
+    }
+}
+
+/// Alternate format (`{:#}`) will display long report including additional information like environment variables
+impl Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(
+            f,
+            &DisplayOptions::default(),
+            verbosity(),
+            ReportStyle::from_alternate(f),
+        )
+    }
+}
+
+const DEFAULT_MAX_CONTEXT_DEPTH: usize = 64;
+
+static MAX_CONTEXT_DEPTH: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_CONTEXT_DEPTH);
+
+/// Set maximum number of context messages stored in `Problem` (default 64)
+///
+/// When this limit is reached, the innermost context messages and the outermost one are kept while the
+/// rest is dropped and displayed as `… and N more contexts` marker.
+/// This protects against context being added in deep recursion or loops by mistake.
+pub fn set_max_context_depth(depth: usize) {
+    MAX_CONTEXT_DEPTH.store(depth, Ordering::Relaxed)
+}
+
+/// Get maximum number of context messages stored in `Problem` as set with `set_max_context_depth`
+pub fn max_context_depth() -> usize {
+    MAX_CONTEXT_DEPTH.load(Ordering::Relaxed)
+}
+
+static CONTEXT_TIMESTAMPS: AtomicBool = AtomicBool::new(false);
+
+/// Record time at which context messages are added to `Problem`s created from now on and display it relative to start of the
+/// operation, e.g. `while downloading (after 2.4s)`
+///
+/// The operation starts when the outermost `in_context_of*` call executing on the current thread is entered; `Problem`s created
+/// outside of `in_context_of*` calls are timed from their creation.
+///
+/// ```rust
+/// use problem::prelude::*;
+///
+/// problem::set_context_timestamps(true);
+///
+/// let problem = Problem::from_error("connection reset").problem_while("downloading");
+/// assert!(problem.to_string().starts_with("while downloading (after "));
+/// ```
+pub fn set_context_timestamps(enabled: bool) {
+    CONTEXT_TIMESTAMPS.store(enabled, Ordering::Relaxed)
+}
+
+/// Generate new random correlation ID (UUID version 4)
+///
+/// ```rust
+/// let id = problem::new_correlation_id();
+/// assert_eq!(id.len(), 36);
+/// ```
+#[cfg(feature = "uuid")]
+pub fn new_correlation_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// ID printed at the bottom of crash reports so that it can be quoted by users: given trace ID or new correlation ID if
+/// `uuid` feature is enabled
+fn error_id(trace_id: Option<&str>) -> Option<String> {
+    let trace_id = trace_id.map(ToOwned::to_owned);
+    #[cfg(feature = "uuid")]
+    let trace_id = trace_id.or_else(|| Some(new_correlation_id()));
+    trace_id
+}
+
+thread_local! {
+    static CURRENT_TRACE_ID: std::cell::RefCell<Option<String>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Set trace or correlation ID that `Problem`s created on this thread will be associated with; returns previously set ID
+///
+/// ```rust
+/// use problem::prelude::*;
+///
+/// problem::set_current_trace_id(Some("4bf92f3577b34da6".to_owned()));
+/// assert_eq!(Problem::from_error("boom!").trace_id(), Some("4bf92f3577b34da6"));
+///
+/// problem::set_current_trace_id(None);
+/// assert_eq!(Problem::from_error("boom!").trace_id(), None);
+/// ```
+pub fn set_current_trace_id(id: Option<String>) -> Option<String> {
+    CURRENT_TRACE_ID
+        .try_with(|current| current.replace(id))
+        .ok()
+        .flatten()
+}
+
+/// Get trace or correlation ID set for this thread with `set_current_trace_id`
+pub fn current_trace_id() -> Option<String> {
+    CURRENT_TRACE_ID
+        .try_with(|current| current.borrow().clone())
+        .ok()
+        .flatten()
+}
+
+/// Order in which context messages are displayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContextOrder {
+    /// Context added last is displayed first, e.g. `while processing file, while parsing input` (default)
+    #[default]
+    OutermostFirst,
+    /// Context added first is displayed first, e.g. `while parsing input, while processing file`
+    InnermostFirst,
+}
+
+/// Options controlling how `Problem` is displayed with `Problem::display_with`
+///
+/// Start from `DisplayOptions::default()` and use `with_*` methods to customize options; more options may be added in
+/// the future.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DisplayOptions<'s> {
+    /// Separator between context messages (default `, `)
+    pub context_separator: &'s str,
+    /// Separator between error messages of the cause chain (default `; caused by: `)
+    pub cause_separator: &'s str,
+    /// Order of context messages
+    pub order: ContextOrder,
+    /// Escape control characters (e.g. ANSI escape sequences) contained in messages, URL and trace ID
+    pub sanitize: bool,
+    /// Maximum length in bytes of individual error and context messages; longer messages are truncated
+    pub max_message_length: Option<usize>,
+}
+
+static INNERMOST_CONTEXT_FIRST: AtomicBool = AtomicBool::new(false);
+
+/// Set order in which context messages are displayed by default
+///
+/// ```rust
+/// use problem::prelude::*;
+/// use problem::{set_context_order, ContextOrder};
+///
+/// set_context_order(ContextOrder::InnermostFirst);
+///
+/// let problem = Problem::from_error("boom!")
+///     .problem_while("parsing input")
+///     .problem_while("processing file");
+///
+/// # if problem.backtrace().is_none() {
+/// assert_eq!(problem.to_string(), "while parsing input, while processing file got error caused by: boom!");
+/// # }
+/// ```
+pub fn set_context_order(order: ContextOrder) {
+    INNERMOST_CONTEXT_FIRST.store(order == ContextOrder::InnermostFirst, Ordering::Relaxed)
+}
+
+/// Get order in which context messages are displayed by default as set with `set_context_order`
+pub fn context_order() -> ContextOrder {
+    if INNERMOST_CONTEXT_FIRST.load(Ordering::Relaxed) {
+        ContextOrder::InnermostFirst
+    } else {
+        ContextOrder::OutermostFirst
+    }
+}
+
+static NO_SANITIZE: AtomicBool = AtomicBool::new(false);
+
+/// Set whether control characters contained in messages (e.g. ANSI escape sequences in file names) are escaped by default
+/// when `Problem` is displayed (default `true`)
+///
+/// ```rust
+/// use problem::prelude::*;
+///
+/// let problem = Problem::from_error("no such file: \u{1b}[2Jevil.txt");
+///
+/// # if problem.backtrace().is_none() {
+/// assert_eq!(problem.to_string(), "no such file: \\u{1b}[2Jevil.txt");
+/// problem::set_sanitize(false);
+/// assert_eq!(problem.to_string(), "no such file: \u{1b}[2Jevil.txt");
+/// # }
+/// ```
+pub fn set_sanitize(enabled: bool) {
+    NO_SANITIZE.store(!enabled, Ordering::Relaxed)
+}
+
+static MAX_MESSAGE_LENGTH: AtomicUsize = AtomicUsize::new(0);
+
+/// Set maximum length in bytes of individual error and context messages when `Problem` is displayed
+///
+/// Longer messages (e.g. errors embedding whole response bodies) are truncated on UTF-8 character boundary and
+/// suffixed with information about their total size. Set to `None` to disable truncation (default).
+///
+/// ```rust
+/// use problem::prelude::*;
+///
+/// problem::set_max_message_length(Some(16));
+/// let problem = Problem::from_error(format!("bad response: {}", "x".repeat(50 * 1024)));
+///
+/// # if problem.backtrace().is_none() {
+/// assert_eq!(problem.to_string(), "bad response: xx… (truncated, 50KiB total)");
+/// # }
+/// ```
+pub fn set_max_message_length(max_length: Option<usize>) {
+    MAX_MESSAGE_LENGTH.store(max_length.unwrap_or(0), Ordering::Relaxed)
+}
+
+/// Default options use context order set with `set_context_order`, sanitization set with `set_sanitize` and maximum
+/// message length set with `set_max_message_length`
+impl Default for DisplayOptions<'static> {
+    fn default() -> DisplayOptions<'static> {
+        DisplayOptions {
+            context_separator: ", ",
+            cause_separator: "; caused by: ",
+            order: context_order(),
+            sanitize: !NO_SANITIZE.load(Ordering::Relaxed),
+            max_message_length: Some(MAX_MESSAGE_LENGTH.load(Ordering::Relaxed))
+                .filter(|max_length| *max_length > 0),
+        }
+    }
+}
+
+impl<'s> DisplayOptions<'s> {
+    /// Set separator between context messages
+    pub fn with_context_separator(mut self, separator: &'s str) -> DisplayOptions<'s> {
+        self.context_separator = separator;
+        self
+    }
+
+    /// Set separator between error messages of the cause chain
+    pub fn with_cause_separator(mut self, separator: &'s str) -> DisplayOptions<'s> {
+        self.cause_separator = separator;
+        self
+    }
+
+    /// Set order of context messages
+    pub fn with_order(mut self, order: ContextOrder) -> DisplayOptions<'s> {
+        self.order = order;
+        self
+    }
+
+    /// Set whether control characters contained in messages, URL and trace ID are escaped
+    pub fn with_sanitize(mut self, sanitize: bool) -> DisplayOptions<'s> {
+        self.sanitize = sanitize;
+        self
+    }
+
+    /// Set maximum length in bytes of individual error and context messages
+    pub fn with_max_message_length(mut self, max_length: Option<usize>) -> DisplayOptions<'s> {
+        self.max_message_length = max_length;
+        self
+    }
+}
+
+/// Formats `Problem` with custom `DisplayOptions`; see `Problem::display_with`
+///
+/// Implements `Error` so it can be used where error is required (see `Problem::as_std_error`).
+#[derive(Debug)]
+pub struct ProblemDisplay<'p, 's> {
+    problem: &'p Problem,
+    options: DisplayOptions<'s>,
+    /// Fixed verbosity level instead of the one set with `set_verbosity`
+    verbosity: Option<u8>,
+    /// Fixed report style instead of one depending on alternate format
+    style: Option<ReportStyle>,
+}
+
+impl Display for ProblemDisplay<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.problem.write(
+            f,
+            &self.options,
+            self.verbosity.unwrap_or_else(verbosity),
+            self.style.unwrap_or_else(|| ReportStyle::from_alternate(f)),
+        )
+    }
+}
+
+impl Error for ProblemDisplay<'_, '_> {}
+
+/// Collection of independent `Problem`s (e.g. failures of individual items of a batch job)
+///
+/// Alternate format (`{:#}`) renders a tree with one branch per sub-problem; a `Problem` created from `Problems` renders the
+/// same tree in its long report.
+#[derive(Debug, Default)]
+pub struct Problems {
+    problems: Vec<Problem>,
+    message: Option<String>,
+}
+
+impl Problems {
+    /// Create empty collection
+    pub fn new() -> Problems {
+        Problems::default()
+    }
+
+    /// Add sub-problem
+    pub fn push(&mut self, problem: impl ToProblem) {
+        self.problems.push(problem.into_problem())
+    }
+
+    /// Get number of sub-problems
+    pub fn len(&self) -> usize {
+        self.problems.len()
+    }
+
+    /// Returns `true` if there are no sub-problems
+    pub fn is_empty(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Iterate sub-problems in order they were added
+    pub fn iter(&self) -> std::slice::Iter<'_, Problem> {
+        self.problems.iter()
+    }
+
+    /// Convert to `Ok(())` if there are no sub-problems, otherwise to `Err` with `Problem` wrapping this collection
+    pub fn into_result(self) -> Result<(), Problem> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(Problem::from_error(self))
+        }
+    }
+
+    /// Group sub-problems by their root cause, largest group first (groups of equal size in order of first occurrence)
+    ///
+    /// Sub-problems caused by `io::Error` are grouped by its `io::ErrorKind`, other by type name of the last error in their
+    /// cause chain. The type is known for errors sub-problems were created from and common standard library errors; errors of
+    /// other types found only as sources of other errors and errors that carry only a message (e.g. created from `String`)
+    /// are grouped by their message. This is useful for end-of-run summaries of batch jobs.
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    /// use std::io;
+    ///
+    /// let mut problems = Problems::new();
+    /// for file in ["a.csv", "b.csv", "c.csv"] {
+    ///     problems.push(Problem::from_error(io::Error::from(io::ErrorKind::NotFound)).problem_while(format!("opening {}", file)));
+    /// }
+    /// problems.push(Problem::from_error(io::Error::from(io::ErrorKind::PermissionDenied)).problem_while("opening d.csv"));
+    ///
+    /// let summary = problems.group_by_cause().iter().map(ToString::to_string).collect::<Vec<_>>();
+    /// assert_eq!(summary.join(", "), "3 NotFound, 1 PermissionDenied");
+    /// ```
+    pub fn group_by_cause(&self) -> Vec<ProblemGroup<'_>> {
+        let mut groups: Vec<ProblemGroup<'_>> = Vec::new();
+        for problem in &self.problems {
+            let cause = problem.root_cause_key();
+            match groups.iter_mut().find(|group| group.cause == cause) {
+                Some(group) => group.count += 1,
+                None => groups.push(ProblemGroup {
+                    cause,
+                    count: 1,
+                    representative: problem,
+                }),
+            }
+        }
+        // stable sort keeps groups of equal size in order of first occurrence
+        groups.sort_by_key(|group| std::cmp::Reverse(group.count));
+        groups
+    }
+
+    /// Set message displayed before the number of sub-problems (e.g. `too many corrupt records: 3 problems occurred`)
+    pub fn with_message(mut self, message: impl ToString) -> Problems {
+        self.message = Some(message.to_string());
+        self
+    }
+
+    fn write_header(&self, f: &mut impl Write) -> fmt::Result {
+        if let Some(message) = self.message.as_ref() {
+            write!(f, "{}: ", message)?;
+        }
+        write!(
+            f,
+            "{} problem{} occurred",
+            self.len(),
+            if self.len() == 1 { "" } else { "s" }
+        )
+    }
+
+    fn write_tree(
+        &self,
+        f: &mut impl Write,
+        options: &DisplayOptions,
+        verbosity: u8,
+        indent: &str,
+    ) -> fmt::Result {
+        self.write_header(f)?;
+        f.write_char(':')?;
+
+        for (no, problem) in self.problems.iter().enumerate() {
+            let last = no + 1 == self.len();
+            write!(f, "\n{}{}", indent, if last { "└─ " } else { "├─ " })?;
+
+            // sub-problems are rendered without their own sections and backtraces
+            match problem.error.downcast_ref::<Problems>() {
+                Some(problems) => {
+                    problem.write_contexts(f, options)?;
+                    let indent = format!("{}{}", indent, if last { "   " } else { "│  " });
+                    problems.write_tree(f, options, verbosity, &indent)?;
+                }
+                None => problem.write(f, options, verbosity.min(1), ReportStyle::Short)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Display number of sub-problems followed by their messages; use alternate format (`{:#}`) for tree rendering
+impl Display for Problems {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            return self.write_tree(f, &DisplayOptions::default(), verbosity(), "");
+        }
+
+        self.write_header(f)?;
+        for (no, problem) in self.problems.iter().enumerate() {
+            f.write_str(if no == 0 { ": " } else { "; " })?;
+            problem.write(
+                f,
+                &DisplayOptions::default(),
+                verbosity().min(1),
+                ReportStyle::Short,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for Problems {}
+
+/// Sub-problems of `Problems` with the same root cause; see `Problems::group_by_cause`
+#[derive(Debug)]
+pub struct ProblemGroup<'p> {
+    cause: String,
+    count: usize,
+    representative: &'p Problem,
+}
+
+impl<'p> ProblemGroup<'p> {
+    /// Root cause of the sub-problems: `io::ErrorKind` name, type name or message of the last error in the cause chain (see
+    /// `Problems::group_by_cause`)
+    pub fn cause(&self) -> &str {
+        &self.cause
+    }
+
+    /// Number of sub-problems in this group
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// First sub-problem of this group
+    pub fn representative(&self) -> &'p Problem {
+        self.representative
+    }
+}
+
+/// Displays number of sub-problems followed by root cause, e.g. `1842 NotFound`
+impl Display for ProblemGroup<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.count, self.cause)
+    }
+}
+
+/// Error with multiple independent causes (e.g. aggregate error of joined tasks or validation of many fields)
+///
+/// `Error::source` can expose only one cause; implement this trait and use `Problem::from_multi_source` so that all branches of
+/// the cause tree are rendered.
+pub trait MultiSource: Error {
+    /// Get all causes of this error
+    fn sources(&self) -> Vec<&(dyn Error + 'static)>;
+}
+
+impl std::iter::FromIterator<Problem> for Problems {
+    fn from_iter<I: IntoIterator<Item = Problem>>(iter: I) -> Problems {
+        Problems {
+            problems: iter.into_iter().collect(),
+            message: None,
+        }
+    }
+}
+
+impl Extend<Problem> for Problems {
+    fn extend<I: IntoIterator<Item = Problem>>(&mut self, iter: I) {
+        self.problems.extend(iter)
+    }
+}
+
+impl IntoIterator for Problems {
+    type Item = Problem;
+    type IntoIter = std::vec::IntoIter<Problem>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.problems.into_iter()
+    }
+}
+
+impl<'p> IntoIterator for &'p Problems {
+    type Item = &'p Problem;
+    type IntoIter = std::slice::Iter<'p, Problem>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.problems.iter()
+    }
+}
+
+/// Marker trait for errors that are converted to `Problem` with `From` (and so by `?` operator)
+///
+/// It is implemented for standard library errors, `String` and `&str` types and errors of this crate. Error types of other
+/// crates opt in by implementing it; alternatively they can implement `From<MyError> for Problem` (and `FatalProblem`) to
+/// customize the conversion, e.g. to attach context or exit code.
+///
+/// ```rust
+/// use problem::prelude::*;
+/// use problem::IntoProblem;
+/// use std::fmt;
+/// use std::error::Error;
+///
+/// #[derive(Debug)]
+/// struct ConfigError;
+///
+/// impl fmt::Display for ConfigError {
+///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+///         write!(f, "bad config")
+///     }
+/// }
+///
+/// impl Error for ConfigError {}
+/// impl IntoProblem for ConfigError {}
+///
+/// #[derive(Debug)]
+/// struct UsageError(&'static str);
+///
+/// impl From<UsageError> for Problem {
+///     fn from(error: UsageError) -> Problem {
+///         Problem::from_error(format!("missing option `{}`", error.0)).with_exit_code(64)
+///     }
+/// }
+///
+/// fn run(config: bool) -> Result<(), Problem> {
+///     if config {
+///         Err(ConfigError)?
+///     }
+///     Err(UsageError("name"))?
+/// }
+///
+/// assert_eq!(run(true).problem_while("starting up").unwrap_err().to_string(), "while starting up got error caused by: bad config");
+/// assert_eq!(run(false).unwrap_err().exit_code(), Some(64));
+/// ```
+pub trait IntoProblem: Into<Box<dyn Error>> {
+    /// Kind assigned to `Problem` converted from this error (see `kind` module)
+    fn problem_kind(&self) -> Option<ProblemKind> {
+        None
+    }
+}
+
+impl IntoProblem for &str {}
+impl IntoProblem for String {}
+impl IntoProblem for std::borrow::Cow<'_, str> {}
+impl IntoProblem for Box<dyn Error> {}
+
+impl<T: 'static> IntoProblem for std::sync::PoisonError<T> {}
+impl<T: 'static> IntoProblem for std::sync::TryLockError<T> {}
+impl<T: Send + 'static> IntoProblem for std::sync::mpsc::SendError<T> {}
+impl<T: Send + 'static> IntoProblem for std::sync::mpsc::TrySendError<T> {}
+
+macro_rules! impl_into_problem {
+    ($($error:ty),* $(,)*) => {
+        $(impl IntoProblem for $error {})*
+    };
+}
+
+impl_into_problem!(
+    io::Error,
+    fmt::Error,
+    std::alloc::LayoutError,
+    std::array::TryFromSliceError,
+    std::cell::BorrowError,
+    std::cell::BorrowMutError,
+    std::char::CharTryFromError,
+    std::char::DecodeUtf16Error,
+    std::char::ParseCharError,
+    std::char::TryFromCharError,
+    std::collections::TryReserveError,
+    std::convert::Infallible,
+    std::env::JoinPathsError,
+    std::env::VarError,
+    std::ffi::FromBytesWithNulError,
+    std::ffi::FromVecWithNulError,
+    std::ffi::IntoStringError,
+    std::ffi::NulError,
+    std::net::AddrParseError,
+    std::num::ParseFloatError,
+    std::num::ParseIntError,
+    std::num::TryFromIntError,
+    std::path::StripPrefixError,
+    std::str::ParseBoolError,
+    std::str::Utf8Error,
+    std::string::FromUtf16Error,
+    std::string::FromUtf8Error,
+    std::sync::mpsc::RecvError,
+    std::sync::mpsc::RecvTimeoutError,
+    std::sync::mpsc::TryRecvError,
+    std::thread::AccessError,
+    std::time::SystemTimeError,
+    std::time::TryFromFloatSecsError,
+    ErrorMessage,
+    Problems,
+    ProblemError,
+);
+
+#[cfg(feature = "log")]
+impl_into_problem!(log::ParseLevelError, log::SetLoggerError);
+#[cfg(feature = "clap")]
+impl_into_problem!(clap::Error);
+#[cfg(feature = "serde_json")]
+impl_into_problem!(serde_json::Error);
+#[cfg(feature = "postcard")]
+impl_into_problem!(postcard::Error);
+#[cfg(feature = "uuid")]
+impl_into_problem!(uuid::Error);
+#[cfg(feature = "tokio")]
+impl_into_problem!(tokio::task::JoinError);
+#[cfg(feature = "crossbeam-channel")]
+impl_into_problem!(
+    crossbeam_channel::RecvError,
+    crossbeam_channel::RecvTimeoutError,
+    crossbeam_channel::TryRecvError,
+);
+#[cfg(feature = "crossbeam-channel")]
+impl<T: Send + 'static> IntoProblem for crossbeam_channel::SendError<T> {}
+#[cfg(feature = "crossbeam-channel")]
+impl<T: Send + 'static> IntoProblem for crossbeam_channel::TrySendError<T> {}
+#[cfg(feature = "crossbeam-channel")]
+impl<T: Send + 'static> IntoProblem for crossbeam_channel::SendTimeoutError<T> {}
+
+/// Every type implementing `IntoProblem` marker trait (including standard library errors, `String` and `&str` types) can be
+/// converted to `Problem` via `?` operator
+impl<E> From<E> for Problem
+where
+    E: IntoProblem,
+{
+    fn from(error: E) -> Problem {
+        let kind = error.problem_kind();
+        let problem = Problem::from_error(error);
+        match kind {
+            Some(kind) if problem.kind().is_none() => problem.with_kind(kind),
+            _ => problem,
+        }
+    }
+}
+
+/// `Box<dyn Error + Send + Sync>` (e.g. returned by libraries that erase error types) can be converted to `Problem` via `?` operator
+impl From<Box<dyn Error + Send + Sync>> for Problem {
+    fn from(error: Box<dyn Error + Send + Sync>) -> Problem {
+        Problem::from_error(error as Box<dyn Error>)
+    }
+}
+
+/// Conversion to `Problem` used by extension traits of this crate (e.g. `problem_while`, `or_failed_to`)
+///
+/// Unlike `From` it is implemented for every error type (including errors of other crates), `String` and `&str` types and
+/// `Problem` itself, so that `Result` with any error can be given context without `map_err`. Types that are converted with
+/// custom `From<MyType> for Problem` and do not implement `Error` can implement it to be used with the extension traits.
+///
+/// ```rust
+/// use problem::prelude::*;
+/// use problem::ToProblem;
+///
+/// #[derive(Debug)]
+/// struct UsageError(&'static str);
+///
+/// impl From<UsageError> for Problem {
+///     fn from(error: UsageError) -> Problem {
+///         Problem::from_error(format!("missing option `{}`", error.0)).with_exit_code(64)
+///     }
+/// }
+///
+/// impl ToProblem for UsageError {
+///     fn into_problem(self) -> Problem {
+///         self.into()
+///     }
+/// }
+///
+/// let problem = Err::<(), _>(UsageError("name")).problem_while("parsing arguments").unwrap_err();
+/// assert_eq!(problem.exit_code(), Some(64));
+///
+/// // `IntoInnerError` does not implement `IntoProblem` but any error can be used with the extension traits
+/// let writer = std::io::BufWriter::new(Vec::new());
+/// let vec = writer.into_inner().problem_while("flushing buffer").unwrap();
+/// assert!(vec.is_empty());
+/// ```
+pub trait ToProblem {
+    /// Convert to `Problem`
+    fn into_problem(self) -> Problem;
+}
+
+impl<E> ToProblem for E
+where
+    E: Into<Box<dyn Error>>,
+{
+    fn into_problem(self) -> Problem {
+        Problem::from_error(self)
+    }
+}
+
+impl ToProblem for Problem {
+    fn into_problem(self) -> Problem {
+        self
+    }
+}
+
+/// Construct `Err` variant of `Result` containing `Problem` with given message formatted with
+/// `format!` macro.
+#[macro_export]
+macro_rules! problem {
+    ($ ($ arg : tt) *) => { Err(Problem::from_error(format!($($arg)*))) };
+}
+
+/// This error type is meant to be used as `main()` result error. It implements `Debug` display so
+/// that the program can terminate with nice message formatted with `Problem` and custom exit
+/// status.
+pub struct FatalProblem {
+    status: i32,
+    problem: Problem,
+}
+
+static EXIT_CODES: Mutex<Vec<(String, i32)>> = Mutex::new(Vec::new());
+
+/// Table mapping `Problem` tags to program exit codes; see `exit_codes`
+#[derive(Debug, Clone, Copy)]
+pub struct ExitCodes(());
+
+/// Get table mapping `Problem` tags (see `Problem::with_tag`) to program exit codes
+///
+/// The table is consulted when `Problem` without exit code set with `Problem::with_exit_code` or registered for its kind (see
+/// `register_kind`) terminates the program with
+/// `FatalProblem` (returned from `main`), `OrExit::or_exit` or `or_failed_to` within `run_main` when panic hook of this crate
+/// is installed (see `format_panic_to_stderr`). If none of its tags is mapped, default exit code is used.
+///
+/// ```rust
+/// use problem::prelude::*;
+///
+/// problem::exit_codes().map("usage", 64).map("config", 78);
+///
+/// let problem = Problem::from_error("missing option `name`").with_tag("config");
+/// assert_eq!(problem::exit_codes().get("config"), Some(78));
+/// ```
+pub fn exit_codes() -> ExitCodes {
+    ExitCodes(())
+}
+
+impl ExitCodes {
+    fn table(&self) -> MutexGuard<'static, Vec<(String, i32)>> {
+        EXIT_CODES
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Map `Problem`s tagged with given tag to given exit code; replaces previous mapping of the tag
+    pub fn map(self, tag: impl Into<String>, code: i32) -> ExitCodes {
+        let tag = tag.into();
+        let mut table = self.table();
+        match table.iter_mut().find(|(mapped, _)| *mapped == tag) {
+            Some(mapping) => mapping.1 = code,
+            None => table.push((tag, code)),
+        }
+        self
+    }
+
+    /// Get exit code mapped to given tag
+    pub fn get(self, tag: &str) -> Option<i32> {
+        self.table()
+            .iter()
+            .find(|(mapped, _)| mapped == tag)
+            .map(|&(_, code)| code)
+    }
+
+    /// Remove all mappings
+    pub fn clear(self) {
+        self.table().clear()
+    }
+}
+
+impl From<Problem> for FatalProblem {
+    fn from(problem: Problem) -> FatalProblem {
+        FatalProblem {
+            status: problem.fatal_exit_code().unwrap_or(DEFAULT_FATAL_STATUS),
+            problem,
+        }
+    }
+}
+
+impl<E> From<E> for FatalProblem
+where
+    E: IntoProblem,
+{
+    fn from(error: E) -> FatalProblem {
+        FatalProblem::from(Problem::from(error))
+    }
+}
+
+impl From<Box<dyn Error + Send + Sync>> for FatalProblem {
+    fn from(error: Box<dyn Error + Send + Sync>) -> FatalProblem {
+        Problem::from(error).into()
+    }
+}
+
+impl FatalProblem {
+    /// Print report to `stderr` and terminate the program with exit status of this `FatalProblem`
+    fn exit(&self) -> ! {
+        run_fatal_cleanup();
+        eprint!("{:#}", self.problem);
+        // trace ID is already part of the report
+        match error_id(None).filter(|_| self.problem.trace_id().is_none()) {
+            Some(id) => eprintln!("\nerror id: {}", id),
+            None => eprintln!(),
+        }
+        std::process::exit(self.status)
+    }
+}
+
+impl fmt::Debug for FatalProblem {
+    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.exit()
+    }
+}
+
+/// Extension of `Result` that terminates the program with report of `Problem` printed to `stderr` on `Err`
+///
+/// Exit status is taken from the `Problem` or `exit_codes` table (see `exit_codes`).
+pub trait OrExit<O> {
+    /// Unwrap `Ok` value or terminate the program
+    fn or_exit(self) -> O;
+}
+
+impl<O, E> OrExit<O> for Result<O, E>
+where
+    E: ToProblem,
+{
+    fn or_exit(self) -> O {
+        self.unwrap_or_else(|error| FatalProblem::from(error.into_problem()).exit())
+    }
+}
+
+/// Extension trait to map `Option` to `Result` with `Problem`
+pub trait Fatal<O> {
+    fn fatal(self) -> Result<O, FatalProblem>;
+    fn fatal_with_status(self, status: i32) -> Result<O, FatalProblem>;
+}
+
+/// Extension trait that allow to convert to `Result` with `FatalProblem`
+impl<O> Fatal<O> for Result<O, Problem> {
+    /// Converts to `Result` with `FatalProblem` and exit status associated with the `Problem` or default exit status
+    fn fatal(self) -> Result<O, FatalProblem> {
+        self.map_err(FatalProblem::from)
+    }
+
+    /// Converts to `Result` with `FatalProblem` and given exit status
+    fn fatal_with_status(self, status: i32) -> Result<O, FatalProblem> {
+        self.map_err(|problem| FatalProblem { status, problem })
+    }
+}
+
+pub mod result {
+    //! Handy Result types using `Problem` as error.
+    use super::{FatalProblem, Problem};
+
+    /// `Result` with `Problem`
+    pub type Result<T> = std::result::Result<T, Problem>;
+
+    /// `Result` with `FatalProblem` to be used as `main()` return value
+    pub type FinalResult = std::result::Result<(), FatalProblem>;
+}
+
+/// Map type containing error to type containing `Problem`
+pub trait MapProblem {
+    type ProblemCarrier;
+    fn map_problem(self) -> Self::ProblemCarrier;
+}
+
+/// Mapping `Result` with error to `Result` with `Problem`
+impl<O, E> MapProblem for Result<O, E>
+where
+    E: ToProblem,
+{
+    type ProblemCarrier = Result<O, Problem>;
+    fn map_problem(self) -> Result<O, Problem> {
+        self.map_err(|e| e.into_problem())
+    }
+}
+
+/// Map type not containing any error to type containing given `Problem`
+pub trait MapProblemOr {
+    type ProblemCarrier;
+    fn map_problem_or(self, problem: impl ToProblem) -> Self::ProblemCarrier;
+    fn map_problem_or_else<F, P>(self, problem: F) -> Self::ProblemCarrier
+    where
+        F: FnOnce() -> P,
+        P: ToProblem;
+}
+
+/// Mapping `Result` with `Option<E>` to `Result` with `Problem` where `E` implements `ToProblem`
+impl<O, E> MapProblemOr for Result<O, Option<E>>
+where
+    E: ToProblem,
+{
+    type ProblemCarrier = Result<O, Problem>;
+
+    fn map_problem_or(self, problem: impl ToProblem) -> Result<O, Problem> {
+        self.map_err(|e| {
+            e.map(ToProblem::into_problem)
+                .unwrap_or_else(|| problem.into_problem())
+        })
+    }
+
+    fn map_problem_or_else<F, P>(self, problem: F) -> Self::ProblemCarrier
+    where
+        F: FnOnce() -> P,
+        P: ToProblem,
+    {
+        self.map_err(|e| {
+            e.map(ToProblem::into_problem)
+                .unwrap_or_else(|| problem().into_problem())
+        })
+    }
+}
+
+/// Extension trait to turn `Err` caused by specific error type back into value
+pub trait RecoverProblem<O> {
+    /// Call `recovery` with the error if it is of type `E` (see `Problem::recover_if`), otherwise keep the `Problem`
+    fn recover<E, F>(self, recovery: F) -> Result<O, Problem>
+    where
+        E: Error + 'static,
+        F: FnOnce(E) -> O;
+}
+
+impl<O, P> RecoverProblem<O> for Result<O, P>
+where
+    P: ToProblem,
+{
+    fn recover<E, F>(self, recovery: F) -> Result<O, Problem>
+    where
+        E: Error + 'static,
+        F: FnOnce(E) -> O,
+    {
+        self.or_else(|problem| problem.into_problem().recover_if::<E>().map(recovery))
+    }
+}
+
+/// Extension trait to map `Option` to `Result` with `Problem`
+pub trait OkOrProblem<O> {
+    fn ok_or_problem<P>(self, problem: P) -> Result<O, Problem>
+    where
+        P: ToProblem;
+    fn ok_or_problem_with<F, P>(self, problem: F) -> Result<O, Problem>
+    where
+        F: FnOnce() -> P,
+        P: ToProblem;
+}
+
+impl<O> OkOrProblem<O> for Option<O> {
+    fn ok_or_problem<P>(self, problem: P) -> Result<O, Problem>
+    where
+        P: ToProblem,
+    {
+        self.ok_or_else(|| problem.into_problem())
+    }
+
+    fn ok_or_problem_with<F, P>(self, problem: F) -> Result<O, Problem>
+    where
+        F: FnOnce() -> P,
+        P: ToProblem,
+    {
+        self.ok_or_else(|| problem().into_problem())
+    }
+}
+
+/// Convert to `Problem` if needed and add context to it
+pub trait ProblemWhile {
+    type WithContext;
+
+    /// Add context information
+    fn problem_while(self, message: impl ToString) -> Self::WithContext;
+
+    /// Add context information from function call
+    fn problem_while_with<F, M>(self, message: F) -> Self::WithContext
+    where
+        F: FnOnce() -> M,
+        M: ToString;
+}
+
+impl ProblemWhile for Problem {
+    type WithContext = Problem;
+
+    fn problem_while(self, message: impl ToString) -> Problem {
+        self.push_context(message.to_string())
+    }
+
+    fn problem_while_with<F, M>(self, message: F) -> Problem
+    where
+        F: FnOnce() -> M,
+        M: ToString,
+    {
+        self.problem_while(message())
+    }
+}
+
+impl Problem {
+    /// Add context message respecting `set_max_context_depth` limit
+    fn push_context(mut self, message: String) -> Problem {
+        let max_depth = max_context_depth();
+        if self.context.len() >= max_depth {
+            self.details_mut().dropped_contexts += 1;
+            if max_depth == 0 {
+                return self;
+            }
+            // keep innermost contexts and replace outermost one
+            self.context.pop();
+        }
+        self.context.push(message);
+
+        if let Some(started) = self.details.as_ref().and_then(|details| details.started) {
+            let depth = self.context.len();
+            let times = &mut self.details_mut().context_times;
+            times.resize(depth - 1, None);
+            times.push(Some(started.elapsed()));
+        }
+        self
+    }
+}
+
+impl<O, E> ProblemWhile for Result<O, E>
+where
+    E: ToProblem,
+{
+    type WithContext = Result<O, Problem>;
+
+    // Ok path is inlined into the caller leaving only the `Result` check; error handling is kept out of line (see tests/codegen.rs)
+    #[inline]
+    fn problem_while(self, message: impl ToString) -> Result<O, Problem> {
+        match self {
+            Ok(ok) => Ok(ok),
+            Err(err) => Err(problem_while_cold(err, || message)),
+        }
+    }
+
+    #[inline]
+    fn problem_while_with<F, M>(self, message: F) -> Result<O, Problem>
+    where
+        F: FnOnce() -> M,
+        M: ToString,
+    {
+        match self {
+            Ok(ok) => Ok(ok),
+            Err(err) => Err(problem_while_cold(err, message)),
+        }
+    }
+}
+
+/// Extension of `Result` that allows to add context message built from the error
+pub trait ProblemWhileWithErr<O, E> {
+    /// Add context information from function call given reference to the error
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    /// use std::fs::File;
+    ///
+    /// let path = "/nonexistent/config.toml";
+    /// let problem = File::open(path)
+    ///     .problem_while_with_err(|err| format!("opening {} ({:?})", path, err.kind()))
+    ///     .unwrap_err();
+    ///
+    /// assert!(problem.to_string().starts_with("while opening /nonexistent/config.toml (NotFound) got error caused by: "));
+    /// ```
+    fn problem_while_with_err<F, M>(self, message: F) -> Result<O, Problem>
+    where
+        F: FnOnce(&E) -> M,
+        M: ToString;
+}
+
+impl<O, E> ProblemWhileWithErr<O, E> for Result<O, E>
+where
+    E: ToProblem,
+{
+    #[inline]
+    fn problem_while_with_err<F, M>(self, message: F) -> Result<O, Problem>
+    where
+        F: FnOnce(&E) -> M,
+        M: ToString,
+    {
+        match self {
+            Ok(ok) => Ok(ok),
+            Err(err) => {
+                let message = message(&err);
+                Err(problem_while_cold(err, || message))
+            }
+        }
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn problem_while_cold<M: ToString>(err: impl ToProblem, message: impl FnOnce() -> M) -> Problem {
+    err.into_problem().problem_while(message())
+}
+
+static CONTEXT_ELAPSED: AtomicBool = AtomicBool::new(false);
+
+/// Measure how long closures executed with `in_context_of*` functions run and append it to the context message on error,
+/// e.g. `while downloading (failed after 3.2s)`
+pub fn set_context_elapsed(enabled: bool) {
+    CONTEXT_ELAPSED.store(enabled, Ordering::Relaxed)
+}
+
+thread_local! {
+    /// Start of the outermost `in_context_of*` call executing on this thread when context timestamps are enabled
+    static OPERATION_START: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// Start of the operation executing on this thread (see `set_context_timestamps`) or now if there is none
+fn operation_start() -> Instant {
+    OPERATION_START
+        .try_with(Cell::get)
+        .ok()
+        .flatten()
+        .unwrap_or_else(Instant::now)
+}
+
+/// Ends operation started with `start_operation` when dropped (also on panic)
+struct OperationGuard(bool);
+
+impl Drop for OperationGuard {
+    fn drop(&mut self) {
+        if self.0 {
+            OPERATION_START.try_with(|start| start.set(None)).ok();
+        }
+    }
+}
+
+/// Mark start of the operation if `enabled` and no operation is executing on this thread yet
+fn start_operation(enabled: bool) -> OperationGuard {
+    let started = enabled
+        && OPERATION_START
+            .try_with(|start| {
+                if start.get().is_some() {
+                    return false;
+                }
+                start.set(Some(Instant::now()));
+                true
+            })
+            .unwrap_or(false);
+    OperationGuard(started)
+}
+
+/// Executes closure with `problem_while` context
+///
+/// If enabled with `set_context_stack`, while the closure is executing the message is also available on the current thread
+/// context stack (see `context_stack`).
+/// The message can be any `ToString` (e.g. `&str` or `String` made with `format!`); it is converted only on error unless the
+/// context stack is enabled, in which case it is converted once before the closure runs.
+pub fn in_context_of<O, M, B>(message: M, body: B) -> Result<O, Problem>
+where
+    M: ToString,
+    B: FnOnce() -> Result<O, Problem>,
+{
+    let _operation = start_operation(CONTEXT_TIMESTAMPS.load(Ordering::Relaxed));
+    if !CONTEXT_STACK_ENABLED.load(Ordering::Relaxed) {
+        return run_timed(CONTEXT_ELAPSED.load(Ordering::Relaxed), body)
+            .map_err(|(problem, elapsed)| problem.problem_while(with_elapsed(message, elapsed)));
+    }
+
+    let pushed = push_context_stack(message.to_string());
+    let result = run_timed(CONTEXT_ELAPSED.load(Ordering::Relaxed), body);
+    let message = match pushed {
+        Ok(depth) => pop_context_stack(depth),
+        Err(message) => Some(message),
+    };
+    result.map_err(|(problem, elapsed)| {
+        problem.problem_while(with_elapsed(message.unwrap_or_default(), elapsed))
+    })
+}
+
+/// Executes closure with `problem_while_with` context
+///
+/// The message is built only on error unless the context stack is enabled, in which case it is built before the closure runs
+/// so that it can be pushed on the stack.
+pub fn in_context_of_with<O, F, M, B>(message: F, body: B) -> Result<O, Problem>
+where
+    F: FnOnce() -> M,
+    M: ToString,
+    B: FnOnce() -> Result<O, Problem>,
+{
+    if CONTEXT_STACK_ENABLED.load(Ordering::Relaxed) {
+        return in_context_of(message(), body);
+    }
+    let _operation = start_operation(CONTEXT_TIMESTAMPS.load(Ordering::Relaxed));
+    run_timed(CONTEXT_ELAPSED.load(Ordering::Relaxed), body)
+        .map_err(|(problem, elapsed)| problem.problem_while(with_elapsed(message(), elapsed)))
+}
+
+/// Run closure measuring its run time if `timed`
+fn run_timed<O, B>(timed: bool, body: B) -> Result<O, (Problem, Option<Duration>)>
+where
+    B: FnOnce() -> Result<O, Problem>,
+{
+    let start = if timed { Some(Instant::now()) } else { None };
+    body().map_err(|problem| (problem, start.map(|start| start.elapsed())))
+}
+
+fn with_elapsed(message: impl ToString, elapsed: Option<Duration>) -> String {
+    match elapsed {
+        Some(elapsed) => format!("{} (failed after {:.1?})", message.to_string(), elapsed),
+        None => message.to_string(),
+    }
+}
+
+/// Executes closure for each item stopping on first error
+///
+/// The error gets `problem_while` context with given message followed by index (counting from 0) and `Debug` representation of the failing item,
+/// e.g. `while processing record #2 ("foo")`.
+pub fn try_for_each_problem<I, E, B>(message: &str, items: I, mut body: B) -> Result<(), Problem>
+where
+    I: IntoIterator,
+    I::Item: fmt::Debug,
+    B: FnMut(&I::Item) -> Result<(), E>,
+    E: ToProblem,
+{
+    for (index, item) in items.into_iter().enumerate() {
+        body(&item)
+            .map_err(ToProblem::into_problem)
+            .problem_while_with(|| format!("{} #{} ({:?})", message, index, item))?;
+    }
+    Ok(())
+}
+
+static CONTEXT_STACK_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Keep messages of `in_context_of` calls that are executing on each thread on its context stack (see `context_stack`)
+///
+/// The stacks are used to report what the program was doing when it was interrupted (see `report_on_signal`, which enables
+/// them), panicked in a worker (see `spawn_compute`) or created a `Problem` (see `set_problem_creation_hook`). They are disabled
+/// by default as keeping them costs a lock and an allocation for every `in_context_of` call.
+pub fn set_context_stack(enabled: bool) {
+    CONTEXT_STACK_ENABLED.store(enabled, Ordering::Relaxed)
+}
+
+type ContextStack = Mutex<Vec<String>>;
+
+/// Context stacks of all threads that used `in_context_of` with their thread names
+static CONTEXT_STACKS: Mutex<Vec<(Option<String>, Weak<ContextStack>)>> = Mutex::new(Vec::new());
+
+thread_local! {
+    static CONTEXT_STACK: Arc<ContextStack> = {
+        let stack = Arc::<ContextStack>::default();
+        let mut stacks = CONTEXT_STACKS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        stacks.retain(|(_, stack)| stack.strong_count() > 0);
+        stacks.push((std::thread::current().name().map(ToOwned::to_owned), Arc::downgrade(&stack)));
+        stack
+    };
+}
+
+/// Move message on the context stack returning its depth, or give it back if the stack is gone (thread is exiting)
+fn push_context_stack(message: String) -> Result<usize, String> {
+    let mut message = Some(message);
+    CONTEXT_STACK
+        .try_with(|stack| {
+            let mut stack = stack
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            stack.extend(message.take());
+            stack.len() - 1
+        })
+        .map_err(|_| message.take().unwrap_or_default())
+}
+
+fn truncate_context_stack(depth: usize) {
+    CONTEXT_STACK
+        .try_with(|stack| {
+            stack
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .truncate(depth)
+        })
+        .ok();
+}
+
+/// Remove message at given depth (and any left above it by a panicking closure) from the context stack and return it
+fn pop_context_stack(depth: usize) -> Option<String> {
+    CONTEXT_STACK
+        .try_with(|stack| {
+            let mut stack = stack
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if depth < stack.len() {
+                stack.drain(depth..).next()
+            } else {
+                None
+            }
+        })
+        .ok()
+        .flatten()
+}
+
+/// Get messages of `in_context_of` calls that are currently executing on this thread, outermost first
+///
+/// The stack is empty unless enabled with `set_context_stack`. Note that if the closure panics its message remains on the stack until the enclosing `in_context_of` call returns.
+pub fn context_stack() -> Vec<String> {
+    CONTEXT_STACK
+        .try_with(|stack| {
+            stack
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .clone()
+        })
+        .unwrap_or_default()
+}
+
+/// Get context stacks (see `context_stack`) of all running threads with their names
+#[cfg(all(feature = "signals", unix))]
+fn all_context_stacks() -> Vec<(Option<String>, Vec<String>)> {
+    CONTEXT_STACKS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+        .filter_map(|(name, stack)| {
+            stack.upgrade().map(|stack| {
+                let stack = stack
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                (name.clone(), stack.clone())
+            })
+        })
+        .collect()
+}
+
+/// Panic with given `Problem` as the payload
+///
+/// Unlike `or_failed_to` the `Problem` is not formatted into panic message but kept as `ProblemError` payload, so when the
+/// panic is caught (e.g. with `std::panic::catch_unwind` or `JoinHandle::join`) `Problem::from_panic_payload` restores it
+/// with its context, backtrace and attachments. Panic hooks of this crate report the `Problem` if the panic is not caught.
+///
+/// ```rust
+/// use problem::prelude::*;
+/// use std::panic;
+///
+/// let payload = panic::catch_unwind(|| {
+///     let problem = Problem::from_error("boom!").problem_while("processing job 42").with_exit_code(3);
+///     problem::resume_with(problem)
+/// }).unwrap_err();
+///
+/// let problem = Problem::from_panic_payload(payload).problem_while("running jobs");
+/// assert_eq!(problem.to_string(), "while running jobs, while processing job 42 got error caused by: boom!");
+/// assert_eq!(problem.exit_code(), Some(3));
+/// ```
+pub fn resume_with(problem: Problem) -> ! {
+    panic::panic_any(ProblemError::from(problem))
+}
+
+static FATAL_SINK: Mutex<Option<fn(&str)>> = Mutex::new(None);
+
+/// Set function that will be called with fatal error message before `or_failed_to` panics
+///
+/// This allows targets without `stderr` or panic hooks to route fatal messages to their UART or RTT writer. The sink receives
+/// minimal message: context messages and error message of the `Problem`, without sections and backtrace; the panic message
+/// still contains the full report.
+///
+/// Note that this crate requires `std`; there is no `no_std` build of it.
+///
+/// ```rust
+/// use problem::prelude::*;
+/// use std::panic;
+/// use std::sync::Mutex;
+///
+/// static UART: Mutex<String> = Mutex::new(String::new());
+///
+/// problem::set_fatal_sink(Some(|message: &str| UART.lock().unwrap().push_str(message)));
+///
+/// let result = panic::catch_unwind(|| {
+///     "80x".parse::<u16>().problem_while("reading config").or_failed_to("start up")
+/// });
+/// problem::set_fatal_sink(None);
+///
+/// let panic_message = result.unwrap_err().downcast::<String>().unwrap();
+/// assert!(panic_message.starts_with("Failed to start up due to: while reading config"));
+/// assert_eq!(
+///     *UART.lock().unwrap(),
+///     "Failed to start up due to: while reading config got error caused by: invalid digit found in string"
+/// );
+/// ```
+pub fn set_fatal_sink(sink: Option<fn(&str)>) {
+    *FATAL_SINK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = sink;
+}
+
+fn fatal_sink() -> Option<fn(&str)> {
+    *FATAL_SINK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Extension of `Result` that allows program to panic with `Display` message on `Err` for fatal application errors that are not bugs
+pub trait FailedTo<O> {
+    fn or_failed_to(self, message: impl Display) -> O;
+}
+
+impl<O, E> FailedTo<O> for Result<O, E>
+where
+    E: ToProblem,
+{
+    fn or_failed_to(self, message: impl Display) -> O {
+        self.unwrap_or_else(|err| failed_to(message, err.into_problem()))
+    }
+}
+
+/// Record exit code of the `Problem`, pass minimal message to fatal sink and panic with the full report
+fn failed_to(message: impl Display, problem: Problem) -> ! {
+    FAILED_TO_EXIT_CODE.with(|code| code.set(problem.fatal_exit_code()));
+    if let Some(sink) = fatal_sink() {
+        sink(&format!("Failed to {} due to: {}", message, problem))
+    }
+    panic!("Failed to {} due to: {:#}", message, problem)
+}
+
+impl<O> FailedTo<O> for Option<O> {
+    fn or_failed_to(self, message: impl Display) -> O {
+        self.unwrap_or_else(|| {
+            if let Some(sink) = fatal_sink() {
+                sink(&format!("Failed to {}", message))
+            }
+            panic!("Failed to {}", message)
+        })
+    }
+}
+
+/// Extension of `Option<Result<O, E>>` (e.g. returned by `Iterator::next`) that allows program to panic with `Display` message
+/// on `None` or `Err`
+///
+/// Panics with "Failed to {message}" on `None` and "Failed to {message} due to: {problem}" on `Some(Err)`. This is a separate
+/// method as implementing `FailedTo` for `Option<Result<O, E>>` would make `or_failed_to` on `Option<O>` ambiguous.
+///
+/// ```rust
+/// use problem::prelude::*;
+/// use std::collections::HashMap;
+///
+/// let mut ports = HashMap::new();
+/// ports.insert("http", "80".parse::<u16>());
+///
+/// assert_eq!(ports.remove("http").flatten_or_failed_to("get HTTP port"), 80);
+/// ```
+pub trait FlattenFailedTo<O> {
+    fn flatten_or_failed_to(self, message: impl Display) -> O;
+}
+
+impl<O, E> FlattenFailedTo<O> for Option<Result<O, E>>
+where
+    E: ToProblem,
+{
+    fn flatten_or_failed_to(self, message: impl Display) -> O {
+        match self {
+            Some(result) => result.or_failed_to(message),
+            None => None::<O>.or_failed_to(message),
+        }
+    }
+}
+
+/// Iterator that will panic on first error with message displaying `Display` formatted message
+pub struct ProblemIter<I, M> {
+    inner: I,
+    message: M,
+}
+
+impl<I, O, E, M> Iterator for ProblemIter<I, M>
+where
+    I: Iterator<Item = Result<O, E>>,
+    E: ToProblem,
+    M: Display,
+{
+    type Item = O;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|res| res.or_failed_to(&self.message))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<I, O, E, M> DoubleEndedIterator for ProblemIter<I, M>
+where
+    I: DoubleEndedIterator<Item = Result<O, E>>,
+    E: ToProblem,
+    M: Display,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|res| res.or_failed_to(&self.message))
+    }
+}
+
+impl<I, O, E, M> ExactSizeIterator for ProblemIter<I, M>
+where
+    I: ExactSizeIterator<Item = Result<O, E>>,
+    E: ToProblem,
+    M: Display,
+{
+}
+
+impl<I, O, E, M> FusedIterator for ProblemIter<I, M>
+where
+    I: FusedIterator<Item = Result<O, E>>,
+    E: ToProblem,
+    M: Display,
+{
+}
+
+/// Iterator that will panic on first error with message returned by a closure called only when error is encountered
+pub struct ProblemIterWith<I, F> {
+    inner: I,
+    message: Option<F>,
+}
+
+impl<I, O, E, F, M> Iterator for ProblemIterWith<I, F>
+where
+    I: Iterator<Item = Result<O, E>>,
+    E: ToProblem,
+    F: FnOnce() -> M,
+    M: Display,
+{
+    type Item = O;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let message = &mut self.message;
+        self.inner
+            .next()
+            .map(|res| unwrap_or_failed_to_with(res, message))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<I, O, E, F, M> DoubleEndedIterator for ProblemIterWith<I, F>
+where
+    I: DoubleEndedIterator<Item = Result<O, E>>,
+    E: ToProblem,
+    F: FnOnce() -> M,
+    M: Display,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let message = &mut self.message;
+        self.inner
+            .next_back()
+            .map(|res| unwrap_or_failed_to_with(res, message))
+    }
+}
+
+impl<I, O, E, F, M> ExactSizeIterator for ProblemIterWith<I, F>
+where
+    I: ExactSizeIterator<Item = Result<O, E>>,
+    E: ToProblem,
+    F: FnOnce() -> M,
+    M: Display,
+{
+}
+
+impl<I, O, E, F, M> FusedIterator for ProblemIterWith<I, F>
+where
+    I: FusedIterator<Item = Result<O, E>>,
+    E: ToProblem,
+    F: FnOnce() -> M,
+    M: Display,
+{
+}
+
+/// Unwrap item of `ProblemIterWith` or panic with message built by the closure
+fn unwrap_or_failed_to_with<O, E, F, M>(res: Result<O, E>, message: &mut Option<F>) -> O
+where
+    E: ToProblem,
+    F: FnOnce() -> M,
+    M: Display,
+{
+    res.unwrap_or_else(|err| {
+        let message = message.take().expect("ProblemIterWith used after panic")();
+        failed_to(message, err.into_problem())
+    })
+}
+
+/// Convert `Iterator` of `Result<O, E>` to iterator of `O` and panic on first `E` with problem message
+pub trait FailedToIter<O, E, M>: Sized {
+    fn or_failed_to(self, message: M) -> ProblemIter<Self, M>;
+
+    /// Like `or_failed_to` but message is built by given closure only when `E` is encountered
+    fn or_failed_to_with<F>(self, message: F) -> ProblemIterWith<Self, F>
+    where
+        F: FnOnce() -> M,
+    {
+        ProblemIterWith {
+            inner: self,
+            message: Some(message),
+        }
+    }
+}
+
+impl<I, O, E, M> FailedToIter<O, E, M> for I
+where
+    I: Iterator<Item = Result<O, E>>,
+    E: ToProblem,
+    M: Display,
+{
+    fn or_failed_to(self, message: M) -> ProblemIter<Self, M> {
+        ProblemIter {
+            inner: self,
+            message,
+        }
+    }
+}
+
+/// Iterator that yields `Ok` values until first `Err` is encountered; the error is kept as `Problem`
+/// and can be retrieved with `first_problem`
+pub struct ProblemTakeUntilIter<I> {
+    inner: I,
+    problem: Option<Problem>,
+    done: bool,
+}
+
+impl<I> ProblemTakeUntilIter<I> {
+    /// `Problem` that stopped the iteration, if any
+    pub fn first_problem(&self) -> Option<&Problem> {
+        self.problem.as_ref()
+    }
+
+    /// Consume the iterator returning `Problem` that stopped the iteration, if any
+    pub fn into_first_problem(self) -> Option<Problem> {
+        self.problem
+    }
+}
+
+impl<I, O, E> Iterator for ProblemTakeUntilIter<I>
+where
+    I: Iterator<Item = Result<O, E>>,
+    E: ToProblem,
+{
+    type Item = O;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.inner.next() {
+            Some(Ok(ok)) => Some(ok),
+            Some(Err(err)) => {
+                self.problem = Some(err.into_problem());
+                self.done = true;
+                None
+            }
+            None => {
+                self.done = true;
+                None
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            (0, Some(0))
+        } else {
+            (0, self.inner.size_hint().1)
+        }
+    }
+}
+
+impl<I, O, E> FusedIterator for ProblemTakeUntilIter<I>
+where
+    I: Iterator<Item = Result<O, E>>,
+    E: ToProblem,
+{
+}
+
+/// Convert `Iterator` of `Result<O, E>` to iterator of `O` that stops on first `E` keeping it as `Problem`
+pub trait TakeUntilProblemIter<O, E>: Sized {
+    fn take_until_problem(self) -> ProblemTakeUntilIter<Self>;
+}
+
+impl<I, O, E> TakeUntilProblemIter<O, E> for I
+where
+    I: Iterator<Item = Result<O, E>>,
+    E: ToProblem,
+{
+    fn take_until_problem(self) -> ProblemTakeUntilIter<Self> {
+        ProblemTakeUntilIter {
+            inner: self,
+            problem: None,
+            done: false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    errors: AtomicU64,
+    warnings: AtomicU64,
+    suppressed: AtomicU64,
+}
+
+/// Shared counter of `Problem`s that were logged as errors or warnings or suppressed
+///
+/// Clones share the same counts so it can be passed to worker threads and read by health endpoints or end-of-run summaries.
+/// `ok_or_log_*` methods count logged `Problem`s in `ProblemCounter::global()`.
+///
+/// ```rust
+/// use problem::prelude::*;
+/// use problem::ProblemCounter;
+///
+/// let counter = ProblemCounter::new();
+/// let numbers: Vec<u32> = ["1", "x", "3"].iter()
+///     .filter_map(|input| input.parse().ok_or_count(&counter))
+///     .collect();
+///
+/// assert_eq!(numbers, [1, 3]);
+/// assert_eq!(counter.suppressed(), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ProblemCounter {
+    counts: Arc<Counts>,
+}
+
+impl ProblemCounter {
+    /// Create counter with all counts zero
+    pub fn new() -> ProblemCounter {
+        ProblemCounter::default()
+    }
+
+    /// Process wide counter used by `ok_or_log_*` methods
+    pub fn global() -> &'static ProblemCounter {
+        static GLOBAL: OnceLock<ProblemCounter> = OnceLock::new();
+        GLOBAL.get_or_init(ProblemCounter::new)
+    }
+
+    /// Count `Problem` logged as error
+    pub fn count_error(&self) {
+        self.counts.errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count `Problem` logged as warning
+    pub fn count_warning(&self) {
+        self.counts.warnings.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count `Problem` that was ignored without reporting
+    pub fn count_suppressed(&self) {
+        self.counts.suppressed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of `Problem`s logged as errors
+    pub fn errors(&self) -> u64 {
+        self.counts.errors.load(Ordering::Relaxed)
+    }
+
+    /// Number of `Problem`s logged as warnings
+    pub fn warnings(&self) -> u64 {
+        self.counts.warnings.load(Ordering::Relaxed)
+    }
+
+    /// Number of `Problem`s ignored without reporting
+    pub fn suppressed(&self) -> u64 {
+        self.counts.suppressed.load(Ordering::Relaxed)
+    }
+
+    /// Number of all counted `Problem`s
+    pub fn total(&self) -> u64 {
+        self.errors() + self.warnings() + self.suppressed()
+    }
+}
+
+/// Number of `Problem`s created by this process
+static PROBLEMS_CREATED: AtomicU64 = AtomicU64::new(0);
+
+type CreationHook = Arc<dyn Fn(&Problem, &[String]) + Send + Sync>;
+
+static CREATION_HOOK: Mutex<Option<CreationHook>> = Mutex::new(None);
+static CREATION_HOOK_SET: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static THREAD_PROBLEMS_CREATED: Cell<u64> = const { Cell::new(0) };
+    static IN_CREATION_HOOK: Cell<bool> = const { Cell::new(false) };
+}
+
+fn problem_created(problem: &Problem) {
+    PROBLEMS_CREATED.fetch_add(1, Ordering::Relaxed);
+    THREAD_PROBLEMS_CREATED
+        .try_with(|created| created.set(created.get() + 1))
+        .ok();
+
+    if CREATION_HOOK_SET.load(Ordering::Relaxed) && !IN_CREATION_HOOK.with(Cell::get) {
+        let hook = CREATION_HOOK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone();
+        if let Some(hook) = hook {
+            IN_CREATION_HOOK.with(|in_hook| in_hook.set(true));
+            let _guard = CreationHookGuard;
+            hook(problem, &context_stack());
+        }
+    }
+}
+
+/// Clears the in creation hook flag on drop so that the hook is called again after it panicked
+struct CreationHookGuard;
+
+impl Drop for CreationHookGuard {
+    fn drop(&mut self) {
+        IN_CREATION_HOOK.try_with(|in_hook| in_hook.set(false)).ok();
+    }
+}
+
+/// Get number of `Problem`s created by this process so far
+pub fn problems_created() -> u64 {
+    PROBLEMS_CREATED.load(Ordering::Relaxed)
+}
+
+/// Execute closure counting `Problem`s created by current thread while it runs
+///
+/// This can be used in performance tests to assert that happy path does not create any `Problem`s.
+///
+/// ```rust
+/// use problem::prelude::*;
+///
+/// let (port, created) = problem::count_problems_created(|| "8080".parse::<u16>().map_problem());
+/// assert!(port.is_ok());
+/// assert_eq!(created, 0);
+/// ```
+pub fn count_problems_created<O>(body: impl FnOnce() -> O) -> (O, u64) {
+    let before = THREAD_PROBLEMS_CREATED.with(Cell::get);
+    let output = body();
+    (output, THREAD_PROBLEMS_CREATED.with(Cell::get) - before)
+}
+
+/// Set hook called each time new `Problem` is created
+///
+/// The hook is called with the new `Problem` and snapshot of context messages of `in_context_of` calls executing on current
+/// thread (see `context_stack`). `Problem`s created by the hook itself do not call it again.
+///
+/// ```rust
+/// use problem::prelude::*;
+///
+/// problem::on_problem_created(|problem, contexts| {
+///     eprintln!("created {} while {}", problem.brief(), contexts.join(", "));
+/// });
+/// ```
+pub fn on_problem_created(hook: impl Fn(&Problem, &[String]) + Send + Sync + 'static) {
+    *CREATION_HOOK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(Arc::new(hook));
+    CREATION_HOOK_SET.store(true, Ordering::Relaxed);
+}
+
+#[cfg_attr(not(any(unix, windows)), allow(dead_code))]
+fn format_stats(created: u64, counter: &ProblemCounter) -> String {
+    format!(
+        "Problem statistics: {} created, {} logged ({} errors, {} warnings), {} suppressed",
+        created,
+        counter.errors() + counter.warnings(),
+        counter.errors(),
+        counter.warnings(),
+        counter.suppressed()
+    )
+}
+
+/// Print statistics of `Problem`s to standard error when program exits normally
+///
+/// Printed are numbers of `Problem`s created and reported to sinks (e.g. logged by `ok_or_log_*` methods) or suppressed with `ok_or_count` on
+/// `ProblemCounter::global()` counter. This can help to spot errors that are silently ignored.
+///
+/// Note that statistics are not printed when program exits via `panic!` or signal.
+///
+/// Available on Unix and Windows only as the statistics are printed by handler registered with C runtime `atexit` function.
+#[cfg(any(unix, windows))]
+pub fn report_stats_at_exit() {
+    extern "C" fn print_stats() {
+        eprintln!(
+            "{}",
+            format_stats(
+                PROBLEMS_CREATED.load(Ordering::Relaxed),
+                ProblemCounter::global()
+            )
+        );
+    }
+
+    static REGISTERED: AtomicBool = AtomicBool::new(false);
+    if !REGISTERED.swap(true, Ordering::Relaxed) {
+        unsafe {
+            libc::atexit(print_stats);
+        }
+    }
+}
+
+#[macro_use]
+pub mod sink;
+pub use sink::strict_mode;
+
+pub mod health;
+
+#[cfg(feature = "prometheus")]
+pub mod metrics;
+
+/// Extension of `Result` that allows to ignore `Err` while counting it as suppressed in given `ProblemCounter`
+pub trait OkOrCount<O> {
+    fn ok_or_count(self, counter: &ProblemCounter) -> Option<O>;
+}
+
+impl<O, E> OkOrCount<O> for Result<O, E> {
+    fn ok_or_count(self, counter: &ProblemCounter) -> Option<O> {
+        self.map_err(|_| counter.count_suppressed()).ok()
+    }
+}
+
+#[cfg(all(feature = "signals", unix))]
+pub mod signals;
+#[cfg(all(feature = "signals", unix))]
+pub use signals::report_on_signal;
+
+#[cfg(feature = "clap")]
+pub mod cli;
+#[cfg(feature = "clap")]
+pub use cli::parse_args_or_problem;
+
+#[cfg(feature = "gelf")]
+pub mod gelf;
+
+#[cfg(feature = "color")]
+pub mod color;
+
+#[cfg(feature = "testing")]
+pub mod test;
+
+#[cfg(feature = "process")]
+pub mod process;
+
+#[cfg(feature = "wire")]
+pub mod wire;
+
+#[cfg(feature = "serde")]
+pub mod serialized;
+
+pub mod compact;
+
+#[cfg(feature = "defmt")]
+pub mod defmt_logged;
+
+#[cfg(feature = "i18n")]
+pub mod l10n;
+
+#[cfg(feature = "crossbeam")]
+pub mod crossbeam;
+
+mod report;
+pub use report::RemoteProblem;
+
+mod detached;
+pub use detached::ProblemError;
+
+mod retained;
+use retained::StoredBacktrace;
+pub use retained::{backtrace_memory_retained, set_backtrace_memory_budget, BACKTRACE_DROPPED};
+
+#[cfg(feature = "backtrace")]
+mod frames;
+#[cfg(feature = "backtrace")]
+pub use frames::{
+    add_backtrace_path_map, refresh_backtrace_policy, set_backtrace_capture, set_backtrace_style,
+    set_raw_backtrace_symbols, set_shorten_backtrace_paths, BacktraceCapture, BacktraceStyle,
+};
+
+mod timeout;
+pub use timeout::with_timeout;
+#[cfg(feature = "futures")]
+pub use timeout::{with_timeout_async, Timeout};
+
+pub mod kind;
+pub use kind::{register_kind, ProblemKind};
+
+mod pool;
+pub use pool::ProblemPool;
+
+mod typed;
+pub use typed::{ProblemWhileTyped, TypedProblem};
+
+mod catch;
+#[cfg(feature = "futures")]
+pub use catch::{catch_async, CatchAsync};
+pub use catch::{spawn_compute, ComputeHandle};
+#[cfg(feature = "tokio")]
+pub use catch::{spawn_compute_async, ComputeTask};
+
+#[cfg(feature = "log")]
+pub mod logged {
+    use super::sink::{count, is_reported, report, LogSink, OkOrReport, OkOrReportIter, Severity};
+    use super::*;
+    use log::Level;
+
+    /// Extension of `Result` that allows program to log on `Err` with `Display` message for application errors that are not critical
+    pub trait OkOrLog<O> {
+        fn ok_or_log_warn(self) -> Option<O>;
+        fn ok_or_log_error(self) -> Option<O>;
+
+        /// Log as warn or error on `Err` depending on kind of the `Problem` (see `Problem::severity`) and continue
+        fn ok_or_log(self) -> Option<O>;
+
+        /// Log as warn on `Err` keeping the `Problem` in returned `Logged` so it can be escalated later
+        fn logged_warn(self) -> Logged<O>;
+
+        /// Log as error on `Err` keeping the `Problem` in returned `Logged` so it can be escalated later
+        fn logged_error(self) -> Logged<O>;
+
+        /// Log as warn on `Err` and continue with default value
+        fn or_default_log_warn(self) -> O
+        where
+            Self: Sized,
+            O: Default,
+        {
+            self.ok_or_log_warn().unwrap_or_default()
+        }
+
+        /// Log as error on `Err` and continue with default value
+        fn or_default_log_error(self) -> O
+        where
+            Self: Sized,
+            O: Default,
+        {
+            self.ok_or_log_error().unwrap_or_default()
+        }
+    }
+
+    impl<O, E> OkOrLog<O> for Result<O, E>
+    where
+        E: ToProblem,
+    {
+        fn ok_or_log_warn(self) -> Option<O> {
+            self.ok_or_report(LogSink, Severity::Warning)
+        }
+
+        fn ok_or_log_error(self) -> Option<O> {
+            self.ok_or_report(LogSink, Severity::Error)
+        }
+
+        fn ok_or_log(self) -> Option<O> {
+            self.map_err(|err| {
+                let problem = err.into_problem();
+                report(&mut LogSink, problem.severity(), &problem)
+            })
+            .ok()
+        }
+
+        fn logged_warn(self) -> Logged<O> {
+            Logged {
+                result: self.map_err(|err| {
+                    let problem = err.into_problem();
+                    report(&mut LogSink, Severity::Warning, &problem);
+                    problem
+                }),
+            }
+        }
+
+        fn logged_error(self) -> Logged<O> {
+            Logged {
+                result: self.map_err(|err| {
+                    let problem = err.into_problem();
+                    report(&mut LogSink, Severity::Error, &problem);
+                    problem
+                }),
+            }
+        }
+    }
+
+    /// Result of `logged_warn` or `logged_error`; keeps `Problem` that was logged so that it can be escalated later
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    ///
+    /// let mut failures = 0;
+    /// let mut last = None;
+    /// for input in ["1", "x", "y"] {
+    ///     let number = input.parse::<u32>().logged_warn();
+    ///     if number.is_problem() {
+    ///         failures += 1;
+    ///     }
+    ///     last = Some(number);
+    /// }
+    ///
+    /// if failures > 1 {
+    ///     let problem = last.unwrap().escalate("too many failures").unwrap_err();
+    ///     assert_eq!(problem.brief(), "while too many failures got error caused by: invalid digit found in string");
+    /// }
+    /// ```
+    #[derive(Debug)]
+    #[must_use]
+    pub struct Logged<T> {
+        result: Result<T, Problem>,
+    }
+
+    impl<T> Logged<T> {
+        /// Returns `true` if `Problem` was logged
+        pub fn is_problem(&self) -> bool {
+            self.result.is_err()
+        }
+
+        /// Get logged `Problem`
+        pub fn problem(&self) -> Option<&Problem> {
+            self.result.as_ref().err()
+        }
+
+        /// Get the value discarding logged `Problem`
+        pub fn ok(self) -> Option<T> {
+            self.result.ok()
+        }
+
+        /// Convert back to `Result`
+        pub fn into_result(self) -> Result<T, Problem> {
+            self.result
+        }
+
+        /// Turn logged `Problem` into `Err` with given message added as its context (see `ProblemWhile::problem_while`)
+        ///
+        /// Tags, exit code, kind, sections and backtrace of the logged `Problem` are kept.
+        pub fn escalate(self, message: impl ToString) -> Result<T, Problem> {
+            self.result
+                .map_err(|problem| problem.problem_while(message))
+        }
+    }
+
+    /// Format of the log messages produced by `ok_or_log_*` methods
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum LogFormat {
+        /// `Continuing with error: ` followed by `Problem::brief` message; `Problem::report` is logged at debug level if it
+        /// contains more information (default)
+        Display,
+        /// Single line of `logfmt` key-value pairs as produced by `Problem::to_logfmt`
+        Logfmt,
+    }
+
+    static LOGFMT: AtomicBool = AtomicBool::new(false);
+
+    /// Set format of the log messages produced by `ok_or_log_*` methods
+    pub fn set_log_format(format: LogFormat) {
+        LOGFMT.store(format == LogFormat::Logfmt, Ordering::Relaxed)
+    }
+
+    /// Returns `true` if `log_problem` would log at given level
+    pub(crate) fn is_log_enabled(level: Level) -> bool {
+        log_enabled!(level)
+    }
+
+    pub(crate) fn log_problem(level: Level, problem: &Problem) {
+        if LOGFMT.load(Ordering::Relaxed) {
+            log!(level, "{}", problem.to_logfmt());
+        } else {
+            let brief = problem.brief();
+            log!(level, "Continuing with error: {}", brief);
+
+            if log_enabled!(Level::Debug) {
+                let report = problem.log_report();
+                if report != brief {
+                    debug!("Report of error: {}", report);
+                }
+            }
+        }
+    }
+
+    reporting_iter! {
+        /// Iterator that will log as warn `Display` formatted message on `Err` and skip to next item; it can be flattened to skip failed items
+        pub struct ProblemWarnLoggingIter<I>(LogSink);
+    }
+
+    reporting_iter! {
+        /// Iterator that will log as error `Display` formatted message on `Err` and skip to next item; it can be flattened to skip failed items
+        pub struct ProblemErrorLoggingIter<I>(LogSink);
+    }
+
+    /// Error encountered by `ProblemThresholdIter`; converted to `Problem` only when logged or yielded
+    enum Failure<E> {
+        Pending(E),
+        Logged(Problem),
+    }
+
+    /// Iterator that will log as warn `Err` items until given number of errors was encountered; then it yields `Err` with
+    /// `Problems` containing all of them and ends
+    ///
+    /// Errors are converted to `Problem` only when they are logged (see `ProblemSink::is_enabled`) or yielded.
+    pub struct ProblemThresholdIter<I, E, M> {
+        inner: I,
+        errors: usize,
+        message: M,
+        failures: Vec<Failure<E>>,
+        done: bool,
+    }
+
+    impl<I, O, E, M> Iterator for ProblemThresholdIter<I, E, M>
+    where
+        I: Iterator<Item = Result<O, E>>,
+        E: ToProblem,
+        M: ToString,
+    {
+        type Item = Result<O, Problem>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            while !self.done {
+                match self.inner.next()? {
+                    Ok(ok) => return Some(Ok(ok)),
+                    Err(err) => {
+                        if self.failures.len() + 1 >= self.errors {
+                            self.failures.push(Failure::Pending(err));
+                            self.done = true;
+                            let mut problems = Problems::new();
+                            for failure in self.failures.drain(..) {
+                                problems.push(match failure {
+                                    Failure::Pending(err) => err.into_problem(),
+                                    Failure::Logged(problem) => problem,
+                                });
+                            }
+                            let problems = problems.with_message(self.message.to_string());
+                            return Some(Err(Problem::from_error(problems)));
+                        }
+                        if is_reported(&LogSink, Severity::Warning) {
+                            let problem = err.into_problem();
+                            report(&mut LogSink, Severity::Warning, &problem);
+                            self.failures.push(Failure::Logged(problem));
+                        } else {
+                            count(Severity::Warning);
+                            self.failures.push(Failure::Pending(err));
+                        }
+                    }
+                }
+            }
+            None
+        }
+    }
+
+    impl<I, O, E, M> FusedIterator for ProblemThresholdIter<I, E, M>
+    where
+        I: FusedIterator<Item = Result<O, E>>,
+        E: ToProblem,
+        M: ToString,
+    {
+    }
+
+    /// Convert `Iterator` of `Result<O, E>` to iterator of `Option<O>` and log any `Err` variants
+    pub trait OkOrLogIter<O, E>: Sized {
+        fn ok_or_log_warn(self) -> ProblemWarnLoggingIter<Self>;
+        fn ok_or_log_error(self) -> ProblemErrorLoggingIter<Self>;
+
+        /// Log first `errors - 1` errors as warnings and yield `Err` with all of them on the `errors`-th one
+        ///
+        /// ```rust
+        /// use problem::prelude::*;
+        ///
+        /// let records = vec![Ok(1u32), Err("bad checksum"), Ok(2), Err("truncated"), Ok(3)];
+        /// let problem = records.into_iter()
+        ///     .fail_after_errors(2, "too many corrupt records")
+        ///     .collect::<Result<Vec<u32>, Problem>>()
+        ///     .unwrap_err();
+        ///
+        /// # if problem.backtrace().is_none() {
+        /// assert_eq!(problem.to_string(), "too many corrupt records: 2 problems occurred: bad checksum; truncated");
+        /// # }
+        /// ```
+        fn fail_after_errors<M>(self, errors: usize, message: M) -> ProblemThresholdIter<Self, E, M>
+        where
+            M: ToString,
+        {
+            ProblemThresholdIter {
+                inner: self,
+                errors,
+                message,
+                failures: Vec::new(),
+                done: false,
+            }
+        }
+    }
+
+    impl<I, O, E> OkOrLogIter<O, E> for I
+    where
+        I: Iterator<Item = Result<O, E>>,
+        E: ToProblem,
+    {
+        fn ok_or_log_warn(self) -> ProblemWarnLoggingIter<Self> {
+            ProblemWarnLoggingIter(self.ok_or_report(LogSink, Severity::Warning))
+        }
+
+        fn ok_or_log_error(self) -> ProblemErrorLoggingIter<Self> {
+            ProblemErrorLoggingIter(self.ok_or_report(LogSink, Severity::Error))
+        }
+    }
+}
+
+/// Printing of `Problem`s to `stderr` or other sinks for programs that don't use logger
+pub mod printed {
+    use super::sink::{OkOrReport, OkOrReportIter, Severity, StderrSink, WriteSink};
+    use super::*;
+    use std::io;
+
+    /// Extension of `Result` that allows program to print `Problem` to `stderr` on `Err` and continue; this is like
+    /// `OkOrLog` but does not require logger
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    ///
+    /// // Prints: WARNING: invalid digit found in string
+    /// let port = "80x".parse::<u16>().ok_or_print_warn().unwrap_or(80);
+    /// assert_eq!(port, 80);
+    /// ```
+    pub trait OkOrPrint<O> {
+        /// Print `Problem` prefixed with `WARNING:` on `Err`
+        fn ok_or_print_warn(self) -> Option<O>;
+        /// Print `Problem` prefixed with `ERROR:` on `Err`
+        fn ok_or_print_error(self) -> Option<O>;
+    }
+
+    impl<O, E> OkOrPrint<O> for Result<O, E>
+    where
+        E: ToProblem,
+    {
+        fn ok_or_print_warn(self) -> Option<O> {
+            self.ok_or_report(StderrSink, Severity::Warning)
+        }
+
+        fn ok_or_print_error(self) -> Option<O> {
+            self.ok_or_report(StderrSink, Severity::Error)
+        }
+    }
+
+    reporting_iter! {
+        /// Iterator that will print `Problem` to `stderr` on `Err` and skip to next item; it can be flattened to skip failed items
+        pub struct ProblemPrintingIter<I>(StderrSink);
+    }
+
+    /// Convert `Iterator` of `Result<O, E>` to iterator of `Option<O>` and print any `Err` variants to `stderr`
+    pub trait OkOrPrintIter<O, E>: Sized {
+        fn ok_or_print_warn(self) -> ProblemPrintingIter<Self>;
+        fn ok_or_print_error(self) -> ProblemPrintingIter<Self>;
+    }
+
+    impl<I, O, E> OkOrPrintIter<O, E> for I
+    where
+        I: Iterator<Item = Result<O, E>>,
+        E: ToProblem,
+    {
+        fn ok_or_print_warn(self) -> ProblemPrintingIter<Self> {
+            ProblemPrintingIter(self.ok_or_report(StderrSink, Severity::Warning))
+        }
+
+        fn ok_or_print_error(self) -> ProblemPrintingIter<Self> {
+            ProblemPrintingIter(self.ok_or_report(StderrSink, Severity::Error))
+        }
+    }
+
+    /// Extension of `Result` that allows program to write `Problem` as a line into `io::Write` sink (e.g. `errors.txt` file)
+    /// on `Err` and continue
+    ///
+    /// Failures to write into the sink are ignored.
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    ///
+    /// let mut errors = Vec::new();
+    /// let port = "80x".parse::<u16>().ok_or_write(&mut errors).unwrap_or(80);
+    ///
+    /// assert_eq!(port, 80);
+    /// assert_eq!(String::from_utf8(errors).unwrap(), "invalid digit found in string\n");
+    /// ```
+    pub trait OkOrWrite<O> {
+        fn ok_or_write<W: io::Write>(self, sink: &mut W) -> Option<O>;
+    }
+
+    impl<O, E> OkOrWrite<O> for Result<O, E>
+    where
+        E: ToProblem,
+    {
+        fn ok_or_write<W: io::Write>(self, sink: &mut W) -> Option<O> {
+            self.ok_or_report(WriteSink(sink), Severity::Error)
+        }
+    }
+
+    reporting_iter! {
+        /// Iterator that will write `Problem` as a line into `io::Write` sink on `Err` and skip to next item; it can be flattened
+        /// to skip failed items
+        pub struct ProblemWritingIter<'w, I, W>(WriteSink<&'w mut W>);
+    }
+
+    /// Convert `Iterator` of `Result<O, E>` to iterator of `Option<O>` and write any `Err` variants as lines into `io::Write` sink
+    pub trait OkOrWriteIter<O, E>: Sized {
+        fn ok_or_write<W: io::Write>(self, sink: &mut W) -> ProblemWritingIter<'_, Self, W>;
+    }
+
+    impl<I, O, E> OkOrWriteIter<O, E> for I
+    where
+        I: Iterator<Item = Result<O, E>>,
+        E: ToProblem,
+    {
+        fn ok_or_write<W: io::Write>(self, sink: &mut W) -> ProblemWritingIter<'_, Self, W> {
+            ProblemWritingIter(self.ok_or_report(WriteSink(sink), Severity::Error))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn write_iter() {
+            let mut errors = Vec::new();
+            let ok: Vec<u32> = vec![Ok(1u32), Err("oops"), Ok(2), Err("oh")]
+                .into_iter()
+                .ok_or_write(&mut errors)
+                .flatten()
+                .collect();
+            assert_eq!(ok, [1, 2]);
+            assert_eq!(String::from_utf8(errors).unwrap(), "oops\noh\n");
+        }
+
+        #[test]
+        fn print_iter() {
+            let ok: Vec<u32> = vec![Ok(1u32), Err("oops"), Ok(2)]
+                .into_iter()
+                .ok_or_print_warn()
+                .flatten()
+                .collect();
+            assert_eq!(ok, [1, 2]);
+        }
+    }
+}
+
+#[cfg(not(feature = "backtrace"))]
+fn format_backtrace() -> Option<String> {
+    None
+}
+
+#[cfg(not(feature = "backtrace"))]
+fn capture_backtrace() -> Option<StoredBacktrace> {
+    None
+}
+
+#[cfg(all(test, feature = "backtrace"))]
+thread_local! {
+    static NO_BACKTRACES: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Do not capture backtraces of `Problem`s created by current thread so that tests can compare their exact rendering even
+/// with `RUST_BACKTRACE` set
+#[cfg(test)]
+pub(crate) fn disable_backtraces() {
+    #[cfg(feature = "backtrace")]
+    NO_BACKTRACES.with(|disabled| disabled.set(true));
+}
+
+/// Capture backtrace for `Problem` as configured with `set_backtrace_capture`
+#[cfg(feature = "backtrace")]
+#[inline(always)]
+fn capture_backtrace() -> Option<StoredBacktrace> {
+    #[cfg(test)]
+    if NO_BACKTRACES.with(Cell::get) {
+        return None;
+    }
+    if frames::backtrace_capture() == frames::BacktraceCapture::FramePointers
+        && frames::backtrace_style().is_some()
+    {
+        if let Some(frames) = frames::capture_frame_pointers() {
+            return Some(StoredBacktrace::deferred(frames, frames::resolve_frames));
+        }
+    }
+    format_backtrace().map(StoredBacktrace::new)
+}
+
+/* Use default Rust format like:
+   0: std::sys_common::backtrace::_print
+             at C:\projects\rust\src\libstd\sys_common\backtrace.rs:94
+   1: std::sys_common::backtrace::_print
+             at C:\projects\rust\src\libstd\sys_common\backtrace.rs:71
+   or with one line per frame in short style.
+*/
+#[cfg(feature = "backtrace")]
+#[inline(always)]
+fn format_backtrace() -> Option<String> {
+    if let Some(style) = frames::backtrace_style() {
+        let mut writer = frames::BacktraceWriter::new(style);
+        backtrace::trace(|frame| writer.write_frame(frame.ip()));
+        Some(writer.finish())
+    } else {
+        None
+    }
+}
+
+fn format_panic(panic: &std::panic::PanicHookInfo, backtrace: Option<String>) -> String {
+    let mut message = String::new();
+
+    let thread = std::thread::current();
+    let name = thread.name().unwrap_or("<unnamed>");
+
+    // taken from libstd
+    let report;
+    let msg = match panic.payload().downcast_ref::<&'static str>() {
+        Some(s) => *s,
+        None => match panic.payload().downcast_ref::<String>() {
+            Some(s) => &s[..],
+            None => match panic.payload().downcast_ref::<ProblemError>() {
+                // raised with `resume_with`
+                Some(problem) => {
+                    report = format!("{:#}", problem.to_problem());
+                    &report[..]
+                }
+                None => "Box<Any>",
+            },
+        },
+    };
+
+    match (backtrace.is_some() || verbosity() >= 3, panic.location()) {
+        (true, Some(location)) => write!(
+            message,
+            "thread '{}' panicked at {} with: {}",
+            name, location, msg
+        )
+        .ok(),
+        (true, None) => write!(message, "thread '{}' panicked with: {}", name, msg).ok(),
+        (false, _) => write!(message, "{}", msg).ok(),
+    };
+
+    if PROCESS_INFO.load(Ordering::Relaxed) {
+        message.push_str("\n--- Process\n");
+        write_process_info(&mut message).ok();
+    }
+
+    if let Some(backtrace) = backtrace {
+        message.push_str("\n--- Panicked\n");
+        message.push_str(&backtrace);
+    }
+
+    if let Some(id) = error_id(current_trace_id().as_deref()) {
+        write!(message, "\nerror id: {}", id).ok();
+    }
+
+    message
+}
+
+static PROCESS_INFO: AtomicBool = AtomicBool::new(false);
+
+/// Include information about the process (executable path, arguments, working directory and on Unix user ID and resource limits)
+/// in panic hook messages
+///
+/// This is disabled by default as this information may be sensitive.
+pub fn include_process_info(enabled: bool) {
+    PROCESS_INFO.store(enabled, Ordering::Relaxed)
+}
+
+fn write_process_info(out: &mut impl Write) -> fmt::Result {
+    match std::env::current_exe() {
+        Ok(exe) => write!(out, "executable: {}", exe.display())?,
+        Err(err) => write!(out, "executable: <{}>", err)?,
+    }
+    write!(
+        out,
+        "\narguments: {:?}",
+        std::env::args_os().collect::<Vec<_>>()
+    )?;
+    match std::env::current_dir() {
+        Ok(dir) => write!(out, "\nworking directory: {}", dir.display())?,
+        Err(err) => write!(out, "\nworking directory: <{}>", err)?,
+    }
+
+    #[cfg(unix)]
+    {
+        write!(out, "\nuser ID: {}", unsafe { libc::getuid() })?;
+
+        for (name, resource) in &[
+            ("open files", libc::RLIMIT_NOFILE),
+            ("stack size", libc::RLIMIT_STACK),
+            ("core file size", libc::RLIMIT_CORE),
+            ("address space", libc::RLIMIT_AS),
+        ] {
+            let mut limit = libc::rlimit {
+                rlim_cur: 0,
+                rlim_max: 0,
+            };
+            if unsafe { libc::getrlimit(*resource, &mut limit) } == 0 {
+                let format_limit = |value: libc::rlim_t| {
+                    if value == libc::RLIM_INFINITY {
+                        "unlimited".to_owned()
+                    } else {
+                        value.to_string()
+                    }
+                };
+                write!(
+                    out,
+                    "\nlimit {}: {} (hard: {})",
+                    name,
+                    format_limit(limit.rlim_cur),
+                    format_limit(limit.rlim_max)
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn format_panic_backtrace() -> Option<String> {
+    if verbosity() >= 2 {
+        format_backtrace()
+    } else {
+        None
+    }
+}
+
+type FatalCleanup = Box<dyn FnOnce() + Send>;
+
+static FATAL_CLEANUP: Mutex<Vec<FatalCleanup>> = Mutex::new(Vec::new());
+
+/// Register cleanup function to be called before program terminates due to fatal error
+///
+/// Cleanup functions are run in reverse order of registration when panic is not caught before it reaches `run_main`, before
+/// program exits with `FatalProblem` and on signals reported with `report_on_signal`. Each function is called at most once.
+/// Cleanup functions should not panic.
+pub fn on_fatal(cleanup: impl FnOnce() + Send + 'static) {
+    FATAL_CLEANUP
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .push(Box::new(cleanup));
+}
+
+fn run_fatal_cleanup() {
+    let cleanup = std::mem::take(
+        &mut *FATAL_CLEANUP
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()),
+    );
+
+    for cleanup in cleanup.into_iter().rev() {
+        cleanup();
+    }
+}
+
+thread_local! {
+    /// Exit code of `Problem` that `or_failed_to` is panicking with
+    static FAILED_TO_EXIT_CODE: Cell<Option<i32>> = const { Cell::new(None) };
+    /// Exit code of `Problem` the last panic on this thread was caused by, as recorded by panic hook
+    static PANIC_EXIT_CODE: Cell<Option<i32>> = const { Cell::new(None) };
+}
+
+/// Record exit code of `Problem` current panic was caused by with `or_failed_to` or `resume_with`; called by panic hooks
+fn record_panic_exit_code(payload: &(dyn Any + Send)) {
+    let code = FAILED_TO_EXIT_CODE
+        .with(Cell::take)
+        .or_else(|| payload_exit_code(payload));
+    PANIC_EXIT_CODE.with(|recorded| recorded.set(code));
+}
+
+/// Exit code of `Problem` raised with `resume_with`
+fn payload_exit_code(payload: &(dyn Any + Send)) -> Option<i32> {
+    payload
+        .downcast_ref::<ProblemError>()
+        .and_then(|problem| problem.to_problem().fatal_exit_code())
+}
+
+/// Exit code of `Problem` that panic with given payload was caused by
+fn panic_exit_code(payload: &(dyn Any + Send)) -> Option<i32> {
+    PANIC_EXIT_CODE
+        .with(Cell::take)
+        .or_else(|| payload_exit_code(payload))
+}
+
+/// Run body of `main` terminating the program with exit code of `Problem` if it panics with one
+///
+/// If the body panics because of `or_failed_to` (panic hook of this crate needs to be installed, see `setup`) or
+/// `resume_with` with `Problem` that has exit code (set with `Problem::with_exit_code` or mapped from its tags with
+/// `exit_codes`), the program exits with this code after the panic was reported by the hook. Otherwise the panic continues
+/// and the program terminates with the usual exit status of 101.
+///
+/// Fatal cleanup functions (see `on_fatal`) are run before the program terminates. Panics that are caught within the body (e.g.
+/// with `std::panic::catch_unwind` or `spawn_compute`) do not terminate the program nor run the cleanup functions.
+///
+/// ```rust,no_run
+/// use problem::prelude::*;
+///
+/// fn main() {
+///     problem::setup();
+///     problem::run_main(|| {
+///         let config = std::fs::read_to_string("app.toml")
+///             .map_err(|error| Problem::from_error(error).with_exit_code(78))
+///             .or_failed_to("read configuration");
+///         println!("{}", config);
+///     })
+/// }
+/// ```
+pub fn run_main<O>(body: impl FnOnce() -> O) -> O {
+    match panic::catch_unwind(panic::AssertUnwindSafe(body)) {
+        Ok(output) => output,
+        Err(payload) => {
+            run_fatal_cleanup();
+            if let Some(code) = panic_exit_code(payload.as_ref()) {
+                std::process::exit(code)
+            }
+            panic::resume_unwind(payload)
+        }
+    }
+}
+
+/// Set panic hook so that formats error message to `stderr` with more `Problem` friendly way
+///
+/// Exit code of `Problem` the panic was raised with by `or_failed_to` is recorded so that `run_main` can terminate the program
+/// with it.
+pub fn format_panic_to_stderr() {
+    panic::set_hook(Box::new(|panic_info| {
+        record_panic_exit_code(panic_info.payload());
+        let backtrace = format_panic_backtrace();
+        eprintln!("Fatal error: {}", format_panic(panic_info, backtrace));
+    }));
+}
+
+/// Set panic hook so that when program panics it will log error massage with `error!` macro
+#[cfg(feature = "log")]
+pub fn format_panic_to_error_log() {
+    panic::set_hook(Box::new(|panic_info| {
+        record_panic_exit_code(panic_info.payload());
+        let backtrace = format_panic_backtrace();
+        error!("{}", format_panic(panic_info, backtrace));
+    }));
+}
+
+/// Default mapping of `Problem` tags to program exit codes installed by `setup` (see `exit_codes`); codes follow `sysexits.h`
+pub const DEFAULT_EXIT_CODES: &[(&str, i32)] = &[
+    ("usage", 64),
+    ("data", 65),
+    ("input", 66),
+    ("unavailable", 69),
+    ("internal", 70),
+    ("io", 74),
+    ("temporary", 75),
+    ("permission", 77),
+    ("config", 78),
+];
+
+/// Set up program for reporting `Problem`s
+///
+/// This will install panic hook with `format_panic_to_error_log` if `log` feature is enabled and logger was already
+/// initialized or with `format_panic_to_stderr` otherwise (`format_panic_to_stderr_colored` if `color` feature is enabled, so
+/// `NO_COLOR`, `CLICOLOR_FORCE` and `CLICOLOR` environment variables are respected).
+///
+/// Verbosity level (see `set_verbosity`) is set from `PROBLEM_VERBOSITY` environment variable if present and
+/// `RUST_BACKTRACE` environment variable is re-read (see `refresh_backtrace_policy`) if `backtrace` feature is enabled.
+///
+/// Tags listed in `DEFAULT_EXIT_CODES` that were not mapped yet are mapped to their exit codes (see `exit_codes`).
+///
+/// It is recommended to call this function at the beginning of `main` after logger was initialized.
+///
+/// ```rust
+/// problem::exit_codes().map("config", 1);
+/// problem::setup();
+///
+/// assert_eq!(problem::exit_codes().get("usage"), Some(64));
+/// assert_eq!(problem::exit_codes().get("config"), Some(1));
+/// ```
+pub fn setup() {
+    if let Some(level) = std::env::var("PROBLEM_VERBOSITY")
+        .ok()
+        .and_then(|level| level.parse().ok())
+    {
+        set_verbosity(level);
+    }
+
+    #[cfg(feature = "backtrace")]
+    refresh_backtrace_policy();
+
+    let codes = exit_codes();
+    for &(tag, code) in DEFAULT_EXIT_CODES {
+        if codes.get(tag).is_none() {
+            codes.map(tag, code);
+        }
+    }
+
+    #[cfg(feature = "log")]
+    {
+        if log::max_level() != log::LevelFilter::Off {
+            format_panic_to_error_log();
+            return;
+        }
+    }
+    #[cfg(feature = "color")]
+    color::format_panic_to_stderr_colored();
+    #[cfg(not(feature = "color"))]
+    format_panic_to_stderr();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::prelude::*;
+    use super::{
+        context_stack, count_problems_created, disable_backtraces, error_chain, error_id,
+        format_hex_dump, format_panic_to_stderr, format_stats, in_context_of, on_fatal,
+        operation_start, panic_exit_code, record_panic_exit_code, run_fatal_cleanup, run_main,
+        run_timed, set_context_stack, set_current_trace_id, start_operation, with_elapsed,
+        ContextOrder, DisplayOptions, ErrorMessage, IntoProblem, MultiSource, ProblemCounter,
+        ProblemError, ReportStyle, SectionVisibility, FAILED_TO_EXIT_CODE, MAX_ERROR_CHAIN_DEPTH,
+        OPERATION_START,
+    };
+    use std::any::Any;
+    use std::cell::Cell;
+    use std::error::Error;
+    use std::fmt::{self, Display};
+    use std::io;
+
+    #[derive(Debug)]
+    struct Foo;
+
+    impl Display for Foo {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "Foo error")
+        }
+    }
+
+    impl Error for Foo {}
+    impl IntoProblem for Foo {}
+
+    #[derive(Debug)]
+    struct Bar(Foo);
+
+    impl Display for Bar {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "Bar error")
+        }
+    }
+
+    impl Error for Bar {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    impl IntoProblem for Bar {}
+
+    #[derive(Debug)]
+    struct Baz(Bar);
+
+    impl Display for Baz {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "Baz error")
+        }
+    }
+
+    impl Error for Baz {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    impl IntoProblem for Baz {}
+
+    #[test]
+    #[should_panic(expected = "Failed to test due to: foo: 1")]
+    fn test_problem_macro() {
+        let err: Result<(), Problem> = problem!("foo: {}", 1);
+        err.or_failed_to("test");
+    }
+
+    #[test]
+    fn test_convertion() {
+        let _: Problem = io::Error::new(io::ErrorKind::InvalidInput, "boom!").into();
+        let _: Problem = "boom!".into(); // via impl<'a> From<&'a str> for Box<dyn Error>
+
+        fn erased() -> Result<(), Problem> {
+            Err(Box::<dyn Error + Send + Sync>::from("boom!"))?
+        }
+        disable_backtraces();
+        assert_eq!(erased().unwrap_err().to_string(), "boom!");
+    }
+
+    #[test]
+    fn test_extension_traits_any_error() {
+        // no `IntoProblem` impl
+        #[derive(Debug)]
+        struct Foreign;
+
+        impl fmt::Display for Foreign {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "foreign")
+            }
+        }
+
+        impl Error for Foreign {}
+
+        disable_backtraces();
+        assert_eq!(
+            Err::<(), _>(Foreign)
+                .problem_while("calling library")
+                .unwrap_err()
+                .to_string(),
+            "while calling library got error caused by: foreign"
+        );
+        assert_eq!(
+            Err::<(), _>(Foreign).map_problem().unwrap_err().to_string(),
+            "foreign"
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to complete processing task due to: while processing object, while processing input data, while parsing input got error caused by: boom!"
+    )]
+    fn test_integration() {
+        Err(io::Error::new(io::ErrorKind::InvalidInput, "boom!"))
+            .problem_while("parsing input")
+            .problem_while("processing input data")
+            .problem_while("processing object")
+            .or_failed_to("complete processing task")
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to complete processing task due to: while processing object, while processing input data, while parsing input got error caused by: boom!"
+    )]
+    fn test_integration_message() {
+        Err("boom!")
+            .problem_while("parsing input")
+            .problem_while("processing input data")
+            .problem_while("processing object")
+            .or_failed_to("complete processing task")
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to complete processing task due to: while processing object, while processing input data, while parsing input got error caused by: Baz error; caused by: Bar error; caused by: Foo error"
+    )]
+    fn test_integration_cause_chain() {
+        Err(Baz(Bar(Foo)))
+            .problem_while("parsing input")
+            .problem_while("processing input data")
+            .problem_while("processing object")
+            .or_failed_to("complete processing task")
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to complete processing task due to: while doing stuff got error caused by: boom!"
+    )]
+    fn test_in_context_of() {
+        in_context_of("doing stuff", || {
+            Err(io::Error::new(io::ErrorKind::InvalidInput, "boom!"))?
+        })
+        .or_failed_to("complete processing task")
+    }
+
+    #[test]
+    fn test_in_context_of_owned_message() {
+        disable_backtraces();
+        set_context_stack(true);
+        let item = 3;
+        let problem = in_context_of(format!("processing item {}", item), || {
+            assert_eq!(context_stack(), ["processing item 3"]);
+            Err::<(), _>(Problem::from_error("boom!"))
+        })
+        .unwrap_err();
+        assert_eq!(
+            problem.to_string(),
+            "while processing item 3 got error caused by: boom!"
+        );
+        assert!(context_stack().is_empty());
+    }
+
+    #[test]
+    fn test_problem_while_with_err() {
+        disable_backtraces();
+        let ok: Result<u8, Problem> = "42"
+            .parse::<u8>()
+            .problem_while_with_err(|_| -> String { panic!("called on Ok") });
+        assert_eq!(ok.unwrap(), 42);
+
+        let problem = Err::<(), _>(io::Error::new(io::ErrorKind::InvalidData, "boom!"))
+            .problem_while_with_err(|err| format!("reading ({:?})", err.kind()))
+            .unwrap_err();
+        assert_eq!(
+            problem.to_string(),
+            "while reading (InvalidData) got error caused by: boom!"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to collect item 2 due to: boom!")]
+    fn test_iter_or_failed_to_with() {
+        use std::cell::Cell;
+        let calls = Cell::new(0);
+
+        let ok: Vec<u32> = vec![Ok::<_, Problem>(1u32), Ok(2u32)]
+            .into_iter()
+            .or_failed_to_with(|| {
+                calls.set(calls.get() + 1);
+                "collect items"
+            })
+            .collect();
+        assert_eq!(ok, vec![1, 2]);
+        assert_eq!(calls.get(), 0);
+
+        let iter = vec![Ok::<_, Problem>(1u32), Ok(2u32)]
+            .into_iter()
+            .or_failed_to_with(|| "collect items");
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.rev().collect::<Vec<_>>(), [2, 1]);
+
+        let _: Vec<u32> = vec![Ok(1u32), Err("boom!")]
+            .into_iter()
+            .or_failed_to_with(|| format!("collect item {}", 2))
+            .collect();
+    }
+
+    #[test]
+    fn test_iter_or_failed_to_with_exit_code() {
+        let result = std::panic::catch_unwind(|| {
+            vec![
+                Ok(1u32),
+                Err(Problem::from_error("boom!").with_exit_code(5)),
+            ]
+            .into_iter()
+            .or_failed_to_with(|| "collect items")
+            .count()
+        });
+        assert!(result.is_err());
+        // panic hook installed by other tests may have already recorded the exit code
+        let code = FAILED_TO_EXIT_CODE
+            .with(Cell::take)
+            .or_else(|| panic_exit_code(&()));
+        assert_eq!(code, Some(5));
+    }
+
+    #[test]
+    fn test_option_result_or_failed_to() {
+        let value = Some("42".parse::<u8>()).flatten_or_failed_to("parse value");
+        assert_eq!(value, 42);
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to parse value due to: invalid digit found in string")]
+    fn test_option_result_or_failed_to_err() {
+        Some("x".parse::<u8>()).flatten_or_failed_to("parse value");
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to parse value")]
+    fn test_option_result_or_failed_to_none() {
+        None::<Result<u8, Problem>>.flatten_or_failed_to("parse value");
+    }
+
+    #[test]
+    fn test_iter_or_failed_to_traits() {
+        let results = vec![Ok::<_, &str>(1u32), Ok(2u32), Ok(3u32)];
+
+        let iter = results.clone().into_iter().or_failed_to("collect numbers");
+        assert_eq!(iter.size_hint(), (3, Some(3)));
+        assert_eq!(iter.len(), 3);
+
+        let rev: Vec<u32> = results
+            .into_iter()
+            .or_failed_to("collect numbers")
+            .rev()
+            .collect();
+        assert_eq!(rev, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn test_take_until_problem() {
+        disable_backtraces();
+        let mut iter = vec![Ok(1), Ok(2), Err(Foo), Ok(3)]
+            .into_iter()
+            .take_until_problem();
+        assert_eq!(iter.by_ref().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.first_problem().unwrap().to_string(), "Foo error");
+
+        let mut iter = vec![Ok::<_, Foo>(1), Ok(2)]
+            .into_iter()
+            .take_until_problem();
+        assert_eq!(iter.by_ref().collect::<Vec<_>>(), vec![1, 2]);
+        assert!(iter.into_first_problem().is_none());
+    }
+
+    #[test]
+    fn test_try_for_each_problem() {
+        disable_backtraces();
+        let mut seen = Vec::new();
+        let p = try_for_each_problem("processing record", vec!["a", "b", "c"], |item| {
+            seen.push(*item);
+            if *item == "b" {
+                Err(Foo)
+            } else {
+                Ok(())
+            }
+        })
+        .unwrap_err();
+
+        assert_eq!(seen, vec!["a", "b"]);
+        assert_eq!(
+            p.to_string(),
+            "while processing record #1 (\"b\") got error caused by: Foo error"
+        );
+
+        assert!(try_for_each_problem("processing record", 0..3, |_| Ok::<_, Foo>(())).is_ok());
+    }
+
+    #[test]
+    fn test_recover() {
+        disable_backtraces();
+        let p = Err::<(), _>(Foo).problem_while("reading").unwrap_err();
+        assert_eq!(
+            p.recover_if::<io::Error>().unwrap_err().to_string(),
+            "while reading got error caused by: Foo error"
+        );
+
+        let p = Err::<(), _>(Foo).problem_while("reading").unwrap_err();
+        assert_eq!(p.recover_if::<Foo>().unwrap().to_string(), "Foo error");
+
+        let p = Problem::from_error(io::Error::other(Bar(Foo))).problem_while("reading");
+        assert_eq!(
+            p.recover_if::<Foo>().unwrap_err().to_string(),
+            "while reading got error caused by: Bar error; caused by: Foo error"
+        );
+        let p = Problem::from_error(io::Error::other(Bar(Foo))).problem_while("reading");
+        assert_eq!(p.recover_if::<Bar>().unwrap().to_string(), "Bar error");
+
+        let not_found = io::Error::new(io::ErrorKind::NotFound, "no config");
+        assert_eq!(
+            Err(not_found)
+                .recover(|error: io::Error| error.kind())
+                .unwrap(),
+            io::ErrorKind::NotFound
+        );
+        assert_eq!(
+            Err::<Bar, _>(io::Error::other(Bar(Foo)))
+                .recover(|bar: Bar| bar)
+                .unwrap()
+                .to_string(),
+            "Bar error"
+        );
+        assert_eq!(
+            Err::<&str, _>(Foo)
+                .recover(|_: io::Error| "default")
+                .unwrap_err()
+                .to_string(),
+            "Foo error"
+        );
+    }
+
+    #[test]
+    fn test_io_kind() {
+        #[derive(Debug)]
+        struct Wrapper(io::Error);
+
+        impl Display for Wrapper {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "Wrapper error")
+            }
+        }
+
+        impl Error for Wrapper {
+            fn source(&self) -> Option<&(dyn Error + 'static)> {
+                Some(&self.0)
+            }
+        }
+
+        let p = Problem::from_error(Wrapper(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no such file",
+        )))
+        .problem_while("opening config");
+        assert_eq!(p.io_kind(), Some(io::ErrorKind::NotFound));
+        assert!(p.is_not_found());
+        assert!(!p.is_permission_denied());
+
+        let p = Problem::from_error(io::Error::new(io::ErrorKind::PermissionDenied, "denied"));
+        assert!(p.is_permission_denied());
+
+        assert_eq!(Problem::from_error(Foo).io_kind(), None);
+    }
+
+    #[test]
+    fn test_from_parts() {
+        let p = Problem::from_parts(
+            Baz(Bar(Foo)),
+            vec!["parsing input".to_owned(), "processing object".to_owned()],
+            Some("0: main".to_owned()),
+        );
+
+        assert_eq!(
+            p.contexts().collect::<Vec<_>>(),
+            vec!["parsing input", "processing object"]
+        );
+        assert_eq!(p.backtrace(), Some("0: main"));
+
+        let mut out = String::new();
+        p.write(&mut out, &DisplayOptions::default(), 2, ReportStyle::Short)
+            .unwrap();
+        assert_eq!(
+            out,
+            "while processing object, while parsing input got error caused by: Baz error; caused by: Bar error; caused by: Foo error\n--- Cause\n0: main"
+        );
+    }
+
+    #[test]
+    fn test_sections() {
+        use std::rc::Rc;
+        let calls = Rc::new(Cell::new(0));
+
+        let p = Problem::from_error("request failed")
+            .with_section("Request", "GET /users")
+            .with_section_lazy("Response", {
+                let calls = calls.clone();
+                move || {
+                    calls.set(calls.get() + 1);
+                    "500 Internal Server Error"
+                }
+            });
+        assert_eq!(calls.get(), 0);
+
+        let mut out = String::new();
+        p.write(&mut out, &DisplayOptions::default(), 1, ReportStyle::Short)
+            .unwrap();
+        assert_eq!(out, "request failed");
+        assert_eq!(calls.get(), 0);
+
+        for _ in 0..2 {
+            let mut out = String::new();
+            p.write(&mut out, &DisplayOptions::default(), 1, ReportStyle::Long)
+                .unwrap();
+            assert_eq!(
+                out,
+                "request failed\n--- Request\nGET /users\n--- Response\n500 Internal Server Error"
+            );
+        }
+        assert_eq!(calls.get(), 1);
+        assert_eq!(
+            p.sections().collect::<Vec<_>>(),
+            [
+                ("Request", "GET /users"),
+                ("Response", "500 Internal Server Error")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_section_visibility() {
+        disable_backtraces();
+        let p = Problem::from_error("bad response")
+            .with_section("Status", "500")
+            .with_section("Headers", "Server: x")
+            .with_section("Body", "<html>")
+            .with_section_visibility("Headers", SectionVisibility::Verbose)
+            .with_section_visibility("Body", SectionVisibility::NotInLogs);
+
+        let render = |verbosity, style| {
+            let mut out = String::new();
+            p.write(&mut out, &DisplayOptions::default(), verbosity, style)
+                .unwrap();
+            out
+        };
+        assert_eq!(
+            render(1, ReportStyle::Long),
+            "bad response\n--- Status\n500\n--- Body\n<html>"
+        );
+        assert_eq!(
+            render(2, ReportStyle::Long),
+            "bad response\n--- Status\n500\n--- Headers\nServer: x\n--- Body\n<html>"
+        );
+        assert_eq!(
+            render(2, ReportStyle::Log),
+            "bad response\n--- Status\n500\n--- Headers\nServer: x"
+        );
+        assert_eq!(render(2, ReportStyle::Short), "bad response");
+    }
+
+    #[test]
+    fn test_display_short_full() {
+        let p = Problem::from_parts(
+            Baz(Bar(Foo)),
+            vec!["parsing input".to_owned()],
+            Some("0: main".to_owned()),
+        )
+        .with_section("Input", "foo");
+
+        assert_eq!(
+            p.display_short().to_string(),
+            "while parsing input got error caused by: Baz error"
+        );
+        assert_eq!(
+            p.display_full().to_string(),
+            "while parsing input got error caused by: Baz error; caused by: Bar error; caused by: Foo error\n--- Input\nfoo\n--- Cause\n0: main"
+        );
+    }
+
+    #[test]
+    fn test_brief_report() {
+        let p = Problem::from_error(Baz(Bar(Foo)))
+            .problem_while("parsing input")
+            .with_section("Input", "foo");
+
+        assert_eq!(
+            p.brief(),
+            "while parsing input got error caused by: Baz error; caused by: Bar error; caused by: Foo error"
+        );
+        assert!(p
+            .report()
+            .starts_with(&format!("{}\n--- Input\nfoo", p.brief())));
+    }
+
+    #[test]
+    fn test_problem_counter() {
+        let counter = ProblemCounter::new();
+        let worker = counter.clone();
+        std::thread::spawn(move || {
+            assert_eq!(Err::<(), _>(Foo).ok_or_count(&worker), None);
+            worker.count_error();
+            worker.count_warning();
+            worker.count_warning();
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(Ok::<_, Foo>(1).ok_or_count(&counter), Some(1));
+        assert_eq!(counter.errors(), 1);
+        assert_eq!(counter.warnings(), 2);
+        assert_eq!(counter.suppressed(), 1);
+        assert_eq!(counter.total(), 4);
+    }
+
+    #[test]
+    fn test_format_stats() {
+        let counter = ProblemCounter::new();
+        counter.count_error();
+        counter.count_warning();
+        counter.count_warning();
+        counter.count_suppressed();
+        assert_eq!(
+            format_stats(7, &counter),
+            "Problem statistics: 7 created, 3 logged (1 errors, 2 warnings), 1 suppressed"
+        );
+    }
+
+    #[test]
+    fn test_context_timestamps() {
+        use std::time::{Duration, Instant};
+
+        let mut p = Problem::from_error("connection reset").problem_while("connecting");
+        p.details_mut().started = Some(Instant::now() - Duration::from_secs(2));
+        let p = p.problem_while("downloading").problem_while("downloading");
+
+        let mut out = String::new();
+        p.write(&mut out, &DisplayOptions::default(), 1, ReportStyle::Short)
+            .unwrap();
+        assert_eq!(
+            out,
+            "while downloading (x2) (after 2.0s), while connecting got error caused by: connection reset"
+        );
+    }
+
+    #[test]
+    fn test_operation_start() {
+        {
+            let _operation = start_operation(false);
+            assert!(OPERATION_START.with(Cell::get).is_none());
+        }
+
+        let outer = start_operation(true);
+        let started = operation_start();
+        {
+            let _inner = start_operation(true);
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            assert_eq!(operation_start(), started);
+        }
+        assert_eq!(OPERATION_START.with(Cell::get), Some(started));
+        drop(outer);
+        assert!(OPERATION_START.with(Cell::get).is_none());
+    }
+
+    #[test]
+    fn test_context_elapsed() {
+        let (p, elapsed) = run_timed(true, || -> Result<(), Problem> {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            Err(Problem::from_error("boom!"))
+        })
+        .unwrap_err();
+        let elapsed = elapsed.unwrap();
+        assert!(elapsed >= std::time::Duration::from_millis(10));
+
+        let p = p.problem_while(with_elapsed("downloading", Some(elapsed)));
+        assert_eq!(
+            p.contexts().next().unwrap(),
+            format!("downloading (failed after {:.1?})", elapsed)
+        );
+
+        let (_, elapsed) =
+            run_timed(false, || Err::<(), _>(Problem::from_error("boom!"))).unwrap_err();
+        assert_eq!(with_elapsed("downloading", elapsed), "downloading");
+    }
+
+    #[test]
+    fn test_trace_id() {
+        let p = Problem::from_error("boom!").with_trace_id("abc123");
+        assert_eq!(p.trace_id(), Some("abc123"));
+        assert_eq!(p.to_logfmt(), "msg=\"boom!\" trace_id=\"abc123\"");
+
+        let mut out = String::new();
+        p.write(&mut out, &DisplayOptions::default(), 1, ReportStyle::Long)
+            .unwrap();
+        assert_eq!(out, "boom!\ntrace id: abc123");
+
+        let mut out = String::new();
+        p.write(&mut out, &DisplayOptions::default(), 1, ReportStyle::Short)
+            .unwrap();
+        assert_eq!(out, "boom!");
+
+        // thread local state is not shared with other tests
+        std::thread::spawn(|| {
+            assert_eq!(set_current_trace_id(Some("def456".to_owned())), None);
+            assert_eq!(Problem::from_error("boom!").trace_id(), Some("def456"));
+            assert_eq!(set_current_trace_id(None), Some("def456".to_owned()));
+            assert_eq!(Problem::from_error("boom!").trace_id(), None);
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn test_error_id() {
+        assert_eq!(error_id(Some("abc123")), Some("abc123".to_owned()));
+        #[cfg(feature = "uuid")]
+        assert_eq!(error_id(None).unwrap().len(), 36);
+        #[cfg(not(feature = "uuid"))]
+        assert_eq!(error_id(None), None);
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let p = Problem::from_error(ErrorMessage::new("bad\u{7}name").with_source(
+            ErrorMessage::new("no such file: \u{1b}]0;pwned\u{7}\u{1b}[31mfoo\tbar\r\nbaz"),
+        ))
+        .problem_while("opening \u{1b}[2J")
+        .with_section("Stderr", "\u{1b}[1mfailed\u{1b}[0m\n");
+
+        let mut out = String::new();
+        p.write(&mut out, &DisplayOptions::default(), 1, ReportStyle::Long)
+            .unwrap();
+        assert_eq!(
+            out,
+            "while opening \\u{1b}[2J got error caused by: bad\\u{7}name; caused by: no such file: \\u{1b}]0;pwned\\u{7}\\u{1b}[31mfoo\tbar\n  baz\n--- Stderr\n\\u{1b}[1mfailed\\u{1b}[0m\n"
+        );
+
+        let options = DisplayOptions {
+            sanitize: false,
+            ..Default::default()
+        };
+        let mut out = String::new();
+        p.write(&mut out, &options, 1, ReportStyle::Short).unwrap();
+        assert_eq!(
+            out,
+            "while opening \u{1b}[2J got error caused by: bad\u{7}name; caused by: no such file: \u{1b}]0;pwned\u{7}\u{1b}[31mfoo\tbar\n  baz"
+        );
+
+        let p = Problem::from_error("bad")
+            .with_url("https://example.com/\u{1b}[2J")
+            .with_trace_id("\u{1b}]0;pwned\u{7}");
+        let mut out = String::new();
+        p.write(&mut out, &DisplayOptions::default(), 1, ReportStyle::Long)
+            .unwrap();
+        assert_eq!(
+            out,
+            "bad\nsee: https://example.com/\\u{1b}[2J\ntrace id: \\u{1b}]0;pwned\\u{7}"
+        );
+    }
+
+    #[test]
+    fn test_count_problems_created() {
+        let (_, created) = count_problems_created(|| {
+            let _ = "1".parse::<u32>().map_problem();
+        });
+        assert_eq!(created, 0);
+
+        let (_, created) = count_problems_created(|| {
+            let _ = "x".parse::<u32>().map_problem().problem_while("parsing");
+            let _ = Problem::from_parts("boom!", Vec::new(), None);
+        });
+        assert_eq!(created, 2);
+    }
+
+    #[test]
+    fn test_hex_dump() {
+        assert_eq!(format_hex_dump(b"", 64), "");
+        assert_eq!(
+            format_hex_dump(b"HTTP/1.1 200 OK\r\n\x00\xff", 64),
+            "00000000  48 54 54 50 2f 31 2e 31  20 32 30 30 20 4f 4b 0d  |HTTP/1.1 200 OK.|\n\
+             00000010  0a 00 ff                                          |...|"
+        );
+        assert_eq!(
+            format_hex_dump(&[0x41; 40], 32),
+            "00000000  41 41 41 41 41 41 41 41  41 41 41 41 41 41 41 41  |AAAAAAAAAAAAAAAA|\n\
+             00000010  41 41 41 41 41 41 41 41  41 41 41 41 41 41 41 41  |AAAAAAAAAAAAAAAA|\n\
+             … (8 more bytes, 40 total)"
+        );
+    }
+
+    #[test]
+    fn test_truncation() {
+        let p = Problem::from_error(ErrorMessage::new("request failed").with_source(
+            ErrorMessage::new(format!("bad body: {}", "zażółć".repeat(10 * 1024))),
+        ))
+        .problem_while("x".repeat(2000));
+
+        let options = DisplayOptions {
+            max_message_length: Some(14),
+            ..Default::default()
+        };
+        let mut out = String::new();
+        p.write(&mut out, &options, 1, ReportStyle::Short).unwrap();
+        assert_eq!(
+            out,
+            "while xxxxxxxxxxxxxx… (truncated, 1KiB total) got error caused by: request failed; caused by: bad body: zaż… (truncated, 100KiB total)"
+        );
+
+        let options = DisplayOptions {
+            max_message_length: Some(100),
+            ..Default::default()
+        };
+        let mut out = String::new();
+        Problem::from_error("x".repeat(200))
+            .write(&mut out, &options, 0, ReportStyle::Short)
+            .unwrap();
+        assert_eq!(out, format!("{}… (truncated, 200B total)", "x".repeat(100)));
+    }
+
+    #[test]
+    fn test_multi_line_messages() {
+        let p = Problem::from_parts(
+            ErrorMessage::new("query failed").with_source(ErrorMessage::new(
+                "ERROR: syntax error at or near \"FORM\"\r\nLINE 1: SELECT * FORM users\n\n",
+            )),
+            vec!["loading users".to_owned()],
+            None,
+        );
+
+        let mut out = String::new();
+        p.write(&mut out, &DisplayOptions::default(), 1, ReportStyle::Short)
+            .unwrap();
+        assert_eq!(
+            out,
+            "while loading users got error caused by: query failed; caused by: ERROR: syntax error at or near \"FORM\"\n  LINE 1: SELECT * FORM users"
+        );
+
+        let p = Problem::from_error("first line\n\nthird line\n");
+        let mut out = String::new();
+        p.write(&mut out, &DisplayOptions::default(), 0, ReportStyle::Short)
+            .unwrap();
+        assert_eq!(out, "first line\n  \n  third line");
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to foo due to: boom!")]
+    fn test_result() {
+        Err(io::Error::new(io::ErrorKind::InvalidInput, "boom!")).or_failed_to("foo")
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to quix due to: Baz error; caused by: Bar error; caused by: Foo error"
+    )]
+    fn test_result_cause_chain() {
+        Err(Baz(Bar(Foo))).or_failed_to("quix")
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Failed to quix due to: Baz error; caused by: Bar error; caused by: Foo error"
+    )]
+    fn test_result_cause_chain_message() {
+        let error = Baz(Bar(Foo));
+        Err(Problem::from_error_message(&error)).or_failed_to("quix")
+    }
 
-    #[derive(Debug)]
-    struct Foo;
+    #[test]
+    #[allow(deprecated)]
+    fn test_error_chain_cause_fallback() {
+        disable_backtraces();
+        #[derive(Debug)]
+        struct Legacy(Bar);
+
+        impl Display for Legacy {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "Legacy error")
+            }
+        }
 
-    impl Display for Foo {
-        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-            write!(f, "Foo error")
+        impl Error for Legacy {
+            fn cause(&self) -> Option<&dyn Error> {
+                Some(&self.0)
+            }
         }
-    }
 
-    impl Error for Foo {}
+        let p = Problem::from_error(Legacy(Bar(Foo)));
+        assert_eq!(
+            p.to_string(),
+            "Legacy error; caused by: Bar error; caused by: Foo error"
+        );
+        assert_eq!(
+            Problem::from_error_message(&Legacy(Bar(Foo))).to_string(),
+            "Legacy error; caused by: Bar error; caused by: Foo error"
+        );
 
-    #[derive(Debug)]
-    struct Bar(Foo);
+        let p = Problem::from_error(Baz(Bar(Foo)));
+        assert!(p.find_cause::<Foo>().is_some());
+        assert!(p.find_cause::<io::Error>().is_none());
+    }
 
-    impl Display for Bar {
-        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-            write!(f, "Bar error")
+    #[test]
+    fn test_error_chain_cycle() {
+        disable_backtraces();
+        #[derive(Debug)]
+        struct Cyclic;
+
+        impl Display for Cyclic {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "Cyclic error")
+            }
         }
-    }
 
-    impl Error for Bar {
-        fn source(&self) -> Option<&(dyn Error + 'static)> {
-            Some(&self.0)
+        impl Error for Cyclic {
+            fn source(&self) -> Option<&(dyn Error + 'static)> {
+                Some(self)
+            }
         }
-    }
 
-    #[derive(Debug)]
-    struct Baz(Bar);
+        assert_eq!(Problem::from_error(Cyclic).to_string(), "Cyclic error");
+        assert_eq!(
+            Problem::from_error_message(&Cyclic).to_logfmt(),
+            r#"msg="Cyclic error""#
+        );
+        assert_eq!(Problem::from_error(Cyclic).io_kind(), None);
 
-    impl Display for Baz {
-        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-            write!(f, "Baz error")
-        }
+        let deep = (0..100).fold(ErrorMessage::new("root"), |source, no| {
+            ErrorMessage::new(no.to_string()).with_source(source)
+        });
+        assert_eq!(error_chain(&deep).count(), MAX_ERROR_CHAIN_DEPTH);
     }
 
-    impl Error for Baz {
-        fn source(&self) -> Option<&(dyn Error + 'static)> {
-            Some(&self.0)
-        }
+    #[test]
+    fn test_dyn_error() {
+        disable_backtraces();
+        let error: Box<dyn Error> = Box::new(Baz(Bar(Foo)));
+        let p = Problem::from_dyn_error(error.as_ref());
+        assert_eq!(
+            p.to_string(),
+            "Baz error; caused by: Bar error; caused by: Foo error"
+        );
+        assert_eq!(p.io_kind(), None);
+
+        let error: Box<dyn Error> =
+            Box::new(ErrorMessage::new("loading config").with_source(ErrorMessage::new("boom!")));
+        assert_eq!(
+            Problem::from_dyn_error_static(error.as_ref()).to_string(),
+            "loading config; caused by: boom!"
+        );
+
+        let error = io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            ErrorMessage::new("access denied").with_source(ErrorMessage::new("read-only")),
+        );
+        let error: &(dyn Error + 'static) = &error;
+        let p = Problem::from_dyn_error_static(error);
+        assert!(p.is_permission_denied());
+        assert_eq!(p.status(), 403);
+        assert_eq!(p.to_string(), "access denied; caused by: read-only");
     }
 
     #[test]
-    #[should_panic(expected = "Failed to test due to: foo: 1")]
-    fn test_problem_macro() {
-        let err: Result<(), Problem> = problem!("foo: {}", 1);
-        err.or_failed_to("test");
+    fn test_url() {
+        let p = Problem::from_error("boom!").with_url("https://docs.example.com/errors/E42");
+        assert_eq!(p.url(), Some("https://docs.example.com/errors/E42"));
+        assert!(format!("{:#}", p).starts_with("boom!\nsee: https://docs.example.com/errors/E42"));
+        assert!(!p.brief().contains("see:"));
     }
 
     #[test]
-    fn test_convertion() {
-        let _: Problem = io::Error::new(io::ErrorKind::InvalidInput, "boom!").into();
-        let _: Problem = "boom!".into(); // via impl<'a> From<&'a str> for Box<dyn Error>
+    fn test_status() {
+        assert_eq!(Problem::from_error("boom!").status(), 500);
+        assert_eq!(Problem::from_error("boom!").with_status(418).status(), 418);
+
+        let not_found = io::Error::new(io::ErrorKind::NotFound, "no file");
+        assert_eq!(Problem::from_error(not_found).status(), 404);
+
+        #[derive(Debug)]
+        struct Wrapper(io::Error);
+
+        impl Display for Wrapper {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "wrapped")
+            }
+        }
+
+        impl Error for Wrapper {
+            fn source(&self) -> Option<&(dyn Error + 'static)> {
+                Some(&self.0)
+            }
+        }
+
+        let denied = Wrapper(io::Error::new(io::ErrorKind::PermissionDenied, "no access"));
+        assert_eq!(Problem::from_error(denied).status(), 403);
     }
 
     #[test]
-    #[should_panic(
-        expected = "Failed to complete processing task due to: while processing object, while processing input data, while parsing input got error caused by: boom!"
-    )]
-    fn test_integration() {
-        Err(io::Error::new(io::ErrorKind::InvalidInput, "boom!"))
+    fn test_verbosity() {
+        let p = Err::<(), _>(Baz(Bar(Foo)))
             .problem_while("parsing input")
-            .problem_while("processing input data")
-            .problem_while("processing object")
-            .or_failed_to("complete processing task")
+            .unwrap_err();
+        let display = |verbosity| {
+            let mut out = String::new();
+            p.write(
+                &mut out,
+                &DisplayOptions::default(),
+                verbosity,
+                ReportStyle::Short,
+            )
+            .unwrap();
+            out
+        };
+
+        assert_eq!(
+            display(0),
+            "while parsing input got error caused by: Baz error"
+        );
+        assert_eq!(
+            display(1),
+            "while parsing input got error caused by: Baz error; caused by: Bar error; caused by: Foo error"
+        );
+        assert!(display(2).starts_with(&display(1)));
     }
 
     #[test]
-    #[should_panic(
-        expected = "Failed to complete processing task due to: while processing object, while processing input data, while parsing input got error caused by: boom!"
-    )]
-    fn test_integration_message() {
-        Err("boom!")
-            .problem_while("parsing input")
-            .problem_while("processing input data")
-            .problem_while("processing object")
-            .or_failed_to("complete processing task")
+    fn test_env() {
+        std::env::set_var("PROBLEM_TEST_ENV", "foo");
+        let p =
+            Problem::from_error("boom!").with_env(&["PROBLEM_TEST_ENV", "PROBLEM_TEST_ENV_UNSET"]);
+
+        let mut out = String::new();
+        p.write(&mut out, &DisplayOptions::default(), 1, ReportStyle::Long)
+            .unwrap();
+        assert_eq!(
+            out,
+            "boom!\n--- Environment\nPROBLEM_TEST_ENV=foo\nPROBLEM_TEST_ENV_UNSET is not set"
+        );
+
+        let mut out = String::new();
+        p.write(&mut out, &DisplayOptions::default(), 1, ReportStyle::Short)
+            .unwrap();
+        assert_eq!(out, "boom!");
     }
 
     #[test]
-    #[should_panic(
-        expected = "Failed to complete processing task due to: while processing object, while processing input data, while parsing input got error caused by: Baz error; caused by: Bar error; caused by: Foo error"
-    )]
-    fn test_integration_cause_chain() {
-        Err(Baz(Bar(Foo)))
+    fn test_logfmt() {
+        let p = Err::<(), _>(Baz(Bar(Foo)))
             .problem_while("parsing input")
-            .problem_while("processing input data")
-            .problem_while("processing object")
-            .or_failed_to("complete processing task")
-    }
+            .problem_while("processing\nobject")
+            .unwrap_err();
+        assert_eq!(
+            p.to_logfmt(),
+            r#"msg="Baz error" context.0="processing\nobject" context.1="parsing input" cause.0="Bar error" cause.1="Foo error""#
+        );
 
-    #[test]
-    #[should_panic(
-        expected = "Failed to complete processing task due to: while doing stuff got error caused by: boom!"
-    )]
-    fn test_in_context_of() {
-        in_context_of("doing stuff", || {
-            Err(io::Error::new(io::ErrorKind::InvalidInput, "boom!"))?
-        })
-        .or_failed_to("complete processing task")
+        let p = Problem::from_error_message(&Baz(Bar(Foo)));
+        assert_eq!(
+            p.to_logfmt(),
+            r#"msg="Baz error" cause.0="Bar error" cause.1="Foo error""#
+        );
     }
 
     #[test]
-    #[should_panic(expected = "Failed to foo due to: boom!")]
-    fn test_result() {
-        Err(io::Error::new(io::ErrorKind::InvalidInput, "boom!")).or_failed_to("foo")
+    fn test_display_with() {
+        let p = Err::<(), _>(Baz(Bar(Foo)))
+            .problem_while("parsing input")
+            .problem_while("processing object")
+            .unwrap_err();
+        let options = DisplayOptions::default()
+            .with_context_separator(" > ")
+            .with_cause_separator(" <- ")
+            .with_order(ContextOrder::OutermostFirst)
+            .with_sanitize(true)
+            .with_max_message_length(None);
+
+        let mut out = String::new();
+        p.write(&mut out, &options, 1, ReportStyle::Short).unwrap();
+        assert_eq!(
+            out,
+            "while processing object > while parsing input got error caused by: Baz error <- Bar error <- Foo error"
+        );
     }
 
     #[test]
-    #[should_panic(
-        expected = "Failed to quix due to: Baz error; caused by: Bar error; caused by: Foo error"
-    )]
-    fn test_result_cause_chain() {
-        Err(Baz(Bar(Foo))).or_failed_to("quix")
+    fn test_repeated_context() {
+        let mut p = Problem::from_error("boom!").problem_while("reading link");
+        for _ in 0..12 {
+            p = p.problem_while("resolving symlink");
+        }
+        p = p.problem_while("opening file");
+
+        assert_eq!(p.contexts().len(), 14);
+        assert_eq!(
+            p.contexts().filter(|c| *c == "resolving symlink").count(),
+            12
+        );
+
+        let mut out = String::new();
+        p.write(&mut out, &DisplayOptions::default(), 1, ReportStyle::Short)
+            .unwrap();
+        assert_eq!(
+            out,
+            "while opening file, while resolving symlink (x12), while reading link got error caused by: boom!"
+        );
     }
 
     #[test]
-    #[should_panic(
-        expected = "Failed to quix due to: Baz error; caused by: Bar error; caused by: Foo error"
-    )]
-    fn test_result_cause_chain_message() {
-        let error = Baz(Bar(Foo));
-        Err(Problem::from_error_message(&error)).or_failed_to("quix")
+    fn test_max_context_depth() {
+        let mut p = Problem::from_error("boom!");
+        for no in 0..100 {
+            p = p.problem_while(format!("level {}", no));
+        }
+
+        assert_eq!(p.contexts().len(), 64);
+        assert_eq!(p.contexts().next(), Some("level 0"));
+        assert_eq!(p.contexts().nth(62), Some("level 62"));
+        assert_eq!(p.contexts().last(), Some("level 99"));
+
+        let mut out = String::new();
+        p.write(&mut out, &DisplayOptions::default(), 1, ReportStyle::Short)
+            .unwrap();
+        assert!(out
+            .starts_with("while level 99, … and 36 more contexts, while level 62, while level 61"));
+        assert!(out.ends_with("while level 0 got error caused by: boom!"));
+
+        let p = Problem::from_parts(
+            "boom!",
+            (0..100).map(|no| format!("level {}", no)).collect(),
+            None,
+        );
+        let mut from_parts = String::new();
+        p.write(
+            &mut from_parts,
+            &DisplayOptions::default(),
+            1,
+            ReportStyle::Short,
+        )
+        .unwrap();
+        assert_eq!(from_parts, out);
+        assert_eq!(ProblemError::from(p).into_problem().to_string(), out);
     }
 
     #[test]
@@ -1162,6 +5915,13 @@ mod tests {
         let _ok = results.into_iter().or_failed_to("foo").collect::<Vec<_>>();
     }
 
+    #[test]
+    #[should_panic(expected = "foo bar!")]
+    fn test_setup() {
+        super::setup();
+        panic!("foo bar!");
+    }
+
     #[test]
     #[should_panic]
     fn test_panic_format_stderr() {
@@ -1183,6 +5943,14 @@ mod tests {
 
     #[test]
     #[should_panic]
+    fn test_panic_format_stderr_resume_with() {
+        format_panic_to_stderr();
+        crate::resume_with(Problem::from_error("boom!").problem_while("processing object"));
+    }
+
+    #[test]
+    #[should_panic]
+    #[allow(clippy::unnecessary_literal_unwrap)]
     fn test_panic_format_stderr_unwrap() {
         format_panic_to_stderr();
         let result: Result<(), io::Error> =
@@ -1192,6 +5960,7 @@ mod tests {
 
     #[test]
     #[should_panic]
+    #[allow(clippy::unnecessary_literal_unwrap)]
     fn test_panic_format_stderr_expect() {
         format_panic_to_stderr();
         let result: Result<(), io::Error> =
@@ -1199,6 +5968,80 @@ mod tests {
         result.expect("foo");
     }
 
+    #[test]
+    fn test_context_stack() {
+        use super::context_stack;
+
+        set_context_stack(true);
+        assert!(context_stack().is_empty());
+        in_context_of("doing stuff", || {
+            in_context_of("doing more stuff", || {
+                assert_eq!(context_stack(), ["doing stuff", "doing more stuff"]);
+                Ok(())
+            })?;
+            assert_eq!(context_stack(), ["doing stuff"]);
+            Ok(())
+        })
+        .unwrap();
+        assert!(context_stack().is_empty());
+
+        let problem = in_context_of_with(
+            || format!("processing item {}", 7),
+            || -> Result<(), Problem> {
+                assert_eq!(context_stack(), ["processing item 7"]);
+                Err(Problem::from_error("boom!"))
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            problem.contexts().collect::<Vec<_>>(),
+            ["processing item 7"]
+        );
+        assert!(context_stack().is_empty());
+    }
+
+    #[test]
+    fn test_run_main() {
+        assert_eq!(run_main(|| 42), 42);
+        assert!(std::panic::catch_unwind(|| run_main(|| panic!("boom!"))).is_err());
+
+        FAILED_TO_EXIT_CODE.with(|code| code.set(Some(3)));
+        record_panic_exit_code(&"boom!");
+        assert_eq!(panic_exit_code(&"boom!"), Some(3));
+        assert_eq!(panic_exit_code(&"boom!"), None);
+
+        let payload = ProblemError::from(Problem::from_error("boom!").with_exit_code(4));
+        assert_eq!(panic_exit_code(&payload), Some(4));
+        record_panic_exit_code(&payload);
+        assert_eq!(panic_exit_code(&"boom!"), Some(4));
+    }
+
+    #[test]
+    fn test_fatal_cleanup() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let c = calls.clone();
+        on_fatal(move || {
+            c.fetch_add(1, Ordering::SeqCst);
+        });
+
+        run_fatal_cleanup();
+        run_fatal_cleanup();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_process_info() {
+        let mut out = String::new();
+        super::write_process_info(&mut out).unwrap();
+        assert!(out.starts_with("executable: "));
+        assert!(out.contains("\nworking directory: "));
+        #[cfg(unix)]
+        assert!(out.contains("\nlimit open files: "));
+    }
+
     #[test]
     #[cfg(feature = "backtrace")]
     fn test_problem_backtrace() {
@@ -1206,9 +6049,8 @@ mod tests {
             .problem_while("bar")
             .problem_while("baz");
 
-        if let Ok("1") = std::env::var("RUST_BACKTRACE").as_ref().map(String::as_str) {
-            assert!(p.backtrace().is_some());
-            println!("{}", p.backtrace().unwrap());
+        if let Ok("1") | Ok("full") = std::env::var("RUST_BACKTRACE").as_ref().map(String::as_str) {
+            assert!(p.backtrace().is_some_and(|backtrace| !backtrace.is_empty()));
         } else {
             assert!(p.backtrace().is_none());
         }
@@ -1230,6 +6072,69 @@ mod tests {
         error.ok_or_log_warn();
     }
 
+    #[test]
+    #[cfg(feature = "log")]
+    fn test_problem_or_default_log() {
+        loggerv::init_quiet().ok();
+        let error: Result<u32, _> = Err(Baz(Bar(Foo)));
+        assert_eq!(error.or_default_log_warn(), 0);
+        let error: Result<String, _> = Err(Foo);
+        assert_eq!(error.or_default_log_error(), "");
+        assert_eq!(Ok::<_, Foo>(42).or_default_log_warn(), 42);
+    }
+
+    #[test]
+    #[cfg(feature = "log")]
+    fn test_problem_logged() {
+        loggerv::init_quiet().ok();
+        let logged = Err::<(), _>(Baz(Bar(Foo)))
+            .problem_while("parsing input")
+            .map_err(|problem| problem.with_tag("input").with_exit_code(3))
+            .logged_warn();
+        assert!(logged.is_problem());
+
+        let p = logged.escalate("too many failures").unwrap_err();
+        assert_eq!(p.brief(), "while too many failures, while parsing input got error caused by: Baz error; caused by: Bar error; caused by: Foo error");
+        assert_eq!(
+            p.contexts().collect::<Vec<_>>(),
+            ["parsing input", "too many failures"]
+        );
+        assert!(p.has_tag("input"));
+        assert_eq!(p.exit_code(), Some(3));
+
+        let logged = Ok::<_, Foo>(1).logged_error();
+        assert!(!logged.is_problem());
+        assert_eq!(logged.escalate("too many failures").unwrap(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "log")]
+    fn test_fail_after_errors() {
+        loggerv::init_quiet().ok();
+        let mut iter = vec![Ok(1), Err(Foo), Ok(2), Err(Foo), Err(Foo), Ok(3)]
+            .into_iter()
+            .fail_after_errors(3, "too many failures");
+        assert_eq!(iter.next().unwrap().unwrap(), 1);
+        assert_eq!(iter.next().unwrap().unwrap(), 2);
+
+        let mut out = String::new();
+        let p = iter.next().unwrap().unwrap_err();
+        p.write(&mut out, &DisplayOptions::default(), 1, ReportStyle::Long)
+            .unwrap();
+        assert_eq!(
+            out,
+            "too many failures: 3 problems occurred:\n├─ Foo error\n├─ Foo error\n└─ Foo error"
+        );
+        assert!(iter.next().is_none());
+
+        let ok = vec![Ok(1), Err(Foo), Ok(2)]
+            .into_iter()
+            .fail_after_errors(2, "too many failures")
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(ok, vec![1, 2]);
+    }
+
     #[test]
     #[cfg(feature = "log")]
     fn test_problem_log_iter_error() {
@@ -1257,4 +6162,204 @@ mod tests {
             vec![1, 2, 3]
         );
     }
+
+    #[test]
+    #[cfg(feature = "log")]
+    fn test_problem_log_iter_traits() {
+        loggerv::init_quiet().ok();
+        let iter = vec![Ok(1), Err(Foo), Ok(2)].into_iter().ok_or_log_warn();
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.rev().flatten().collect::<Vec<_>>(), vec![2, 1]);
+
+        let iter = vec![Ok(1), Err(Foo), Ok(2)].into_iter().ok_or_log_error();
+        assert_eq!(iter.size_hint(), (3, Some(3)));
+        assert_eq!(iter.rev().flatten().collect::<Vec<_>>(), vec![2, 1]);
+    }
+
+    #[test]
+    fn test_problems_group_by_cause() {
+        disable_backtraces();
+        let problems: Problems = vec![
+            Problem::from_error("timeout"),
+            Problem::from_error(Baz(Bar(Foo))).problem_while("parsing input"),
+            Problem::from_error(Foo),
+            Problem::from_error(io::Error::other("disk full")),
+            Problem::from_error("timeout").problem_while("retrying"),
+            Problem::from_error(Bar(Foo)),
+            Problem::from_error("x".parse::<u32>().unwrap_err()),
+            Problem::from_error("".parse::<u32>().unwrap_err()),
+            Problem::from_error(Box::<dyn Error>::from("-".parse::<u32>().unwrap_err())),
+            Problem::from_error(ErrorMessage::new("bad record")),
+        ]
+        .into_iter()
+        .collect();
+
+        let groups = problems.group_by_cause();
+        assert_eq!(
+            groups
+                .iter()
+                .map(|group| (group.cause(), group.count()))
+                .collect::<Vec<_>>(),
+            [
+                ("ParseIntError", 3),
+                ("timeout", 2),
+                ("Foo error", 2),
+                ("Foo", 1),
+                ("Other", 1),
+                ("bad record", 1)
+            ]
+        );
+        assert_eq!(
+            groups[2].representative().to_string(),
+            "while parsing input got error caused by: Baz error; caused by: Bar error; caused by: Foo error"
+        );
+        assert_eq!(groups[4].to_string(), "1 Other");
+        assert!(Problems::new().group_by_cause().is_empty());
+    }
+
+    #[test]
+    fn test_problems_tree() {
+        let mut inner = Problems::new();
+        inner.push("bad header");
+        inner.push(Problem::from_error("bad row").problem_while("reading row 3"));
+
+        let mut problems = Problems::new();
+        problems.push(Problem::from_error(inner).problem_while("importing b.csv"));
+        problems.push("file not found");
+
+        let mut out = String::new();
+        problems
+            .write_tree(&mut out, &DisplayOptions::default(), 1, "")
+            .unwrap();
+        assert_eq!(
+            out,
+            "2 problems occurred:
+├─ while importing b.csv got error caused by: 2 problems occurred:
+│  ├─ bad header
+│  └─ while reading row 3 got error caused by: bad row
+└─ file not found"
+        );
+
+        let p = problems.into_result().unwrap_err();
+        let mut out = String::new();
+        p.write(&mut out, &DisplayOptions::default(), 1, ReportStyle::Short)
+            .unwrap();
+        assert_eq!(
+            out,
+            "2 problems occurred: while importing b.csv got error caused by: 2 problems occurred: bad header; while reading row 3 got error caused by: bad row; file not found"
+        );
+
+        assert!(Problems::new().into_result().is_ok());
+    }
+
+    #[test]
+    fn test_multi_source() {
+        disable_backtraces();
+        #[derive(Debug)]
+        struct Aggregate(Vec<Baz>, io::Error);
+
+        impl Display for Aggregate {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "validation failed")
+            }
+        }
+
+        impl Error for Aggregate {}
+
+        impl MultiSource for Aggregate {
+            fn sources(&self) -> Vec<&(dyn Error + 'static)> {
+                let mut sources: Vec<&(dyn Error + 'static)> =
+                    self.0.iter().map(|error| error as _).collect();
+                sources.push(&self.1);
+                sources
+            }
+        }
+
+        let error = Aggregate(
+            vec![Baz(Bar(Foo))],
+            io::Error::new(io::ErrorKind::NotFound, "missing.toml"),
+        );
+        let p = Problem::from_multi_source(&error).problem_while("loading config");
+
+        let mut out = String::new();
+        p.write(&mut out, &DisplayOptions::default(), 1, ReportStyle::Long)
+            .unwrap();
+        assert_eq!(
+            out,
+            "while loading config got error caused by: validation failed: 2 problems occurred:
+├─ Baz error; caused by: Bar error; caused by: Foo error
+└─ missing.toml"
+        );
+        assert_eq!(
+            p.to_string(),
+            "while loading config got error caused by: validation failed: 2 problems occurred: Baz error; caused by: Bar error; caused by: Foo error; missing.toml"
+        );
+    }
+
+    #[test]
+    fn test_exit_code_tags() {
+        let table = vec![("usage".to_owned(), 64), ("config".to_owned(), 78)];
+
+        let problem = Problem::from_error("boom!");
+        assert_eq!(problem.tags().count(), 0);
+        assert_eq!(problem.map_exit_code(&table), None);
+
+        let problem = problem.with_tag("io").with_tag("config");
+        assert_eq!(problem.tags().collect::<Vec<_>>(), ["io", "config"]);
+        assert!(problem.has_tag("config"));
+        assert!(!problem.has_tag("usage"));
+        assert_eq!(problem.map_exit_code(&table), Some(78));
+
+        let problem = problem.with_exit_code(3);
+        assert_eq!(problem.map_exit_code(&table), Some(3));
+    }
+
+    #[test]
+    fn test_static_msg() {
+        const SENTINEL: Problem = Problem::static_msg("not ready");
+
+        let problem = SENTINEL.problem_while("polling");
+        assert_eq!(
+            problem.to_string(),
+            "while polling got error caused by: not ready"
+        );
+        assert!(problem.backtrace().is_none());
+        assert_eq!(
+            format!("{:?}", SENTINEL.error),
+            "StaticMessage(\"not ready\")"
+        );
+
+        let problem = SENTINEL.recover_if::<io::Error>().unwrap_err();
+        assert_eq!(problem.to_string(), "not ready");
+    }
+
+    #[test]
+    fn test_from_panic_payload() {
+        disable_backtraces();
+        let payload: Box<dyn Any + Send> = Box::new("boom!");
+        assert_eq!(Problem::from_panic_payload(payload).to_string(), "boom!");
+
+        let payload: Box<dyn Any + Send> = Box::new("boom!".to_owned());
+        assert_eq!(Problem::from_panic_payload(payload).to_string(), "boom!");
+
+        let error: Box<dyn Error + Send + Sync> = Box::new(io::Error::other("disk full"));
+        let payload: Box<dyn Any + Send> = Box::new(error);
+        let problem = Problem::from_panic_payload(payload);
+        assert_eq!(problem.to_string(), "disk full");
+        assert!(problem.find_cause::<io::Error>().is_some());
+
+        let problem = Problem::from_error("boom!")
+            .problem_while("processing")
+            .with_exit_code(3);
+        let payload: Box<dyn Any + Send> = Box::new(ProblemError::from(problem));
+        let problem = Problem::from_panic_payload(payload);
+        assert_eq!(problem.contexts().collect::<Vec<_>>(), ["processing"]);
+        assert_eq!(problem.exit_code(), Some(3));
+
+        let payload: Box<dyn Any + Send> = Box::new(42);
+        assert_eq!(
+            Problem::from_panic_payload(payload).to_string(),
+            "Box<dyn Any>"
+        );
+    }
 }
@@ -0,0 +1,104 @@
+//! Statistics of `Problem`s in Prometheus text exposition format.
+//!
+//! `render` formats numbers of `Problem`s created, reported to sinks (e.g. with `ok_or_log_*` methods) and suppressed, as counted
+//! in `ProblemCounter::global()`, so they can be served on `/metrics` endpoint of a service and used for alerting on error rates.
+//!
+//! ```rust
+//! use problem::prelude::*;
+//!
+//! let _ = "80x".parse::<u16>().ok_or_print_error();
+//!
+//! let metrics = problem::metrics::render();
+//! assert!(metrics.contains("# TYPE problem_reported_total counter\n"));
+//! assert!(metrics.contains("problem_reported_total{severity=\"error\"} 1\n"));
+//! ```
+use super::{backtrace_memory_retained, problems_created, ProblemCounter};
+use std::fmt::Write;
+
+/// Content type of the format produced by `render`
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+fn write_metric(out: &mut String, name: &str, kind: &str, help: &str, samples: &[(&str, u64)]) {
+    writeln!(out, "# HELP {} {}", name, help).ok();
+    writeln!(out, "# TYPE {} {}", name, kind).ok();
+    for (labels, value) in samples {
+        writeln!(out, "{}{} {}", name, labels, value).ok();
+    }
+}
+
+fn render_with(created: u64, counter: &ProblemCounter, backtrace_memory: u64) -> String {
+    let mut out = String::new();
+    write_metric(
+        &mut out,
+        "problem_created_total",
+        "counter",
+        "Number of Problems created.",
+        &[("", created)],
+    );
+    write_metric(
+        &mut out,
+        "problem_reported_total",
+        "counter",
+        "Number of Problems reported to sinks by severity.",
+        &[
+            ("{severity=\"error\"}", counter.errors()),
+            ("{severity=\"warning\"}", counter.warnings()),
+        ],
+    );
+    write_metric(
+        &mut out,
+        "problem_suppressed_total",
+        "counter",
+        "Number of Problems ignored without reporting.",
+        &[("", counter.suppressed())],
+    );
+    write_metric(
+        &mut out,
+        "problem_backtrace_memory_bytes",
+        "gauge",
+        "Memory used by backtraces of live Problems.",
+        &[("", backtrace_memory)],
+    );
+    out
+}
+
+/// Format statistics of `Problem`s of this process in Prometheus text exposition format (see `CONTENT_TYPE`)
+pub fn render() -> String {
+    render_with(
+        problems_created(),
+        ProblemCounter::global(),
+        backtrace_memory_retained() as u64,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn text_format() {
+        let counter = ProblemCounter::new();
+        counter.count_error();
+        counter.count_error();
+        counter.count_warning();
+        let _ = Err::<(), _>("ignored").ok_or_count(&counter);
+
+        assert_eq!(
+            render_with(5, &counter, 1024),
+            "# HELP problem_created_total Number of Problems created.\n\
+             # TYPE problem_created_total counter\n\
+             problem_created_total 5\n\
+             # HELP problem_reported_total Number of Problems reported to sinks by severity.\n\
+             # TYPE problem_reported_total counter\n\
+             problem_reported_total{severity=\"error\"} 2\n\
+             problem_reported_total{severity=\"warning\"} 1\n\
+             # HELP problem_suppressed_total Number of Problems ignored without reporting.\n\
+             # TYPE problem_suppressed_total counter\n\
+             problem_suppressed_total 1\n\
+             # HELP problem_backtrace_memory_bytes Memory used by backtraces of live Problems.\n\
+             # TYPE problem_backtrace_memory_bytes gauge\n\
+             problem_backtrace_memory_bytes 1024\n"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! Reuse of `Problem` context buffers.
+use super::{Problem, ToProblem};
+use std::cell::RefCell;
+use std::fmt::{Display, Write};
+
+/// Pool of context message buffers for `Problem`s created and dropped in tight loops
+///
+/// `Problem`s created with `ProblemPool::problem_while` take context `Vec` and message `String` from the pool and format
+/// the message into it; `ProblemPool::recycle` returns the buffers of a `Problem` that is no longer needed back to the pool.
+/// This reduces allocator pressure when processing many items with a few percent of failures (e.g. parsing records). The
+/// error itself is still boxed as with `Problem::from_error`.
+///
+/// The pool is not `Sync`; use one per thread.
+///
+/// ```rust
+/// use problem::prelude::*;
+/// use problem::ProblemPool;
+///
+/// let pool = ProblemPool::new(16);
+/// let mut failed = 0;
+///
+/// for (line, record) in ["1", "x", "3", "y"].iter().enumerate() {
+///     match record.parse::<u32>() {
+///         Ok(_) => (),
+///         Err(err) => {
+///             let problem = pool.problem_while(err, format_args!("parsing record {}", line + 1));
+///             assert!(problem.to_string().starts_with("while parsing record "));
+///             failed += 1;
+///             pool.recycle(problem);
+///         }
+///     }
+/// }
+///
+/// assert_eq!(failed, 2);
+/// assert_eq!(pool.available(), 1);
+/// ```
+#[derive(Debug)]
+pub struct ProblemPool {
+    capacity: usize,
+    contexts: RefCell<Vec<Vec<String>>>,
+    messages: RefCell<Vec<String>>,
+}
+
+impl ProblemPool {
+    /// Create empty pool keeping at most given number of context `Vec`s and given number of message `String`s
+    pub fn new(capacity: usize) -> ProblemPool {
+        ProblemPool {
+            capacity,
+            contexts: RefCell::new(Vec::with_capacity(capacity)),
+            messages: RefCell::new(Vec::with_capacity(capacity)),
+        }
+    }
+
+    /// Convert error to `Problem` and add context message formatted into pooled buffer; same as `ProblemWhile::problem_while`
+    pub fn problem_while(&self, error: impl ToProblem, message: impl Display) -> Problem {
+        let mut problem = error.into_problem();
+        if problem.context.capacity() == 0 {
+            if let Some(context) = self.contexts.borrow_mut().pop() {
+                problem.context = context;
+            }
+        }
+        let mut buffer = self.messages.borrow_mut().pop().unwrap_or_default();
+        write!(buffer, "{}", message).ok();
+        problem.push_context(buffer)
+    }
+
+    /// Return context buffers of given `Problem` to the pool and drop it; buffers over pool capacity are freed
+    pub fn recycle(&self, mut problem: Problem) {
+        let mut context = std::mem::take(&mut problem.context);
+        {
+            let mut messages = self.messages.borrow_mut();
+            for mut message in context.drain(..) {
+                if messages.len() == self.capacity {
+                    break;
+                }
+                message.clear();
+                messages.push(message);
+            }
+        }
+        let mut contexts = self.contexts.borrow_mut();
+        if context.capacity() > 0 && contexts.len() < self.capacity {
+            context.clear();
+            contexts.push(context);
+        }
+    }
+
+    /// Number of context `Vec`s available for reuse
+    pub fn available(&self) -> usize {
+        self.contexts.borrow().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn reuse() {
+        crate::disable_backtraces();
+        let pool = ProblemPool::new(1);
+
+        let problem = pool
+            .problem_while("boom!", format_args!("parsing record {}", 42))
+            .problem_while("processing batch");
+        assert_eq!(
+            problem.contexts().collect::<Vec<_>>(),
+            ["parsing record 42", "processing batch"]
+        );
+        let message = problem.context[0].as_ptr();
+        pool.recycle(problem);
+        assert_eq!(pool.available(), 1);
+
+        let problem = pool.problem_while("boom!", "reading");
+        assert_eq!(
+            problem.to_string(),
+            "while reading got error caused by: boom!"
+        );
+        assert_eq!(problem.context[0].as_ptr(), message);
+        assert_eq!(pool.available(), 0);
+
+        let other = pool.problem_while(Problem::from_error("bang!"), "writing");
+        pool.recycle(problem);
+        pool.recycle(other);
+        assert_eq!(pool.available(), 1);
+        assert_eq!(pool.messages.borrow().len(), 1);
+    }
+}
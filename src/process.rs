@@ -0,0 +1,210 @@
+//! Running of subprocesses reporting failures as `Problem`s.
+//!
+//! ```rust
+//! use problem::prelude::*;
+//! use problem::process::run;
+//! use std::process::Command;
+//!
+//! # #[cfg(unix)]
+//! # {
+//! let problem = run(Command::new("false")).unwrap_err();
+//! assert_eq!(problem.to_string(), "while running `false` got error caused by: process exited with code 1");
+//!
+//! let problem = Command::new("false").status().unwrap().success_or_problem("linking objects").unwrap_err();
+//! assert_eq!(problem.to_string(), "while linking objects got error caused by: process exited with code 1");
+//! # }
+//! ```
+use super::{IntoProblem, Problem, ProblemWhile};
+use std::error::Error;
+use std::fmt;
+use std::process::{Command, ExitStatus, Output};
+
+/// Error of a process that did not exit successfully
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExitStatusError {
+    status: ExitStatus,
+}
+
+impl ExitStatusError {
+    /// Exit status of the process
+    pub fn status(&self) -> ExitStatus {
+        self.status
+    }
+
+    /// Exit code of the process if it exited normally
+    pub fn code(&self) -> Option<i32> {
+        self.status.code()
+    }
+
+    /// Number of signal that terminated the process
+    #[cfg(unix)]
+    pub fn signal(&self) -> Option<i32> {
+        use std::os::unix::process::ExitStatusExt;
+        self.status.signal()
+    }
+
+    #[cfg(not(unix))]
+    fn signal(&self) -> Option<i32> {
+        None
+    }
+}
+
+impl fmt::Display for ExitStatusError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.code(), self.signal()) {
+            (Some(code), _) => write!(f, "process exited with code {}", code),
+            (None, Some(signal)) => write!(f, "process was terminated by signal {}", signal),
+            (None, None) => write!(f, "process exited with {}", self.status),
+        }
+    }
+}
+
+impl Error for ExitStatusError {}
+
+impl IntoProblem for ExitStatusError {}
+
+/// Format program and its arguments for display
+fn command_line(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| {
+            let arg = arg.to_string_lossy();
+            if arg.is_empty() || arg.contains(char::is_whitespace) {
+                format!("{:?}", arg)
+            } else {
+                arg.into_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Create `Problem` for unsuccessful exit status with captured standard error output attached as `Stderr` section
+fn exit_status_problem(status: ExitStatus, stderr: &[u8]) -> Problem {
+    let problem = Problem::from_error(ExitStatusError { status });
+    let stderr = String::from_utf8_lossy(stderr);
+    let stderr = stderr.trim();
+    if stderr.is_empty() {
+        problem
+    } else {
+        problem.with_section("Stderr", stderr)
+    }
+}
+
+/// Run command to completion capturing its output
+///
+/// Failure to spawn the process and unsuccessful exit status (including termination by signal) are reported as `Problem` with
+/// the command line as context and captured standard error output attached as `Stderr` section of the long report.
+/// `ExitStatusError` can be used to get the exit code (see `Problem::recover_if`).
+pub fn run(mut command: Command) -> Result<Output, Problem> {
+    let context = format!("running `{}`", command_line(&command));
+    let output = command.output().problem_while(&context)?;
+    output.success_or_problem(context)
+}
+
+/// Extension trait for checking result of a process
+pub trait SuccessOrProblem: Sized {
+    /// Returns `Err` with `ExitStatusError` with given context message if the process did not exit successfully
+    fn success_or_problem(self, message: impl ToString) -> Result<Self, Problem>;
+}
+
+impl SuccessOrProblem for ExitStatus {
+    fn success_or_problem(self, message: impl ToString) -> Result<ExitStatus, Problem> {
+        if self.success() {
+            Ok(self)
+        } else {
+            Err(exit_status_problem(self, &[])).problem_while(message)
+        }
+    }
+}
+
+/// Captured standard error output is trimmed and attached as `Stderr` section of the long report.
+impl SuccessOrProblem for Output {
+    fn success_or_problem(self, message: impl ToString) -> Result<Output, Problem> {
+        if self.status.success() {
+            Ok(self)
+        } else {
+            Err(exit_status_problem(self.status, &self.stderr)).problem_while(message)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DisplayOptions, ReportStyle};
+
+    #[test]
+    fn spawn_failure() {
+        let problem = run(Command::new("/nonexistent/problem-test")).unwrap_err();
+        assert!(problem.is_not_found());
+        assert_eq!(
+            problem.contexts().collect::<Vec<_>>(),
+            ["running `/nonexistent/problem-test`"]
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn exit_code_and_stderr() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo 'bad input' >&2; exit 3"]);
+        let problem = run(command).unwrap_err();
+
+        let mut out = String::new();
+        problem
+            .write(&mut out, &DisplayOptions::default(), 1, ReportStyle::Long)
+            .unwrap();
+        assert_eq!(
+            out,
+            "while running `sh -c \"echo 'bad input' >&2; exit 3\"` got error caused by: process exited with code 3\n--- Stderr\nbad input"
+        );
+        assert_eq!(
+            problem.recover_if::<ExitStatusError>().unwrap().code(),
+            Some(3)
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn signal() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "kill -9 $$"]);
+        let error = run(command)
+            .unwrap_err()
+            .recover_if::<ExitStatusError>()
+            .unwrap();
+        assert_eq!(error.signal(), Some(9));
+        assert_eq!(error.to_string(), "process was terminated by signal 9");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn output_success_or_problem() {
+        let output = Command::new("sh")
+            .args(["-c", "echo '  linker failed  ' >&2; exit 1"])
+            .output()
+            .unwrap();
+        let problem = output.success_or_problem("linking objects").unwrap_err();
+
+        let mut out = String::new();
+        problem
+            .write(&mut out, &DisplayOptions::default(), 1, ReportStyle::Long)
+            .unwrap();
+        assert_eq!(
+            out,
+            "while linking objects got error caused by: process exited with code 1\n--- Stderr\nlinker failed"
+        );
+
+        let status = Command::new("true").status().unwrap();
+        assert!(status.success_or_problem("checking").is_ok());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn success() {
+        let mut command = Command::new("echo");
+        command.arg("hello");
+        assert_eq!(run(command).unwrap().stdout, b"hello\n");
+    }
+}
@@ -0,0 +1,287 @@
+//! Parsing of `Problem` reports produced by this crate back into structured form.
+use super::{ErrorMessage, Problem};
+
+/// `Problem` reconstructed from its text report (see `Problem::parse_report`)
+///
+/// Original error types are not available; error and its causes are represented by their messages.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemoteProblem {
+    failed_to: Option<String>,
+    contexts: Vec<String>,
+    causes: Vec<String>,
+    url: Option<String>,
+    trace_id: Option<String>,
+    sections: Vec<(String, String)>,
+    backtrace: Option<String>,
+}
+
+impl RemoteProblem {
+    /// Message given to `or_failed_to` if the report comes from a panic
+    pub fn failed_to(&self) -> Option<&str> {
+        self.failed_to.as_deref()
+    }
+
+    /// Context messages innermost first (like `Problem::contexts`)
+    pub fn contexts(&self) -> impl DoubleEndedIterator<Item = &str> + ExactSizeIterator {
+        self.contexts.iter().map(String::as_str)
+    }
+
+    /// Error message followed by messages of its causes
+    pub fn causes(&self) -> impl DoubleEndedIterator<Item = &str> + ExactSizeIterator {
+        self.causes.iter().map(String::as_str)
+    }
+
+    /// Documentation URL if reported
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// Trace or correlation ID if reported
+    pub fn trace_id(&self) -> Option<&str> {
+        self.trace_id.as_deref()
+    }
+
+    /// Additional sections of long report (e.g. `Environment`) as title and body pairs
+    pub fn sections(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.sections
+            .iter()
+            .map(|(title, body)| (title.as_str(), body.as_str()))
+    }
+
+    /// Backtrace of the `Problem` cause or panic location if reported
+    pub fn backtrace(&self) -> Option<&str> {
+        self.backtrace.as_deref()
+    }
+
+    fn parse(report: &str) -> RemoteProblem {
+        let mut remote = RemoteProblem::default();
+
+        let mut summary = Vec::new();
+        let mut section: Option<(String, Vec<&str>)> = None;
+        for line in report.trim_end().lines() {
+            if let Some(title) = line.strip_prefix("--- ") {
+                remote.push_section(section.take());
+                section = Some((title.to_owned(), Vec::new()));
+            } else if let Some((_, body)) = section.as_mut() {
+                body.push(line);
+            } else if let Some(url) = line.strip_prefix("see: ") {
+                remote.url = Some(url.to_owned());
+            } else if let Some(trace_id) = line.strip_prefix("trace id: ") {
+                remote.trace_id = Some(trace_id.to_owned());
+            } else if let Some(error_id) = line.strip_prefix("error id: ") {
+                remote.trace_id.get_or_insert_with(|| error_id.to_owned());
+            } else {
+                summary.push(line);
+            }
+        }
+        remote.push_section(section);
+
+        let mut summary = summary.join("\n");
+        if let Some(rest) = summary.strip_prefix("Fatal error: ") {
+            summary = rest.to_owned();
+        }
+        if summary.starts_with("thread '") {
+            if let Some(at) = summary.find(" with: ") {
+                summary = summary[at + " with: ".len()..].to_owned();
+            }
+        }
+        if let Some(rest) = summary.strip_prefix("Failed to ") {
+            if let Some(at) = rest.find(" due to: ") {
+                remote.failed_to = Some(rest[..at].to_owned());
+                summary = rest[at + " due to: ".len()..].to_owned();
+            }
+        }
+
+        let error = match summary.find(" got error caused by: ") {
+            Some(at) => {
+                remote.parse_contexts(&summary[..at]);
+                &summary[at + " got error caused by: ".len()..]
+            }
+            None => summary.as_str(),
+        };
+        remote.causes = error
+            .split("; caused by: ")
+            .map(ToOwned::to_owned)
+            .collect();
+
+        remote
+    }
+
+    fn parse_contexts(&mut self, contexts: &str) {
+        for context in contexts.split(", ") {
+            let context = match context.strip_prefix("while ") {
+                Some(context) => context,
+                // `… and N more contexts` marker or context message containing separator
+                None => match self.contexts.last_mut() {
+                    Some(last) if !context.starts_with('…') => {
+                        last.push_str(", ");
+                        last.push_str(context);
+                        continue;
+                    }
+                    _ => continue,
+                },
+            };
+
+            // expand collapsed repeated context messages
+            let repeated = context
+                .strip_suffix(')')
+                .and_then(|context| context.rsplit_once(" (x"))
+                .and_then(|(context, count)| {
+                    count.parse::<usize>().ok().map(|count| (context, count))
+                });
+            match repeated {
+                Some((context, count)) => {
+                    self.contexts.extend((0..count).map(|_| context.to_owned()))
+                }
+                None => self.contexts.push(context.to_owned()),
+            }
+        }
+        // displayed outermost first
+        self.contexts.reverse();
+    }
+
+    fn push_section(&mut self, section: Option<(String, Vec<&str>)>) {
+        if let Some((title, body)) = section {
+            let body = body.join("\n");
+            match title.as_str() {
+                "Cause" | "Panicked" => self.backtrace = Some(body),
+                _ => self.sections.push((title, body)),
+            }
+        }
+    }
+
+    /// Convert to `Problem` with `ErrorMessage` cause chain, context messages, sections, documentation URL, trace ID and backtrace
+    /// of this report
+    ///
+    /// Further context can be added to the returned `Problem` as usual.
+    pub fn into_problem(self) -> Problem {
+        let error = ErrorMessage::from_chain(self.causes).unwrap_or_else(|| ErrorMessage::new(""));
+        let mut problem = Problem::from_parts(error, self.contexts, self.backtrace);
+        if let Some(url) = self.url {
+            problem = problem.with_url(url);
+        }
+        if let Some(trace_id) = self.trace_id {
+            problem = problem.with_trace_id(trace_id);
+        }
+        for (title, body) in self.sections {
+            problem = problem.with_section(title, body);
+        }
+        problem
+    }
+}
+
+impl From<RemoteProblem> for Problem {
+    fn from(remote: RemoteProblem) -> Problem {
+        remote.into_problem()
+    }
+}
+
+impl Problem {
+    /// Parse `Problem` report as produced by `Display` (including alternate format) or panic hooks of this crate
+    ///
+    /// This is useful for supervisors that want to re-wrap failures of child processes reported on their standard error.
+    /// Note that context messages are expected in default order and separated with default separator.
+    ///
+    /// ```rust
+    /// use problem::prelude::*;
+    ///
+    /// let remote = Problem::parse_report("Fatal error: Failed to import data due to: while importing b.csv, while parsing row 3 got error caused by: bad row; caused by: invalid digit");
+    /// assert_eq!(remote.failed_to(), Some("import data"));
+    /// assert_eq!(remote.contexts().collect::<Vec<_>>(), ["parsing row 3", "importing b.csv"]);
+    /// assert_eq!(remote.causes().collect::<Vec<_>>(), ["bad row", "invalid digit"]);
+    ///
+    /// let problem = remote.into_problem().problem_while("running importer");
+    /// assert_eq!(problem.to_string(), "while running importer, while importing b.csv, while parsing row 3 got error caused by: bad row; caused by: invalid digit");
+    /// ```
+    pub fn parse_report(report: &str) -> RemoteProblem {
+        RemoteProblem::parse(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use crate::{DisplayOptions, ReportStyle};
+
+    #[test]
+    fn round_trip() {
+        let mut problem = Problem::from_parts(
+            ErrorMessage::new("bad row").with_source(ErrorMessage::new("invalid digit")),
+            vec![
+                "parsing row 3, column 2".to_owned(),
+                "importing b.csv".to_owned(),
+            ],
+            Some("   0: main\n   1: start".to_owned()),
+        )
+        .with_url("https://example.com/import")
+        .with_trace_id("abc123")
+        .with_env(&["PROBLEM_REPORT_TEST_UNSET"]);
+        for _ in 0..3 {
+            problem = problem.problem_while("retrying");
+        }
+
+        let mut report = String::new();
+        problem
+            .write(
+                &mut report,
+                &DisplayOptions::default(),
+                2,
+                ReportStyle::Long,
+            )
+            .unwrap();
+        let remote = Problem::parse_report(&report);
+
+        assert_eq!(remote.failed_to(), None);
+        assert_eq!(
+            remote.contexts().collect::<Vec<_>>(),
+            problem.contexts().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            remote.causes().collect::<Vec<_>>(),
+            ["bad row", "invalid digit"]
+        );
+        assert_eq!(remote.url(), Some("https://example.com/import"));
+        assert_eq!(remote.trace_id(), Some("abc123"));
+        assert_eq!(
+            remote.sections().collect::<Vec<_>>(),
+            [("Environment", "PROBLEM_REPORT_TEST_UNSET is not set")]
+        );
+        assert_eq!(remote.backtrace(), Some("   0: main\n   1: start"));
+
+        let mut rendered = String::new();
+        remote
+            .into_problem()
+            .write(
+                &mut rendered,
+                &DisplayOptions::default(),
+                2,
+                ReportStyle::Long,
+            )
+            .unwrap();
+        assert_eq!(rendered, report);
+    }
+
+    #[test]
+    fn panic_report() {
+        let remote = Problem::parse_report(
+            "thread 'main' panicked at src/main.rs:3:5 with: Failed to start due to: boom!\n--- Panicked\n   0: main",
+        );
+        assert_eq!(remote.failed_to(), Some("start"));
+        assert_eq!(remote.contexts().len(), 0);
+        assert_eq!(remote.causes().collect::<Vec<_>>(), ["boom!"]);
+        assert_eq!(remote.backtrace(), Some("   0: main"));
+    }
+
+    #[test]
+    fn error_id() {
+        let remote = Problem::parse_report(
+            "Failed to start due to: boom!\nerror id: 67e55044-10b1-426f-9247-bb680e5fe0c8",
+        );
+        assert_eq!(remote.causes().collect::<Vec<_>>(), ["boom!"]);
+        assert_eq!(
+            remote.trace_id(),
+            Some("67e55044-10b1-426f-9247-bb680e5fe0c8")
+        );
+    }
+}
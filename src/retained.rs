@@ -0,0 +1,243 @@
+//! Accounting of backtrace text retained by live `Problem`s.
+//!
+//! Long-running services may keep many `Problem`s (e.g. of failed tasks queued for later reporting) and their backtraces
+//! can add up to significant amount of memory. With `set_backtrace_memory_budget` total size of backtrace text retained
+//! by live `Problem`s is capped; when a new backtrace would exceed the budget it is not retained and `BACKTRACE_DROPPED` is
+//! displayed instead.
+//!
+//! Backtraces captured as return addresses only (see `BacktraceCapture::FramePointers`) are kept unresolved until first
+//! accessed and are accounted by the size of the addresses until then.
+use std::cell::OnceCell;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Text displayed in place of backtrace that was dropped to stay within memory budget
+pub const BACKTRACE_DROPPED: &str = "<backtrace dropped to stay within memory budget>";
+
+/// Backtrace as stored in a `Problem`
+enum Stored {
+    Text(String),
+    /// Return addresses of frames, function resolving them to backtrace text and the text once resolved
+    Frames(Vec<usize>, fn(&[usize]) -> String, OnceCell<String>),
+    /// Not retained to stay within memory budget
+    Dropped,
+}
+
+impl Stored {
+    fn size(&self) -> usize {
+        match self {
+            Stored::Text(text) => text.len(),
+            Stored::Frames(frames, _, text) => {
+                std::mem::size_of_val(frames.as_slice()) + text.get().map_or(0, String::len)
+            }
+            Stored::Dropped => 0,
+        }
+    }
+}
+
+struct Store {
+    budget: AtomicUsize,
+    retained: AtomicUsize,
+}
+
+static STORE: Store = Store::new();
+
+impl Store {
+    const fn new() -> Store {
+        Store {
+            budget: AtomicUsize::new(usize::MAX),
+            retained: AtomicUsize::new(0),
+        }
+    }
+
+    /// Account given number of bytes if they fit the budget
+    fn admit(&self, size: usize) -> bool {
+        let budget = self.budget.load(Ordering::Relaxed);
+        self.retained
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |retained| {
+                retained.checked_add(size).filter(|&total| total <= budget)
+            })
+            .is_ok()
+    }
+}
+
+/// Set maximum total number of bytes of backtrace text retained by live `Problem`s (unlimited by default)
+///
+/// Backtrace captured when retained backtraces already take up the budget is not retained and `BACKTRACE_DROPPED` is
+/// displayed instead. Backtraces of existing `Problem`s are kept.
+///
+/// ```rust
+/// problem::set_backtrace_memory_budget(Some(16 * 1024 * 1024));
+/// ```
+pub fn set_backtrace_memory_budget(bytes: Option<usize>) {
+    STORE
+        .budget
+        .store(bytes.unwrap_or(usize::MAX), Ordering::Relaxed);
+}
+
+/// Get total number of bytes of backtrace text retained by live `Problem`s
+pub fn backtrace_memory_retained() -> usize {
+    STORE.retained.load(Ordering::Relaxed)
+}
+
+/// Backtrace of a `Problem` accounted in the memory budget
+pub(crate) struct StoredBacktrace {
+    store: &'static Store,
+    // Note: boxed to keep `Problem` small
+    stored: Box<Stored>,
+}
+
+impl StoredBacktrace {
+    pub(crate) fn new(text: String) -> StoredBacktrace {
+        StoredBacktrace::new_in(&STORE, Stored::Text(text))
+    }
+
+    /// Store return addresses of frames to be resolved with given function when backtrace is accessed for the first time
+    #[cfg_attr(not(feature = "backtrace"), allow(dead_code))]
+    pub(crate) fn deferred(frames: Vec<usize>, resolve: fn(&[usize]) -> String) -> StoredBacktrace {
+        StoredBacktrace::new_in(&STORE, Stored::Frames(frames, resolve, OnceCell::new()))
+    }
+
+    fn new_in(store: &'static Store, stored: Stored) -> StoredBacktrace {
+        let stored = if store.admit(stored.size()) {
+            stored
+        } else {
+            Stored::Dropped
+        };
+        StoredBacktrace {
+            store,
+            stored: Box::new(stored),
+        }
+    }
+
+    pub(crate) fn get(&self) -> &str {
+        match &*self.stored {
+            Stored::Text(text) => text,
+            Stored::Frames(frames, resolve, text) => text.get_or_init(|| {
+                let text = resolve(frames);
+                // Note: resolved backtrace is retained even if it exceeds the budget as it is already borrowed
+                self.store.retained.fetch_add(text.len(), Ordering::Relaxed);
+                text
+            }),
+            Stored::Dropped => BACKTRACE_DROPPED,
+        }
+    }
+
+    /// Take out backtrace text; it is no longer accounted in the memory budget
+    pub(crate) fn into_string(mut self) -> String {
+        let stored = std::mem::replace(&mut *self.stored, Stored::Dropped);
+        self.store
+            .retained
+            .fetch_sub(stored.size(), Ordering::Relaxed);
+        match stored {
+            Stored::Text(text) => text,
+            Stored::Frames(frames, resolve, text) => {
+                text.into_inner().unwrap_or_else(|| resolve(&frames))
+            }
+            Stored::Dropped => BACKTRACE_DROPPED.to_owned(),
+        }
+    }
+}
+
+impl Drop for StoredBacktrace {
+    fn drop(&mut self) {
+        self.store
+            .retained
+            .fetch_sub(self.stored.size(), Ordering::Relaxed);
+    }
+}
+
+impl fmt::Debug for StoredBacktrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.get(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> Stored {
+        Stored::Text(text.to_owned())
+    }
+
+    fn store(budget: usize) -> &'static Store {
+        let store = Box::leak(Box::new(Store::new()));
+        store.budget.store(budget, Ordering::Relaxed);
+        store
+    }
+
+    #[test]
+    fn unlimited() {
+        let store = store(usize::MAX);
+        let first = StoredBacktrace::new_in(store, text("0: main"));
+        let second = StoredBacktrace::new_in(store, text("1: start"));
+        assert_eq!(store.retained.load(Ordering::Relaxed), 15);
+
+        assert_eq!(first.get(), "0: main");
+        assert_eq!(second.into_string(), "1: start");
+        assert_eq!(store.retained.load(Ordering::Relaxed), 7);
+        drop(first);
+        assert_eq!(store.retained.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn over_budget_dropped() {
+        let store = store(20);
+        let first = StoredBacktrace::new_in(store, text("0: main"));
+        let second = StoredBacktrace::new_in(store, text("0: worker"));
+        assert_eq!(store.retained.load(Ordering::Relaxed), 16);
+
+        let third = StoredBacktrace::new_in(store, text("0: handler"));
+        assert_eq!(first.get(), "0: main");
+        assert_eq!(second.get(), "0: worker");
+        assert_eq!(third.get(), BACKTRACE_DROPPED);
+        assert_eq!(store.retained.load(Ordering::Relaxed), 16);
+
+        drop(first);
+        let fourth = StoredBacktrace::new_in(store, text("0: job"));
+        assert_eq!(fourth.get(), "0: job");
+        assert_eq!(store.retained.load(Ordering::Relaxed), 15);
+
+        assert_eq!(third.into_string(), BACKTRACE_DROPPED);
+        drop((second, fourth));
+        assert_eq!(store.retained.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn deferred() {
+        fn resolve(frames: &[usize]) -> String {
+            frames
+                .iter()
+                .enumerate()
+                .map(|(no, frame)| format!("{}: {:#x}", no, frame))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+
+        let store = store(usize::MAX);
+        let frames = StoredBacktrace::new_in(
+            store,
+            Stored::Frames(vec![0x10, 0x20], resolve, OnceCell::new()),
+        );
+        assert_eq!(
+            store.retained.load(Ordering::Relaxed),
+            2 * std::mem::size_of::<usize>()
+        );
+
+        assert_eq!(frames.get(), "0: 0x10\n1: 0x20");
+        assert_eq!(
+            store.retained.load(Ordering::Relaxed),
+            2 * std::mem::size_of::<usize>() + 15
+        );
+        assert_eq!(frames.get(), "0: 0x10\n1: 0x20");
+
+        let frames =
+            StoredBacktrace::new_in(store, Stored::Frames(vec![0x30], resolve, OnceCell::new()));
+        assert_eq!(frames.into_string(), "0: 0x30");
+        assert_eq!(
+            store.retained.load(Ordering::Relaxed),
+            2 * std::mem::size_of::<usize>() + 15
+        );
+    }
+}
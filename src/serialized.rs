@@ -0,0 +1,282 @@
+//! `serde` serialization of `Problem`s for log pipelines.
+//!
+//! Serialized `Problem` is a map with `schema` field set to `SCHEMA_VERSION` followed by error message and messages of its
+//! causes (`messages`), context messages, innermost first (`contexts`) and optional fields: `backtrace`, `url`, `status`,
+//! `exit_code`, `sections` (list of maps with `title`, `body` and optional `visibility`), `tags`, `trace_id`, `dropped_contexts`
+//! and `kind`. Optional fields are omitted when not set.
+//!
+//! Within a schema version fields are never removed or renamed and their meaning does not change; new optional fields may be
+//! added, so consumers should ignore fields they don't know. Deserialization is tolerant: missing fields (e.g. in records of
+//! older versions or written by other tools) take default values and unknown fields are ignored. The original error types are
+//! not preserved; deserialized `Problem` will have `ErrorMessage` cause chain instead. Kind is restored only if it is built-in or
+//! registered with `register_kind`; context timestamps are not serialized.
+//!
+//! ```rust
+//! # extern crate serde_json;
+//! use problem::prelude::*;
+//!
+//! let problem = Problem::from_error("boom!").problem_while("processing job 42").with_exit_code(3);
+//! assert_eq!(
+//!     serde_json::to_string(&problem).unwrap(),
+//!     r#"{"schema":1,"messages":["boom!"],"contexts":["processing job 42"],"exit_code":3}"#
+//! );
+//! ```
+//!
+//! HTTP services can respond with RFC 7807 problem details object made with `Problem::to_problem_details` instead.
+use super::detached::DetachedDetails;
+use super::{
+    error_chain, DisplayOptions, ErrorMessage, Problem, ProblemKind, ReportStyle, SectionVisibility,
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Version of the schema written when `Problem` is serialized
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct SerializedProblem {
+    /// Records without this field are version 0
+    schema: u32,
+    messages: Vec<String>,
+    contexts: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    backtrace: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exit_code: Option<i32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    sections: Vec<SerializedSection>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace_id: Option<String>,
+    #[serde(skip_serializing_if = "is_zero")]
+    dropped_contexts: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kind: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct SerializedSection {
+    title: String,
+    body: String,
+    #[serde(skip_serializing_if = "is_always")]
+    visibility: SectionVisibility,
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+fn is_always(visibility: &SectionVisibility) -> bool {
+    *visibility == SectionVisibility::Always
+}
+
+/// Serializes as map described in module documentation
+impl Serialize for Problem {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let details = DetachedDetails::of(self);
+        SerializedProblem {
+            schema: SCHEMA_VERSION,
+            messages: error_chain(self.error.as_ref())
+                .map(ToString::to_string)
+                .collect(),
+            contexts: self.context.clone(),
+            backtrace: self.backtrace().map(|backtrace| backtrace.to_owned()),
+            url: details.url,
+            status: details.status,
+            exit_code: details.exit_code,
+            sections: details
+                .sections
+                .into_iter()
+                .map(|(title, body, visibility)| SerializedSection {
+                    title,
+                    body,
+                    visibility,
+                })
+                .collect(),
+            tags: details.tags,
+            trace_id: details.trace_id,
+            dropped_contexts: details.dropped_contexts,
+            kind: details.kind.map(|kind| kind.name().to_owned()),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Deserializes map described in module documentation; missing fields take default values
+impl<'de> Deserialize<'de> for Problem {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Problem, D::Error> {
+        let serialized = SerializedProblem::deserialize(deserializer)?;
+
+        let error =
+            ErrorMessage::from_chain(serialized.messages).unwrap_or_else(|| ErrorMessage::new(""));
+        let mut problem = Problem::from_parts(error, serialized.contexts, serialized.backtrace);
+        DetachedDetails {
+            url: serialized.url,
+            status: serialized.status,
+            exit_code: serialized.exit_code,
+            sections: serialized
+                .sections
+                .into_iter()
+                .map(|section| (section.title, section.body, section.visibility))
+                .collect(),
+            dropped_contexts: serialized.dropped_contexts,
+            trace_id: serialized.trace_id,
+            tags: serialized.tags,
+            kind: serialized.kind.as_deref().and_then(ProblemKind::from_name),
+            ..DetachedDetails::default()
+        }
+        .attach_to(&mut problem);
+
+        Ok(problem)
+    }
+}
+
+/// Media type of serialized `ProblemDetails`
+pub const PROBLEM_JSON_CONTENT_TYPE: &str = "application/problem+json";
+
+/// RFC 7807 problem details object describing `Problem` in HTTP API response (see `PROBLEM_JSON_CONTENT_TYPE`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProblemDetails {
+    /// Documentation URL (see `Problem::with_url`) or `about:blank` if not set
+    #[serde(rename = "type")]
+    pub type_uri: String,
+    /// Reason phrase of the HTTP status code
+    pub title: String,
+    /// HTTP status code (see `Problem::status`)
+    pub status: u16,
+    /// One line message with context messages and error cause chain
+    pub detail: String,
+}
+
+impl Problem {
+    /// Describe this `Problem` with RFC 7807 problem details object
+    ///
+    /// ```rust
+    /// # extern crate serde_json;
+    /// use problem::prelude::*;
+    /// use std::io;
+    ///
+    /// let problem = Problem::from_error(io::Error::new(io::ErrorKind::NotFound, "no such user"))
+    ///     .problem_while("loading user 42")
+    ///     .with_url("https://docs.example.com/errors/E42");
+    ///
+    /// # if problem.backtrace().is_none() {
+    /// assert_eq!(
+    ///     serde_json::to_string(&problem.to_problem_details()).unwrap(),
+    ///     r#"{"type":"https://docs.example.com/errors/E42","title":"Not Found","status":404,"detail":"while loading user 42 got error caused by: no such user"}"#
+    /// );
+    /// # }
+    /// ```
+    pub fn to_problem_details(&self) -> ProblemDetails {
+        let status = self.status();
+        let mut detail = String::new();
+        self.write(
+            &mut detail,
+            &DisplayOptions::default(),
+            1,
+            ReportStyle::Short,
+        )
+        .ok();
+        ProblemDetails {
+            type_uri: self.url().unwrap_or("about:blank").to_owned(),
+            title: reason_phrase(status).to_owned(),
+            status,
+            detail,
+        }
+    }
+}
+
+/// Reason phrase of HTTP status code or of its class if not known
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        410 => "Gone",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        422 => "Unprocessable Entity",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ if status < 500 => "Client Error",
+        _ => "Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate serde_json;
+
+    use crate::prelude::*;
+    use crate::ProblemKind;
+
+    #[test]
+    fn round_trip() {
+        let problem = Problem::from_error("boom!")
+            .problem_while("processing job 42")
+            .with_exit_code(3)
+            .with_tag("jobs")
+            .with_kind(ProblemKind::USER)
+            .with_section("Job", "id=42");
+
+        let json = serde_json::to_value(&problem).unwrap();
+        assert_eq!(json["schema"], 1);
+        assert_eq!(json["messages"], serde_json::json!(["boom!"]));
+        assert_eq!(json["contexts"], serde_json::json!(["processing job 42"]));
+        assert!(json.get("url").is_none());
+        assert_eq!(json["kind"], "user");
+        assert!(json["sections"][0].get("visibility").is_none());
+
+        let decoded: Problem = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.to_string(), problem.to_string());
+        assert_eq!(decoded.exit_code(), Some(3));
+        assert!(decoded.has_tag("jobs"));
+        assert_eq!(decoded.kind(), Some(ProblemKind::USER));
+        assert_eq!(decoded.sections().collect::<Vec<_>>(), [("Job", "id=42")]);
+    }
+
+    #[test]
+    fn problem_details() {
+        let details = Problem::from_error("boom!").to_problem_details();
+        assert_eq!(details.type_uri, "about:blank");
+        assert_eq!(details.title, "Internal Server Error");
+        assert_eq!(details.status, 500);
+        assert_eq!(details.detail, "boom!");
+
+        let details = Problem::from_error("slow down")
+            .with_status(420)
+            .to_problem_details();
+        assert_eq!(details.title, "Client Error");
+        assert_eq!(
+            serde_json::to_value(&details).unwrap()["type"],
+            "about:blank"
+        );
+    }
+
+    #[test]
+    fn tolerant() {
+        let problem: Problem = serde_json::from_str(
+            r#"{"messages": ["disk full"], "host": "db1", "spans": [{"name": "write"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(problem.to_string(), "disk full");
+        assert_eq!(problem.exit_code(), None);
+
+        let problem: Problem = serde_json::from_str(r#"{"schema": 2}"#).unwrap();
+        assert_eq!(problem.to_string(), "");
+    }
+}
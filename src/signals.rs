@@ -0,0 +1,91 @@
+//! Reporting of termination signals with `in_context_of` context stacks of running threads.
+use super::{all_context_stacks, run_fatal_cleanup, set_context_stack, Problem, ProblemWhile};
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+use std::fmt::Write;
+
+/// Install `SIGINT` and `SIGTERM` handlers that print context stack of every thread currently executing
+/// `in_context_of` closure to `stderr` and exit the program
+///
+/// Context stacks are enabled (see `set_context_stack`). Cleanup functions registered with `on_fatal` are called before exiting
+/// with status `128 + signal number`.
+///
+/// Example output:
+/// ```noformat
+/// Interrupted while: syncing repo, while: downloading chunk 85
+/// ```
+pub fn report_on_signal() -> Result<(), Problem> {
+    let mut signals =
+        Signals::new([SIGINT, SIGTERM]).problem_while("installing signal handlers")?;
+    set_context_stack(true);
+
+    std::thread::Builder::new()
+        .name("problem-signals".to_owned())
+        .spawn(move || {
+            if let Some(signal) = signals.forever().next() {
+                eprint!("{}", format_interrupted(all_context_stacks()));
+                run_fatal_cleanup();
+                std::process::exit(128 + signal);
+            }
+        })
+        .problem_while("spawning signal handling thread")?;
+
+    Ok(())
+}
+
+fn format_interrupted(stacks: Vec<(Option<String>, Vec<String>)>) -> String {
+    let stacks: Vec<_> = stacks
+        .into_iter()
+        .filter(|(_, stack)| !stack.is_empty())
+        .collect();
+
+    if stacks.is_empty() {
+        return "Interrupted\n".to_owned();
+    }
+
+    let mut message = String::new();
+    for (name, stack) in &stacks {
+        message.push_str("Interrupted");
+        if stacks.len() > 1 {
+            write!(
+                message,
+                " thread '{}'",
+                name.as_deref().unwrap_or("<unnamed>")
+            )
+            .unwrap();
+        }
+        for context in stack {
+            write!(message, " while: {},", context).unwrap();
+        }
+        message.pop();
+        message.push('\n');
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_interrupted() {
+        assert_eq!(format_interrupted(vec![]), "Interrupted\n");
+        assert_eq!(
+            format_interrupted(vec![
+                (
+                    Some("main".to_owned()),
+                    vec!["syncing repo".to_owned(), "downloading chunk 85".to_owned()]
+                ),
+                (None, vec![]),
+            ]),
+            "Interrupted while: syncing repo, while: downloading chunk 85\n"
+        );
+        assert_eq!(
+            format_interrupted(vec![
+                (Some("main".to_owned()), vec!["syncing repo".to_owned()]),
+                (None, vec!["idling".to_owned()]),
+            ]),
+            "Interrupted thread 'main' while: syncing repo\nInterrupted thread '<unnamed>' while: idling\n"
+        );
+    }
+}
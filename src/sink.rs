@@ -0,0 +1,835 @@
+//! Reporting of `Problem`s to pluggable backends.
+//!
+//! `ProblemSink` receives `Problem`s that program decided to continue after (e.g. with `ok_or_log_warn`). This crate provides
+//! sinks for `log` crate (`LogSink`), `stderr` (`StderrSink`) and `io::Write` (`WriteSink`); other backends can be plugged
+//! in by implementing `ProblemSink` and used with `ok_or_report` extensions on `Result` and iterators of `Result`.
+//!
+//! ```rust
+//! use problem::prelude::*;
+//! use problem::sink::{ProblemSink, Severity};
+//!
+//! #[derive(Default)]
+//! struct Collect(Vec<String>);
+//!
+//! impl ProblemSink for Collect {
+//!     fn report(&mut self, severity: Severity, problem: &Problem) {
+//!         self.0.push(format!("{:?}: {}", severity, problem.brief()));
+//!     }
+//! }
+//!
+//! let mut sink = Collect::default();
+//! let ok: Vec<u32> = vec![Ok(1), Err("oops"), Ok(2)]
+//!     .into_iter()
+//!     .ok_or_report(&mut sink, Severity::Warning)
+//!     .flatten()
+//!     .collect();
+//!
+//! assert_eq!(ok, [1, 2]);
+//! assert_eq!(sink.0, ["Warning: oops"]);
+//! ```
+use super::{health, Problem, ProblemCounter, ProblemError, ProblemWhile, ToProblem};
+use std::cell::RefCell;
+use std::io;
+use std::iter::FusedIterator;
+use std::panic::Location;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+
+/// Severity of reported `Problem`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// Program can continue normally
+    Warning,
+    /// Program can continue but some of its work failed
+    Error,
+}
+
+/// Backend receiving `Problem`s that program continues after
+///
+/// Reported `Problem`s are counted in `ProblemCounter::global()` counter before they are passed to the sink.
+pub trait ProblemSink {
+    /// Report given `Problem`
+    fn report(&mut self, severity: Severity, problem: &Problem);
+
+    /// Returns `false` if `Problem`s of given severity would be discarded by this sink (e.g. log level is disabled)
+    ///
+    /// Errors are then not converted to `Problem` nor formatted; they are still counted. Errors are always reported in strict
+    /// mode, when recorded with `record_problems` or tracked with `track_last_problem`.
+    fn is_enabled(&self, _severity: Severity) -> bool {
+        true
+    }
+
+    /// Returns `true` if this sink only logs or prints `Problem`s so reporting to it panics in strict mode (see `strict_mode`)
+    ///
+    /// Sinks that hand `Problem`s over for further handling (e.g. `ChannelSink`) keep the default `false`.
+    fn panics_in_strict_mode(&self) -> bool {
+        false
+    }
+}
+
+impl<S: ProblemSink + ?Sized> ProblemSink for &mut S {
+    fn report(&mut self, severity: Severity, problem: &Problem) {
+        (**self).report(severity, problem)
+    }
+
+    fn is_enabled(&self, severity: Severity) -> bool {
+        (**self).is_enabled(severity)
+    }
+
+    fn panics_in_strict_mode(&self) -> bool {
+        (**self).panics_in_strict_mode()
+    }
+}
+
+impl<S: ProblemSink + ?Sized> ProblemSink for Box<S> {
+    fn report(&mut self, severity: Severity, problem: &Problem) {
+        (**self).report(severity, problem)
+    }
+
+    fn is_enabled(&self, severity: Severity) -> bool {
+        (**self).is_enabled(severity)
+    }
+
+    fn panics_in_strict_mode(&self) -> bool {
+        (**self).panics_in_strict_mode()
+    }
+}
+
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// Enable strict mode in which reporting `Problem` to a logging or printing sink (e.g. with `ok_or_log_warn` or
+/// `ok_or_print_error`) panics instead
+///
+/// This is useful in tests and debug builds to surface errors that would be otherwise logged and swallowed. Sinks that
+/// handle reported `Problem`s (e.g. `ChannelSink` or custom sinks) are not affected; see
+/// `ProblemSink::panics_in_strict_mode`.
+///
+/// ```rust
+/// problem::strict_mode(cfg!(debug_assertions));
+/// ```
+pub fn strict_mode(enabled: bool) {
+    STRICT.store(enabled, Ordering::Relaxed)
+}
+
+/// Count `Problem` in global counter and report it to given sink
+#[cfg(feature = "log")]
+pub(crate) fn report(sink: &mut impl ProblemSink, severity: Severity, problem: &Problem) {
+    let strict = is_strict(sink);
+    report_strict(sink, severity, problem, strict)
+}
+
+/// Returns `true` if strict mode is enabled and applies to given sink
+fn is_strict(sink: &impl ProblemSink) -> bool {
+    sink.panics_in_strict_mode() && STRICT.load(Ordering::Relaxed)
+}
+
+/// Returns `true` if `Problem` of given severity needs to be reported: sink is enabled for the severity or the `Problem` is
+/// observed otherwise (strict mode, recording or last problem tracking)
+pub(crate) fn is_reported(sink: &impl ProblemSink, severity: Severity) -> bool {
+    is_strict(sink)
+        || sink.is_enabled(severity)
+        || health::is_tracking()
+        || RECORDED.with(|recorded| recorded.borrow().is_some())
+}
+
+/// Report `Problem` converted from given error if it needs to be reported (see `is_reported`); otherwise only count it
+pub(crate) fn report_error<E: ToProblem>(
+    sink: &mut impl ProblemSink,
+    severity: Severity,
+    error: E,
+) {
+    if is_reported(sink, severity) {
+        let strict = is_strict(sink);
+        report_strict(sink, severity, &error.into_problem(), strict)
+    } else {
+        count(severity)
+    }
+}
+
+/// Count `Problem` in global counter without reporting it
+pub(crate) fn count(severity: Severity) {
+    match severity {
+        Severity::Error => ProblemCounter::global().count_error(),
+        Severity::Warning => ProblemCounter::global().count_warning(),
+    }
+}
+
+fn report_strict(sink: &mut impl ProblemSink, severity: Severity, problem: &Problem, strict: bool) {
+    count(severity);
+    RECORDED.with(|recorded| {
+        if let Some(recorded) = recorded.borrow_mut().as_mut() {
+            recorded.push((severity, problem.report()));
+        }
+    });
+    health::problem_reported(severity, problem);
+    if strict {
+        panic!(
+            "{:?} reported in strict mode: {}",
+            severity,
+            problem.report()
+        );
+    }
+    sink.report(severity, problem)
+}
+
+/// Logs `Problem`s with `log` crate macros (see `logged::set_log_format`)
+#[cfg(feature = "log")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LogSink;
+
+#[cfg(feature = "log")]
+impl LogSink {
+    fn level(severity: Severity) -> log::Level {
+        match severity {
+            Severity::Warning => log::Level::Warn,
+            Severity::Error => log::Level::Error,
+        }
+    }
+}
+
+#[cfg(feature = "log")]
+impl ProblemSink for LogSink {
+    fn report(&mut self, severity: Severity, problem: &Problem) {
+        super::logged::log_problem(LogSink::level(severity), problem)
+    }
+
+    fn is_enabled(&self, severity: Severity) -> bool {
+        super::logged::is_log_enabled(LogSink::level(severity))
+    }
+
+    fn panics_in_strict_mode(&self) -> bool {
+        true
+    }
+}
+
+/// Prints `Problem`s to `stderr` prefixed with `WARNING:` or `ERROR:`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StderrSink;
+
+impl ProblemSink for StderrSink {
+    fn report(&mut self, severity: Severity, problem: &Problem) {
+        write_prefixed(&mut io::stderr().lock(), severity, problem).ok();
+    }
+
+    fn panics_in_strict_mode(&self) -> bool {
+        true
+    }
+}
+
+fn write_prefixed(w: &mut impl io::Write, severity: Severity, problem: &Problem) -> io::Result<()> {
+    let prefix = match severity {
+        Severity::Warning => "WARNING",
+        Severity::Error => "ERROR",
+    };
+    writeln!(w, "{}: {}", prefix, problem.brief())
+}
+
+/// Writes `Problem`s as lines into `io::Write` (e.g. `errors.txt` file); failures to write are ignored
+#[derive(Debug)]
+pub struct WriteSink<W>(pub W);
+
+impl<W: io::Write> ProblemSink for WriteSink<W> {
+    fn report(&mut self, _severity: Severity, problem: &Problem) {
+        writeln!(self.0, "{}", problem.brief()).ok();
+    }
+
+    fn panics_in_strict_mode(&self) -> bool {
+        true
+    }
+}
+
+/// Sending half of a channel that `Problem`s can be forwarded to as `ProblemError`s (e.g. `mpsc::Sender<ProblemError>`)
+pub trait ProblemSender {
+    /// Send given `Problem`; failures (e.g. disconnected receiver) are ignored
+    fn send_problem(&self, problem: ProblemError);
+}
+
+impl<S: ProblemSender + ?Sized> ProblemSender for &S {
+    fn send_problem(&self, problem: ProblemError) {
+        (**self).send_problem(problem)
+    }
+}
+
+impl ProblemSender for mpsc::Sender<ProblemError> {
+    fn send_problem(&self, problem: ProblemError) {
+        self.send(problem).ok();
+    }
+}
+
+/// Blocks if the channel is full
+impl ProblemSender for mpsc::SyncSender<ProblemError> {
+    fn send_problem(&self, problem: ProblemError) {
+        self.send(problem).ok();
+    }
+}
+
+/// Blocks if the channel is full
+#[cfg(feature = "crossbeam")]
+impl ProblemSender for crossbeam_channel::Sender<ProblemError> {
+    fn send_problem(&self, problem: ProblemError) {
+        self.send(problem).ok();
+    }
+}
+
+/// Forwards `Problem`s to a channel (see `ProblemSender`) so they can be handled by a dedicated consumer (e.g. thread)
+#[derive(Debug)]
+pub struct ChannelSink<S>(pub S);
+
+impl<S: ProblemSender> ProblemSink for ChannelSink<S> {
+    fn report(&mut self, _severity: Severity, problem: &Problem) {
+        self.0.send_problem(problem.into())
+    }
+}
+
+/// Extension of `Result` that allows program to report `Problem` to given sink on `Err` and continue
+pub trait OkOrReport<O> {
+    fn ok_or_report(self, sink: impl ProblemSink, severity: Severity) -> Option<O>;
+}
+
+impl<O, E> OkOrReport<O> for Result<O, E>
+where
+    E: ToProblem,
+{
+    fn ok_or_report(self, mut sink: impl ProblemSink, severity: Severity) -> Option<O> {
+        self.map_err(|err| report_error(&mut sink, severity, err))
+            .ok()
+    }
+}
+
+/// Iterator that will report `Problem` to a sink on `Err` and skip to next item; it can be flattened to skip failed items
+///
+/// Errors are converted to `Problem` only when they are reported (see `ProblemSink::is_enabled`).
+pub struct ProblemReportingIter<I, S> {
+    inner: I,
+    sink: S,
+    severity: Severity,
+}
+
+impl<I, S> ProblemReportingIter<I, S> {
+    fn ok_or_report<O, E>(&mut self, result: Result<O, E>) -> Option<O>
+    where
+        E: ToProblem,
+        S: ProblemSink,
+    {
+        result
+            .map_err(|err| report_error(&mut self.sink, self.severity, err))
+            .ok()
+    }
+}
+
+impl<I, O, E, S> Iterator for ProblemReportingIter<I, S>
+where
+    I: Iterator<Item = Result<O, E>>,
+    E: ToProblem,
+    S: ProblemSink,
+{
+    type Item = Option<O>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.inner.next()?;
+        Some(self.ok_or_report(result))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<I, O, E, S> DoubleEndedIterator for ProblemReportingIter<I, S>
+where
+    I: DoubleEndedIterator<Item = Result<O, E>>,
+    E: ToProblem,
+    S: ProblemSink,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let result = self.inner.next_back()?;
+        Some(self.ok_or_report(result))
+    }
+}
+
+impl<I, O, E, S> ExactSizeIterator for ProblemReportingIter<I, S>
+where
+    I: ExactSizeIterator<Item = Result<O, E>>,
+    E: ToProblem,
+    S: ProblemSink,
+{
+}
+
+impl<I, O, E, S> FusedIterator for ProblemReportingIter<I, S>
+where
+    I: FusedIterator<Item = Result<O, E>>,
+    E: ToProblem,
+    S: ProblemSink,
+{
+}
+
+/// Define iterator type wrapping `ProblemReportingIter` for given sink type
+macro_rules! reporting_iter {
+    ($(#[$meta:meta])* pub struct $name:ident<$($lt:lifetime,)* I $(, $param:ident)*>($sink:ty);) => {
+        $(#[$meta])*
+        pub struct $name<$($lt,)* I $(, $param)*>($crate::sink::ProblemReportingIter<I, $sink>);
+
+        impl<$($lt,)* I $(, $param)*> Iterator for $name<$($lt,)* I $(, $param)*>
+        where
+            $crate::sink::ProblemReportingIter<I, $sink>: Iterator,
+        {
+            type Item = <$crate::sink::ProblemReportingIter<I, $sink> as Iterator>::Item;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                self.0.next()
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.0.size_hint()
+            }
+        }
+
+        impl<$($lt,)* I $(, $param)*> DoubleEndedIterator for $name<$($lt,)* I $(, $param)*>
+        where
+            $crate::sink::ProblemReportingIter<I, $sink>: DoubleEndedIterator,
+        {
+            fn next_back(&mut self) -> Option<Self::Item> {
+                self.0.next_back()
+            }
+        }
+
+        impl<$($lt,)* I $(, $param)*> ExactSizeIterator for $name<$($lt,)* I $(, $param)*>
+        where
+            $crate::sink::ProblemReportingIter<I, $sink>: ExactSizeIterator,
+        {
+        }
+
+        impl<$($lt,)* I $(, $param)*> ::std::iter::FusedIterator for $name<$($lt,)* I $(, $param)*>
+        where
+            $crate::sink::ProblemReportingIter<I, $sink>: ::std::iter::FusedIterator,
+        {
+        }
+    };
+}
+
+/// Convert `Iterator` of `Result<O, E>` to iterator of `Option<O>` and report any `Err` variants to given sink
+pub trait OkOrReportIter<O, E>: Sized {
+    fn ok_or_report<S: ProblemSink>(
+        self,
+        sink: S,
+        severity: Severity,
+    ) -> ProblemReportingIter<Self, S>;
+}
+
+impl<I, O, E> OkOrReportIter<O, E> for I
+where
+    I: Iterator<Item = Result<O, E>>,
+    E: ToProblem,
+{
+    fn ok_or_report<S: ProblemSink>(
+        self,
+        sink: S,
+        severity: Severity,
+    ) -> ProblemReportingIter<Self, S> {
+        ProblemReportingIter {
+            inner: self,
+            sink,
+            severity,
+        }
+    }
+}
+
+/// Extension of `Result` that allows program to forward `Problem` to a channel on `Err` and continue
+///
+/// ```rust
+/// use problem::prelude::*;
+/// use problem::ProblemError;
+/// use std::sync::mpsc;
+/// use std::thread;
+///
+/// let (sender, receiver) = mpsc::channel::<ProblemError>();
+/// let consumer = thread::spawn(move || receiver.iter().map(|problem| problem.to_string()).collect::<Vec<_>>());
+///
+/// let ports: Vec<u16> = vec!["80", "80x", "443"]
+///     .into_iter()
+///     .map(|port| port.parse::<u16>())
+///     .ok_or_send(&sender)
+///     .flatten()
+///     .collect();
+/// drop(sender);
+///
+/// assert_eq!(ports, [80, 443]);
+/// assert_eq!(consumer.join().unwrap(), ["invalid digit found in string"]);
+/// ```
+pub trait OkOrSend<O> {
+    fn ok_or_send<S: ProblemSender>(self, sender: &S) -> Option<O>;
+}
+
+impl<O, E> OkOrSend<O> for Result<O, E>
+where
+    E: ToProblem,
+{
+    fn ok_or_send<S: ProblemSender>(self, sender: &S) -> Option<O> {
+        self.ok_or_report(ChannelSink(sender), Severity::Error)
+    }
+}
+
+reporting_iter! {
+    /// Iterator that will forward `Problem` to a channel on `Err` and skip to next item; it can be flattened to skip failed items
+    pub struct ProblemSendingIter<'s, I, S>(ChannelSink<&'s S>);
+}
+
+/// Convert `Iterator` of `Result<O, E>` to iterator of `Option<O>` and forward any `Err` variants to a channel
+pub trait OkOrSendIter<O, E>: Sized {
+    fn ok_or_send<S: ProblemSender>(self, sender: &S) -> ProblemSendingIter<'_, Self, S>;
+}
+
+impl<I, O, E> OkOrSendIter<O, E> for I
+where
+    I: Iterator<Item = Result<O, E>>,
+    E: ToProblem,
+{
+    fn ok_or_send<S: ProblemSender>(self, sender: &S) -> ProblemSendingIter<'_, Self, S> {
+        ProblemSendingIter(self.ok_or_report(ChannelSink(sender), Severity::Error))
+    }
+}
+
+thread_local! {
+    static RECORDED: RefCell<Option<Vec<(Severity, String)>>> = const { RefCell::new(None) };
+}
+
+/// Start recording `Problem`s reported to sinks on current thread (e.g. with `ok_or_log_warn`)
+///
+/// Recording stops when returned `ProblemRecorder` is dropped. This can be used in tests as runtime analogue of
+/// `-D warnings` for error handling.
+///
+/// ```rust
+/// use problem::prelude::*;
+///
+/// let recorder = problem::sink::record_problems();
+/// let port = "80".parse::<u16>().ok_or_print_warn();
+/// recorder.no_problems_logged();
+/// ```
+pub fn record_problems() -> ProblemRecorder {
+    ProblemRecorder {
+        outer: RECORDED.with(|recorded| recorded.borrow_mut().replace(Vec::new())),
+    }
+}
+
+/// Records `Problem`s reported to sinks on current thread until dropped (see `record_problems`)
+///
+/// Recorded `Problem`s are also passed to outer recorder if any.
+#[derive(Debug)]
+#[must_use]
+pub struct ProblemRecorder {
+    outer: Option<Vec<(Severity, String)>>,
+}
+
+impl ProblemRecorder {
+    /// Get severity and long report of each `Problem` recorded so far
+    pub fn problems(&self) -> Vec<(Severity, String)> {
+        RECORDED.with(|recorded| recorded.borrow().clone().unwrap_or_default())
+    }
+
+    /// Panic with reports of recorded `Problem`s if any was recorded
+    #[track_caller]
+    pub fn no_problems_logged(self) {
+        let problems = self.problems();
+        if !problems.is_empty() {
+            let mut message = format!("{} problem(s) logged:", problems.len());
+            for (severity, report) in problems {
+                message.push_str(&format!("\n{:?}: {}", severity, report));
+            }
+            panic!("{}", message);
+        }
+    }
+}
+
+impl Drop for ProblemRecorder {
+    fn drop(&mut self) {
+        RECORDED.with(|recorded| {
+            let mut recorded = recorded.borrow_mut();
+            let problems = recorded.take().unwrap_or_default();
+            *recorded = self.outer.take().map(|mut outer| {
+                outer.extend(problems);
+                outer
+            });
+        })
+    }
+}
+
+type DiscardedSink = Arc<Mutex<dyn ProblemSink + Send>>;
+
+static DISCARDED_SINK: Mutex<Option<DiscardedSink>> = Mutex::new(None);
+
+/// Set global sink receiving `Problem`s discarded with `ok_discarded` (none by default)
+///
+/// Discarded `Problem`s are reported with `Severity::Warning` and context message pointing at the place where
+/// `ok_discarded` was called, so that intentionally ignored errors can be audited in production.
+///
+/// ```rust
+/// use problem::prelude::*;
+/// use problem::sink::StderrSink;
+///
+/// problem::sink::set_discarded_sink(StderrSink);
+///
+/// // Prints: WARNING: while discarding at src/main.rs:8:37 got error caused by: invalid digit found in string
+/// let cache_size = "12x".parse::<u32>().ok_discarded();
+/// assert!(cache_size.is_none());
+/// ```
+pub fn set_discarded_sink(sink: impl ProblemSink + Send + 'static) {
+    *DISCARDED_SINK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(Arc::new(Mutex::new(sink)));
+}
+
+/// Remove global sink set with `set_discarded_sink`
+pub fn clear_discarded_sink() {
+    *DISCARDED_SINK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+}
+
+/// Count `Problem` as suppressed and report it to given sink with location where it was discarded
+fn discard(sink: &mut (impl ProblemSink + ?Sized), problem: Problem, location: &Location) {
+    ProblemCounter::global().count_suppressed();
+    sink.report(
+        Severity::Warning,
+        &problem.problem_while(format!("discarding at {}", location)),
+    )
+}
+
+/// Extension of `Result` that allows to ignore `Err` like `Result::ok` while reporting it to global discarded errors sink
+/// (see `set_discarded_sink`)
+pub trait OkDiscarded<O> {
+    fn ok_discarded(self) -> Option<O>;
+}
+
+impl<O, E> OkDiscarded<O> for Result<O, E>
+where
+    E: ToProblem,
+{
+    #[track_caller]
+    fn ok_discarded(self) -> Option<O> {
+        match self {
+            Ok(ok) => Some(ok),
+            Err(err) => {
+                let location = Location::caller();
+                // Global lock is released before the error is converted and reported so that neither can deadlock on it
+                let sink = DISCARDED_SINK
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .clone();
+                match sink {
+                    Some(sink) => {
+                        let problem = err.into_problem();
+                        let mut sink = sink.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                        discard(&mut *sink, problem, location)
+                    }
+                    None => ProblemCounter::global().count_suppressed(),
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn send() {
+        crate::disable_backtraces();
+        let (sender, receiver) = mpsc::sync_channel(4);
+        assert_eq!(Ok::<_, Problem>(1).ok_or_send(&sender), Some(1));
+        assert_eq!(
+            Err::<u32, _>(Problem::from_error("boom!").with_exit_code(3)).ok_or_send(&sender),
+            None
+        );
+        drop(sender);
+
+        let problems = receiver
+            .iter()
+            .map(ProblemError::into_problem)
+            .collect::<Vec<_>>();
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].to_string(), "boom!");
+        assert_eq!(problems[0].exit_code(), Some(3));
+    }
+
+    #[test]
+    fn disabled_sink() {
+        use std::cell::Cell;
+
+        struct Disabled(Vec<Severity>);
+
+        impl ProblemSink for Disabled {
+            fn report(&mut self, severity: Severity, _problem: &Problem) {
+                self.0.push(severity)
+            }
+
+            fn is_enabled(&self, severity: Severity) -> bool {
+                severity == Severity::Error
+            }
+        }
+
+        struct Costly<'c>(&'c Cell<usize>);
+
+        impl ToProblem for Costly<'_> {
+            fn into_problem(self) -> Problem {
+                self.0.set(self.0.get() + 1);
+                Problem::from_error("costly")
+            }
+        }
+
+        let conversions = Cell::new(0);
+        let mut sink = Disabled(Vec::new());
+        assert_eq!(
+            Err::<(), _>(Costly(&conversions)).ok_or_report(&mut sink, Severity::Warning),
+            None
+        );
+        assert_eq!(conversions.get(), 0);
+
+        let recorder = record_problems();
+        let _ = Err::<(), _>(Costly(&conversions)).ok_or_report(&mut sink, Severity::Warning);
+        assert_eq!(recorder.problems().len(), 1);
+        drop(recorder);
+
+        let _ = vec![Err::<(), _>(Costly(&conversions))]
+            .into_iter()
+            .ok_or_report(&mut sink, Severity::Error)
+            .count();
+        assert_eq!(conversions.get(), 2);
+        assert_eq!(sink.0, [Severity::Warning, Severity::Error]);
+    }
+
+    #[test]
+    fn write_lines() {
+        let problem = Problem::from_error("boom!").problem_while("parsing input");
+        let mut out = Vec::new();
+        write_prefixed(&mut out, Severity::Warning, &problem).unwrap();
+        write_prefixed(&mut out, Severity::Error, &problem).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "WARNING: while parsing input got error caused by: boom!\nERROR: while parsing input got error caused by: boom!\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Warning reported in strict mode: while parsing input got error caused by: boom!"
+    )]
+    fn strict() {
+        let problem = Problem::from_error("boom!").problem_while("parsing input");
+        report_strict(&mut StderrSink, Severity::Warning, &problem, true);
+    }
+
+    #[test]
+    fn strict_sinks() {
+        let (sender, _receiver) = mpsc::channel();
+        assert!(StderrSink.panics_in_strict_mode());
+        assert!(Box::new(WriteSink(io::sink())).panics_in_strict_mode());
+        assert!(!ChannelSink(sender).panics_in_strict_mode());
+    }
+
+    #[test]
+    fn recording() {
+        crate::disable_backtraces();
+        let outer = record_problems();
+        {
+            let inner = record_problems();
+            Err::<(), _>("boom!").ok_or_report(WriteSink(io::sink()), Severity::Error);
+            assert_eq!(inner.problems(), [(Severity::Error, "boom!".to_owned())]);
+        }
+        assert_eq!(outer.problems().len(), 1);
+        Err::<(), _>("oops").ok_or_report(WriteSink(io::sink()), Severity::Warning);
+        assert_eq!(outer.problems().len(), 2);
+        drop(outer);
+
+        let recorder = record_problems();
+        Ok::<_, &str>(()).ok_or_report(WriteSink(io::sink()), Severity::Warning);
+        recorder.no_problems_logged();
+    }
+
+    #[test]
+    #[should_panic(expected = "1 problem(s) logged:\nWarning: boom!")]
+    fn no_problems_logged() {
+        let recorder = record_problems();
+        Err::<(), _>("boom!").ok_or_report(WriteSink(io::sink()), Severity::Warning);
+        recorder.no_problems_logged();
+    }
+
+    #[test]
+    fn discard_location() {
+        let mut out = Vec::new();
+        let location = Location::caller();
+        discard(
+            &mut WriteSink(&mut out),
+            Problem::from_error("boom!"),
+            location,
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "while discarding at {}:{}:{} got error caused by: boom!\n",
+                location.file(),
+                location.line(),
+                location.column()
+            )
+        );
+    }
+
+    #[test]
+    fn discarded_sink() {
+        crate::disable_backtraces();
+        struct Nested;
+
+        impl ToProblem for Nested {
+            fn into_problem(self) -> Problem {
+                assert_eq!("x".parse::<u32>().ok_discarded(), None);
+                Problem::from_error("nested")
+            }
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        set_discarded_sink(ChannelSink(sender));
+        assert_eq!(Err::<(), _>(Nested).ok_discarded(), None);
+        clear_discarded_sink();
+
+        let reported: Vec<String> = receiver
+            .try_iter()
+            .map(|problem| problem.into_problem().to_string())
+            .collect();
+        assert_eq!(reported.len(), 2);
+        assert!(reported[0].ends_with("got error caused by: invalid digit found in string"));
+        assert!(reported[1].ends_with("got error caused by: nested"));
+    }
+
+    #[test]
+    fn write_sink() {
+        let problem = Problem::from_error("boom!").problem_while("parsing input");
+        let mut out = Vec::new();
+        WriteSink(&mut out).report(Severity::Warning, &problem);
+        WriteSink(&mut out).report(Severity::Error, &problem);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "while parsing input got error caused by: boom!\nwhile parsing input got error caused by: boom!\n"
+        );
+    }
+
+    #[test]
+    fn report_iter() {
+        let mut reported = Vec::new();
+        {
+            let mut sink = WriteSink(&mut reported);
+            let ok: Vec<u32> = vec![Ok(1u32), Err("oops"), Ok(2), Err("oh")]
+                .into_iter()
+                .rev()
+                .ok_or_report(&mut sink, Severity::Error)
+                .flatten()
+                .collect();
+            assert_eq!(ok, [2, 1]);
+            assert!(Err::<(), _>("boom!")
+                .ok_or_report(&mut sink, Severity::Warning)
+                .is_none());
+        }
+        assert_eq!(String::from_utf8(reported).unwrap(), "oh\noops\nboom!\n");
+    }
+}
@@ -0,0 +1,249 @@
+//! Helpers for integration testing of programs reporting `Problem`s.
+//!
+//! `fatal_report_matcher` returns predicate (see `predicates` crate) matching fatal error report printed by panic hooks of
+//! this crate or `FatalProblem` on standard error. It can be used with `assert_cmd` crate:
+//!
+//! ```rust,ignore
+//! use assert_cmd::Command;
+//! use problem::test::fatal_report_matcher;
+//!
+//! Command::cargo_bin("myapp").unwrap()
+//!     .arg("--config").arg("missing.toml")
+//!     .assert()
+//!     .failure()
+//!     .stderr(fatal_report_matcher("Failed to load config").context("reading missing.toml").cause("No such file"));
+//! ```
+//!
+//! For property testing of code handling `Problem`s, `Problem` implements `arbitrary::Arbitrary` and `problem_strategy` provides
+//! `proptest` strategy. Generated `Problem`s have random cause chains, context messages and attachments (URL and sections).
+//!
+//! ```rust
+//! # extern crate proptest;
+//! use problem::prelude::*;
+//! use proptest::prelude::*;
+//!
+//! proptest!(|(problem in problem::test::problem_strategy())| {
+//!     prop_assert!(problem.to_string().contains(problem.contexts().next().unwrap_or("")));
+//! });
+//! ```
+use super::{ErrorMessage, Problem};
+use arbitrary::{Arbitrary, Unstructured};
+use predicates_core::reflection::PredicateReflection;
+use predicates_core::Predicate;
+use proptest::collection::vec;
+use proptest::option;
+use proptest::strategy::Strategy;
+use std::fmt;
+
+/// Maximum number of generated context messages
+const MAX_CONTEXTS: usize = 5;
+/// Maximum number of generated causes of the error
+const MAX_CAUSES: usize = 3;
+/// Maximum number of generated sections
+const MAX_SECTIONS: usize = 2;
+
+fn build_problem(
+    messages: Vec<String>,
+    contexts: Vec<String>,
+    url: Option<String>,
+    sections: Vec<(String, String)>,
+) -> Problem {
+    let error = ErrorMessage::from_chain(messages).unwrap_or_else(|| ErrorMessage::new("error"));
+    let mut problem = Problem::from_parts(error, contexts, None);
+    if let Some(url) = url {
+        problem = problem.with_url(url);
+    }
+    for (title, body) in sections {
+        problem = problem.with_section(title, body);
+    }
+    problem
+}
+
+impl<'a> Arbitrary<'a> for Problem {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Problem> {
+        let causes = u.int_in_range(0..=MAX_CAUSES)?;
+        let messages = (0..=causes)
+            .map(|_| u.arbitrary())
+            .collect::<arbitrary::Result<_>>()?;
+        let contexts = u.int_in_range(0..=MAX_CONTEXTS)?;
+        let contexts = (0..contexts)
+            .map(|_| u.arbitrary())
+            .collect::<arbitrary::Result<_>>()?;
+        let url = u.arbitrary()?;
+        let sections = u.int_in_range(0..=MAX_SECTIONS)?;
+        let sections = (0..sections)
+            .map(|_| u.arbitrary())
+            .collect::<arbitrary::Result<_>>()?;
+        Ok(build_problem(messages, contexts, url, sections))
+    }
+}
+
+/// `proptest` strategy generating `Problem`s with random cause chains, context messages and attachments
+pub fn problem_strategy() -> impl Strategy<Value = Problem> {
+    // report parser trims messages so they should not start or end with spaces
+    let message = "[a-zA-Z0-9]([a-zA-Z0-9 ]{0,18}[a-zA-Z0-9])?";
+    (
+        vec(message, 1..=MAX_CAUSES + 1),
+        vec(message, 0..=MAX_CONTEXTS),
+        option::of("https://example\\.com/[a-z]{1,10}"),
+        vec((message, "[a-zA-Z0-9 =\n]{0,40}"), 0..=MAX_SECTIONS),
+    )
+        .prop_map(|(messages, contexts, url, sections)| {
+            build_problem(messages, contexts, url, sections)
+        })
+}
+
+/// Predicate matching fatal error report layout of this crate; backtraces and other sections are ignored
+///
+/// Created with `fatal_report_matcher`.
+#[derive(Debug, Clone)]
+pub struct FatalReportMatcher {
+    message: String,
+    contexts: Vec<String>,
+    causes: Vec<String>,
+}
+
+/// Create predicate matching fatal error report with given message
+///
+/// The message is matched against `or_failed_to` message (with or without `Failed to ` prefix) or if the report does not
+/// come from `or_failed_to`, against error message and messages of its causes (as substring).
+pub fn fatal_report_matcher(message: impl Into<String>) -> FatalReportMatcher {
+    FatalReportMatcher {
+        message: message.into(),
+        contexts: Vec::new(),
+        causes: Vec::new(),
+    }
+}
+
+impl FatalReportMatcher {
+    /// Require context message equal to given one
+    pub fn context(mut self, context: impl Into<String>) -> FatalReportMatcher {
+        self.contexts.push(context.into());
+        self
+    }
+
+    /// Require error message or message of one of its causes to contain given text
+    pub fn cause(mut self, cause: impl Into<String>) -> FatalReportMatcher {
+        self.causes.push(cause.into());
+        self
+    }
+
+    fn matches(&self, output: &str) -> bool {
+        // report is printed last; skip any output preceding it
+        let report = match output.rfind("Fatal error: ") {
+            Some(at) => &output[at..],
+            None => output,
+        };
+        let remote = Problem::parse_report(report);
+        let has_cause = |text: &str| remote.causes().any(|cause| cause.contains(text));
+
+        let message_matches = match remote.failed_to() {
+            Some(failed_to) => {
+                let message = self.message.as_str();
+                failed_to == message.strip_prefix("Failed to ").unwrap_or(message)
+            }
+            None => has_cause(&self.message),
+        };
+
+        message_matches
+            && self
+                .contexts
+                .iter()
+                .all(|context| remote.contexts().any(|reported| reported == context))
+            && self.causes.iter().all(|cause| has_cause(cause))
+    }
+}
+
+impl fmt::Display for FatalReportMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "fatal report of {:?}", self.message)?;
+        for context in &self.contexts {
+            write!(f, " while {:?}", context)?;
+        }
+        for cause in &self.causes {
+            write!(f, " caused by {:?}", cause)?;
+        }
+        Ok(())
+    }
+}
+
+impl PredicateReflection for FatalReportMatcher {}
+
+impl Predicate<str> for FatalReportMatcher {
+    fn eval(&self, output: &str) -> bool {
+        self.matches(output)
+    }
+}
+
+impl Predicate<[u8]> for FatalReportMatcher {
+    fn eval(&self, output: &[u8]) -> bool {
+        self.matches(&String::from_utf8_lossy(output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PANIC_REPORT: &str = "starting up\n\
+        Fatal error: thread 'main' panicked at src/main.rs:12:5 with: Failed to load config due to: while reading missing.toml, while loading settings got error caused by: No such file or directory (os error 2)\n\
+        --- Cause\n   0: myapp::main at src/main.rs:10\n\
+        --- Panicked\n   0: myapp::main at src/main.rs:12";
+
+    #[test]
+    fn panic_report() {
+        assert!(fatal_report_matcher("Failed to load config").eval(PANIC_REPORT));
+        assert!(fatal_report_matcher("load config")
+            .context("reading missing.toml")
+            .context("loading settings")
+            .cause("No such file")
+            .eval(PANIC_REPORT.as_bytes()));
+        assert!(!fatal_report_matcher("Failed to load").eval(PANIC_REPORT));
+        assert!(!fatal_report_matcher("Failed to load config")
+            .context("reading other.toml")
+            .eval(PANIC_REPORT));
+        assert!(!fatal_report_matcher("Failed to load config")
+            .cause("main.rs")
+            .eval(PANIC_REPORT));
+    }
+
+    #[test]
+    fn fatal_problem_report() {
+        let report = "while reading missing.toml got error caused by: No such file or directory (os error 2)\n--- Environment\nHOME=/root";
+        assert!(fatal_report_matcher("No such file")
+            .context("reading missing.toml")
+            .eval(report));
+        assert!(!fatal_report_matcher("HOME").eval(report));
+    }
+
+    #[test]
+    fn arbitrary_problems() {
+        let bytes: Vec<u8> = (0..1024u32).map(|no| (no * 7919 % 251) as u8).collect();
+        let mut u = Unstructured::new(&bytes);
+        while !u.is_empty() {
+            let problem = Problem::arbitrary(&mut u).unwrap();
+            assert!(problem.contexts().len() <= MAX_CONTEXTS);
+            assert!(problem.sections().count() <= MAX_SECTIONS);
+        }
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn generated_problems_round_trip_reports(problem in problem_strategy()) {
+            let remote = Problem::parse_report(&problem.brief());
+            proptest::prop_assert_eq!(remote.contexts().collect::<Vec<_>>(), problem.contexts().collect::<Vec<_>>());
+            proptest::prop_assert!((1..=MAX_CAUSES + 1).contains(&remote.causes().len()));
+        }
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            fatal_report_matcher("Failed to load config")
+                .context("reading")
+                .cause("boom")
+                .to_string(),
+            "fatal report of \"Failed to load config\" while \"reading\" caused by \"boom\""
+        );
+    }
+}
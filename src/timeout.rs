@@ -0,0 +1,268 @@
+//! Running of operations with a time limit.
+use super::catch::run_compute;
+use super::{Problem, ProblemError, ProblemWhile};
+use std::io;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn timeout_problem(elapsed: Duration) -> Problem {
+    Problem::from_error(io::Error::new(
+        io::ErrorKind::TimedOut,
+        format!("timed out after {:.1?}", elapsed),
+    ))
+}
+
+/// Run closure on a separate thread and wait for its result at most given time
+///
+/// If the closure does not complete in time, `Problem` caused by `io::Error` of `TimedOut` kind with elapsed time is returned with
+/// given context message (e.g. `while waiting for lock got error caused by: timed out after 5.0s`). Note that the closure is
+/// not interrupted and its thread keeps running in the background. If the closure panics, the panic is converted to `Problem`
+/// with `Problem::from_panic_payload` (see `spawn_compute`).
+///
+/// ```rust
+/// use problem::with_timeout;
+/// use std::time::Duration;
+///
+/// assert_eq!(with_timeout(Duration::from_secs(5), "adding numbers", || 2 + 2).unwrap(), 4);
+///
+/// let problem = with_timeout(Duration::from_millis(10), "waiting for lock", || {
+///     std::thread::sleep(Duration::from_secs(1))
+/// }).unwrap_err();
+/// assert!(problem.to_string().starts_with("while waiting for lock got error caused by: timed out after"));
+/// ```
+pub fn with_timeout<T, F>(timeout: Duration, message: impl ToString, body: F) -> Result<T, Problem>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let start = Instant::now();
+    let (sender, receiver) = mpsc::sync_channel(1);
+    thread::Builder::new()
+        .name("problem-timeout".to_owned())
+        .spawn(move || {
+            sender.send(run_compute(|| Ok::<_, Problem>(body()))).ok();
+        })
+        .problem_while_with(|| message.to_string())?;
+
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => result
+            .map_err(ProblemError::into_problem)
+            .problem_while_with(|| message.to_string()),
+        Err(RecvTimeoutError::Timeout) => {
+            Err(timeout_problem(start.elapsed())).problem_while_with(|| message.to_string())
+        }
+        Err(RecvTimeoutError::Disconnected) => Err(Problem::from_error("operation panicked"))
+            .problem_while_with(|| message.to_string()),
+    }
+}
+
+#[cfg(feature = "futures")]
+pub use self::future::{with_timeout_async, Timeout};
+
+#[cfg(feature = "futures")]
+mod future {
+    use super::*;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Waker};
+
+    #[derive(Default)]
+    struct Timer {
+        expired: AtomicBool,
+        done: AtomicBool,
+        waker: Mutex<Option<Waker>>,
+    }
+
+    /// Future returned by `with_timeout_async`
+    pub struct Timeout<F, M> {
+        future: Pin<Box<F>>,
+        timeout: Duration,
+        message: M,
+        started: Option<(Instant, Arc<Timer>, thread::Thread)>,
+    }
+
+    // the inner future is pinned on the heap
+    impl<F, M> Unpin for Timeout<F, M> {}
+
+    impl<F, M> Future for Timeout<F, M>
+    where
+        F: Future,
+        M: ToString,
+    {
+        type Output = Result<F::Output, Problem>;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+            if self.started.is_none() {
+                let timer = Arc::<Timer>::default();
+                let timeout = self.timeout;
+                let thread = thread::Builder::new()
+                    .name("problem-timeout".to_owned())
+                    .spawn({
+                        let timer = timer.clone();
+                        move || {
+                            let deadline = Instant::now() + timeout;
+                            while !timer.done.load(Ordering::Acquire) {
+                                let now = Instant::now();
+                                if now >= deadline {
+                                    timer.expired.store(true, Ordering::Release);
+                                    if let Some(waker) = timer.waker.lock().unwrap().take() {
+                                        waker.wake()
+                                    }
+                                    break;
+                                }
+                                thread::park_timeout(deadline - now);
+                            }
+                        }
+                    });
+                match thread {
+                    Ok(thread) => {
+                        self.started = Some((Instant::now(), timer, thread.thread().clone()))
+                    }
+                    Err(err) => {
+                        return Poll::Ready(Err(err).problem_while(self.message.to_string()))
+                    }
+                }
+            }
+
+            let (start, timer) = match self.started.as_ref() {
+                Some((start, timer, _)) => (*start, timer.clone()),
+                None => unreachable!(),
+            };
+            *timer.waker.lock().unwrap() = Some(cx.waker().clone());
+
+            if let Poll::Ready(value) = self.future.as_mut().poll(cx) {
+                return Poll::Ready(Ok(value));
+            }
+            if timer.expired.load(Ordering::Acquire) {
+                return Poll::Ready(
+                    Err(timeout_problem(start.elapsed())).problem_while(self.message.to_string()),
+                );
+            }
+            Poll::Pending
+        }
+    }
+
+    impl<F, M> Drop for Timeout<F, M> {
+        fn drop(&mut self) {
+            if let Some((_, timer, thread)) = self.started.as_ref() {
+                timer.done.store(true, Ordering::Release);
+                thread.unpark();
+            }
+        }
+    }
+
+    /// Wait for future to complete at most given time (see `with_timeout`)
+    ///
+    /// The time starts counting when the returned future is first polled. No async runtime is required as the timer runs on a
+    /// separate thread.
+    pub fn with_timeout_async<F, M>(timeout: Duration, message: M, future: F) -> Timeout<F, M>
+    where
+        F: Future,
+        M: ToString,
+    {
+        Timeout {
+            future: Box::pin(future),
+            timeout,
+            message,
+            started: None,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::task::Wake;
+
+        struct ThreadWaker(thread::Thread);
+
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark()
+            }
+        }
+
+        fn block_on<F: Future>(future: F) -> F::Output {
+            let mut future = Box::pin(future);
+            let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+            let mut cx = Context::from_waker(&waker);
+            loop {
+                if let Poll::Ready(value) = future.as_mut().poll(&mut cx) {
+                    return value;
+                }
+                thread::park();
+            }
+        }
+
+        struct Never;
+
+        impl Future for Never {
+            type Output = ();
+
+            fn poll(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<()> {
+                Poll::Pending
+            }
+        }
+
+        #[test]
+        fn ready() {
+            let value = block_on(with_timeout_async(
+                Duration::from_secs(5),
+                "adding numbers",
+                std::future::ready(4),
+            ));
+            assert_eq!(value.unwrap(), 4);
+        }
+
+        #[test]
+        fn timed_out() {
+            let problem = block_on(with_timeout_async(
+                Duration::from_millis(10),
+                "waiting for reply",
+                Never,
+            ))
+            .unwrap_err();
+            assert_eq!(problem.io_kind(), Some(io::ErrorKind::TimedOut));
+            assert!(problem
+                .to_string()
+                .starts_with("while waiting for reply got error caused by: timed out after"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completed() {
+        assert_eq!(
+            with_timeout(Duration::from_secs(5), "adding numbers", || 2 + 2).unwrap(),
+            4
+        );
+    }
+
+    #[test]
+    fn timed_out() {
+        let problem = with_timeout(Duration::from_millis(10), "waiting for lock", || {
+            thread::sleep(Duration::from_secs(1))
+        })
+        .unwrap_err();
+        assert_eq!(problem.io_kind(), Some(io::ErrorKind::TimedOut));
+        assert_eq!(problem.status(), 504);
+        assert_eq!(problem.contexts().collect::<Vec<_>>(), ["waiting for lock"]);
+    }
+
+    #[test]
+    fn panicked() {
+        // unlike `panic!` this does not call panic hook that would print the message
+        let problem = with_timeout(Duration::from_secs(5), "computing", || -> u32 {
+            std::panic::resume_unwind(Box::new("boom!"))
+        })
+        .unwrap_err();
+        assert_eq!(problem.contexts().collect::<Vec<_>>(), ["computing"]);
+        assert_eq!(problem.causes().last().unwrap().to_string(), "boom!");
+    }
+}
@@ -0,0 +1,138 @@
+//! `Problem` with typed context describing the failed operation.
+//!
+//! Context messages of `Problem` are meant for people. When application needs to decide what to do depending on *what*
+//! failed it can describe operations with its own type (e.g. enum) and attach it with `problem_while_typed`. The operation can
+//! then be matched on and `TypedProblem` converted to plain `Problem` (e.g. with `?`) with the operation added as context message.
+//!
+//! ```rust
+//! use problem::prelude::*;
+//! use problem::TypedProblem;
+//! use std::fmt;
+//!
+//! #[derive(Debug, Clone, Copy, PartialEq)]
+//! enum Operation {
+//!     LoadConfig,
+//!     Connect,
+//! }
+//!
+//! impl fmt::Display for Operation {
+//!     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+//!         match self {
+//!             Operation::LoadConfig => write!(f, "loading config"),
+//!             Operation::Connect => write!(f, "connecting to server"),
+//!         }
+//!     }
+//! }
+//!
+//! fn start() -> Result<u16, TypedProblem<Operation>> {
+//!     let port = "80x".parse::<u16>().problem_while_typed(Operation::LoadConfig)?;
+//!     Ok(port)
+//! }
+//!
+//! let error = start().unwrap_err();
+//! assert_eq!(*error.context(), Operation::LoadConfig);
+//!
+//! let problem: Problem = error.into();
+//! assert_eq!(problem.to_string(), "while loading config got error caused by: invalid digit found in string");
+//! ```
+use super::{Problem, ProblemWhile, ToProblem};
+
+/// `Problem` with context of type `C` describing the operation that failed; see module documentation
+///
+/// Converts to `Problem` with the context added as outermost context message (formatted with `Display`).
+#[derive(Debug)]
+pub struct TypedProblem<C> {
+    context: C,
+    problem: Problem,
+}
+
+impl<C> TypedProblem<C> {
+    /// Create `TypedProblem` from context and error
+    pub fn new(context: C, error: impl ToProblem) -> TypedProblem<C> {
+        TypedProblem {
+            context,
+            problem: error.into_problem(),
+        }
+    }
+
+    /// Get the context describing operation that failed
+    pub fn context(&self) -> &C {
+        &self.context
+    }
+
+    /// Get the `Problem` without the context
+    pub fn problem(&self) -> &Problem {
+        &self.problem
+    }
+
+    /// Change the context keeping the `Problem`
+    pub fn map_context<D>(self, f: impl FnOnce(C) -> D) -> TypedProblem<D> {
+        TypedProblem {
+            context: f(self.context),
+            problem: self.problem,
+        }
+    }
+
+    /// Split into the context and the `Problem` without the context
+    pub fn into_parts(self) -> (C, Problem) {
+        (self.context, self.problem)
+    }
+}
+
+impl<C: ToString> From<TypedProblem<C>> for Problem {
+    fn from(typed: TypedProblem<C>) -> Problem {
+        typed.problem.problem_while(typed.context)
+    }
+}
+
+/// Extension of `Result` that allows to attach typed context to the error converting it to `TypedProblem`
+pub trait ProblemWhileTyped<O> {
+    /// Add context of type `C` describing the operation that failed
+    fn problem_while_typed<C>(self, context: C) -> Result<O, TypedProblem<C>>;
+}
+
+impl<O, E> ProblemWhileTyped<O> for Result<O, E>
+where
+    E: ToProblem,
+{
+    fn problem_while_typed<C>(self, context: C) -> Result<O, TypedProblem<C>> {
+        self.map_err(|error| TypedProblem::new(context, error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Step {
+        Parse(usize),
+    }
+
+    #[test]
+    fn typed_context() {
+        crate::disable_backtraces();
+        let error = "x"
+            .parse::<u8>()
+            .problem_while("reading field")
+            .problem_while_typed(Step::Parse(3))
+            .unwrap_err();
+        assert_eq!(*error.context(), Step::Parse(3));
+        assert_eq!(
+            error.problem().contexts().collect::<Vec<_>>(),
+            ["reading field"]
+        );
+
+        let error = error.map_context(|Step::Parse(line)| format!("parsing line {}", line));
+        let problem: Problem = error.into();
+        assert_eq!(
+            problem.contexts().collect::<Vec<_>>(),
+            ["reading field", "parsing line 3"]
+        );
+
+        let (step, problem) = TypedProblem::new(Step::Parse(1), "boom!").into_parts();
+        assert_eq!(step, Step::Parse(1));
+        assert_eq!(problem.to_string(), "boom!");
+    }
+}
@@ -0,0 +1,151 @@
+//! Compact binary encoding of `Problem`s for sending them between processes.
+//!
+//! Encoded `Problem` starts with schema version followed by `postcard` encoded error message chain, context messages, backtrace and
+//! additional information like documentation URL, tags and kind. The original error types are not preserved; decoded `Problem`
+//! will have `ErrorMessage` cause chain instead. Kind is restored only if it is built-in or registered with `register_kind` in the
+//! decoding process; context timestamps are not encoded.
+//!
+//! ```rust
+//! use problem::prelude::*;
+//!
+//! let problem = Problem::from_error("boom!").problem_while("processing job 42");
+//! let bytes = problem.to_bytes();
+//!
+//! let problem = Problem::from_bytes(&bytes).unwrap();
+//! assert_eq!(problem.to_string(), "while processing job 42 got error caused by: boom!");
+//! ```
+use super::detached::DetachedDetails;
+use super::{error_chain, ErrorMessage, Problem, ProblemKind, ProblemWhile, SectionVisibility};
+use serde::{Deserialize, Serialize};
+
+/// Version of the encoding schema written by `Problem::to_bytes`
+pub const WIRE_VERSION: u8 = 2;
+
+#[derive(Serialize, Deserialize)]
+struct WireProblem {
+    /// Error message followed by messages of its causes
+    messages: Vec<String>,
+    /// Innermost first
+    contexts: Vec<String>,
+    backtrace: Option<String>,
+    url: Option<String>,
+    status: Option<u16>,
+    exit_code: Option<i32>,
+    sections: Vec<(String, String, SectionVisibility)>,
+    dropped_contexts: u64,
+    trace_id: Option<String>,
+    tags: Vec<String>,
+    kind: Option<String>,
+}
+
+impl Problem {
+    /// Encode this `Problem` in compact binary format (see module documentation)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let messages = error_chain(self.error.as_ref())
+            .map(ToString::to_string)
+            .collect();
+
+        let details = DetachedDetails::of(self);
+        let wire = WireProblem {
+            messages,
+            contexts: self.context.clone(),
+            backtrace: self.backtrace().map(|backtrace| backtrace.to_owned()),
+            url: details.url,
+            status: details.status,
+            exit_code: details.exit_code,
+            sections: details.sections,
+            dropped_contexts: details.dropped_contexts as u64,
+            trace_id: details.trace_id,
+            tags: details.tags,
+            kind: details.kind.map(|kind| kind.name().to_owned()),
+        };
+
+        postcard::to_allocvec(&(WIRE_VERSION, wire)).expect("encoding Problem")
+    }
+
+    /// Decode `Problem` encoded with `Problem::to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Problem, Problem> {
+        let (version, bytes) = postcard::take_from_bytes::<u8>(bytes)
+            .problem_while("decoding Problem schema version")?;
+        if version != WIRE_VERSION {
+            return Err(Problem::from_error(format!(
+                "unsupported Problem schema version {} (expected {})",
+                version, WIRE_VERSION
+            )));
+        }
+
+        let wire = postcard::from_bytes::<WireProblem>(bytes).problem_while("decoding Problem")?;
+
+        let error =
+            ErrorMessage::from_chain(wire.messages).unwrap_or_else(|| ErrorMessage::new(""));
+        let mut problem = Problem::from_parts(error, wire.contexts, wire.backtrace);
+        DetachedDetails {
+            url: wire.url,
+            status: wire.status,
+            exit_code: wire.exit_code,
+            sections: wire.sections,
+            dropped_contexts: wire.dropped_contexts as usize,
+            trace_id: wire.trace_id,
+            tags: wire.tags,
+            kind: wire.kind.as_deref().and_then(ProblemKind::from_name),
+            ..DetachedDetails::default()
+        }
+        .attach_to(&mut problem);
+
+        Ok(problem)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use std::error::Error;
+
+    #[test]
+    fn round_trip() {
+        let problem = Problem::from_parts(
+            ErrorMessage::new("bad row").with_source(ErrorMessage::new("invalid digit")),
+            vec!["parsing row 3".to_owned(), "importing b.csv".to_owned()],
+            Some("0: main".to_owned()),
+        )
+        .with_url("https://example.com/import")
+        .with_exit_code(3)
+        .with_tag("import")
+        .with_trace_id("abc123")
+        .with_kind(ProblemKind::CONFIG)
+        .with_section("Row", "1,2,x")
+        .with_section_visibility("Row", SectionVisibility::Verbose)
+        .with_env(&["PROBLEM_WIRE_TEST_UNSET"]);
+
+        let decoded = Problem::from_bytes(&problem.to_bytes()).unwrap();
+
+        assert_eq!(format!("{:#}", decoded), format!("{:#}", problem));
+        assert_eq!(
+            decoded.contexts().collect::<Vec<_>>(),
+            problem.contexts().collect::<Vec<_>>()
+        );
+        assert_eq!(decoded.backtrace(), Some("0: main"));
+        assert_eq!(decoded.url(), Some("https://example.com/import"));
+        assert_eq!(decoded.exit_code(), Some(3));
+        assert!(decoded.has_tag("import"));
+        assert_eq!(decoded.trace_id(), Some("abc123"));
+        assert_eq!(decoded.kind(), Some(ProblemKind::CONFIG));
+        assert!(decoded
+            .recover_if::<ErrorMessage>()
+            .unwrap()
+            .source()
+            .is_some());
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let mut bytes = Problem::from_error("boom!").to_bytes();
+        bytes[0] = WIRE_VERSION + 1;
+        assert!(Problem::from_bytes(&bytes)
+            .unwrap_err()
+            .to_string()
+            .starts_with("unsupported Problem schema version"));
+        assert!(Problem::from_bytes(&[]).is_err());
+    }
+}
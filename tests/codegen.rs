@@ -0,0 +1,76 @@
+//! `Ok` path of `problem_while` must be inlined into the caller leaving error handling to the cold function.
+//!
+//! Builds `examples/codegen_probe.rs` in release mode with `--emit=asm` and inspects its `#[no_mangle]` probes.
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Build the probe and return its assembly
+fn probe_assembly() -> String {
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("codegen");
+    let status = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned()))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env("CARGO_TARGET_DIR", &target_dir)
+        .args(["rustc", "--quiet", "--release", "--no-default-features"])
+        .args(["--example", "codegen_probe", "--"])
+        .args(["--emit=asm", "-C", "codegen-units=1"])
+        .status()
+        .expect("failed to run cargo");
+    assert!(status.success(), "building codegen probe failed");
+
+    let newest = fs::read_dir(target_dir.join("release").join("examples"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension() == Some(OsStr::new("s"))
+                && path
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .starts_with("codegen_probe-")
+        })
+        .max_by_key(|path: &PathBuf| path.metadata().unwrap().modified().unwrap())
+        .expect("no assembly emitted");
+    fs::read_to_string(newest).unwrap()
+}
+
+/// Lines of the body of given function
+fn function<'a>(assembly: &'a str, symbol: &str) -> Vec<&'a str> {
+    let label = format!("{}:", symbol);
+    assembly
+        .lines()
+        .skip_while(|line| line.trim_start_matches('_') != label)
+        .skip(1)
+        .take_while(|line| {
+            let line = line.trim();
+            line != ".cfi_endproc" && line != ".seh_endproc" && !line.starts_with(".Lfunc_end")
+        })
+        .collect()
+}
+
+#[test]
+fn ok_path_is_inlined() {
+    let assembly = probe_assembly();
+
+    let ok_path = function(&assembly, "probe_ok_path");
+    assert!(!ok_path.is_empty(), "probe_ok_path not found");
+    assert!(
+        ok_path.iter().all(|line| !line.contains("problem_while")),
+        "probe_ok_path calls into problem_while:\n{}",
+        ok_path.join("\n")
+    );
+
+    let parse = function(&assembly, "probe_parse");
+    assert!(
+        parse.iter().any(|line| line.contains("problem_while_cold")),
+        "probe_parse does not call problem_while_cold:\n{}",
+        parse.join("\n")
+    );
+    assert!(
+        parse.iter().all(|line| !line.contains("__rust_alloc")),
+        "probe_parse allocates outside of problem_while_cold:\n{}",
+        parse.join("\n")
+    );
+}
@@ -0,0 +1,34 @@
+//! Hook set with `on_problem_created` is called for each new `Problem`.
+extern crate problem;
+
+use problem::prelude::*;
+use std::cell::RefCell;
+use std::panic;
+
+thread_local! {
+    static CREATED: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+#[test]
+fn creation_hook() {
+    problem::on_problem_created(|problem, _contexts| {
+        let message = problem.brief().to_string();
+        if message == "panic in hook" {
+            panic!("hook failed");
+        }
+        // Problems created by the hook do not call it again
+        let _ = Problem::from_error("created by hook");
+        CREATED.with(|created| created.borrow_mut().push(message));
+    });
+
+    let _ = "x".parse::<u32>().map_problem();
+    assert!(panic::catch_unwind(|| Problem::from_error("panic in hook")).is_err());
+    let _ = Problem::from_error("boom!");
+
+    CREATED.with(|created| {
+        assert_eq!(
+            *created.borrow(),
+            ["invalid digit found in string", "boom!"]
+        )
+    });
+}
@@ -0,0 +1,63 @@
+//! Program terminated with `FatalProblem` converted with `?` from an error exits with exit code registered for its kind.
+extern crate problem;
+
+use problem::prelude::*;
+use problem::{kind::KindPolicy, register_kind, IntoProblem, ProblemKind};
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::process::Command;
+
+const DATABASE: ProblemKind = ProblemKind::new("database");
+
+#[derive(Debug)]
+struct ConnectionRefused;
+
+impl fmt::Display for ConnectionRefused {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("connection refused")
+    }
+}
+
+impl Error for ConnectionRefused {}
+
+impl IntoProblem for ConnectionRefused {
+    fn problem_kind(&self) -> Option<ProblemKind> {
+        Some(DATABASE)
+    }
+}
+
+fn connect() -> Result<(), ConnectionRefused> {
+    Err(ConnectionRefused)
+}
+
+fn main_body() -> Result<(), FatalProblem> {
+    register_kind(
+        DATABASE,
+        KindPolicy {
+            exit_code: Some(69),
+            ..KindPolicy::default()
+        },
+    );
+    connect()?;
+    Ok(())
+}
+
+#[test]
+fn kind_exit_code() {
+    if env::var_os("PROBLEM_EXIT_CODE_CHILD").is_some() {
+        // `Debug` of `FatalProblem` prints the report and exits as when it is returned from `main`
+        if let Err(fatal) = problem::run_main(main_body) {
+            let _ = format!("{:?}", fatal);
+        }
+        unreachable!("program should have exited");
+    }
+
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "kind_exit_code", "--nocapture"])
+        .env("PROBLEM_EXIT_CODE_CHILD", "1")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(69));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("connection refused"));
+}
@@ -0,0 +1,82 @@
+//! `Ok` path of context adding methods must not allocate.
+extern crate problem;
+
+use problem::prelude::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::hint::black_box;
+use std::io;
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Counts allocations made on threads that enabled counting
+struct CountingAlloc;
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if COUNTING.with(Cell::get) {
+            ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+fn allocations(f: impl FnOnce()) -> usize {
+    ALLOCATIONS.with(|count| count.set(0));
+    COUNTING.with(|counting| counting.set(true));
+    f();
+    COUNTING.with(|counting| counting.set(false));
+    ALLOCATIONS.with(Cell::get)
+}
+
+fn parse(input: &str) -> Result<u32, Problem> {
+    let number = input.parse::<u32>().problem_while("parsing number")?;
+    let checked = black_box(Ok::<_, io::Error>(number))
+        .problem_while_with(|| format!("checking {}", number))?;
+    Ok(checked)
+}
+
+#[test]
+fn ok_path_does_not_allocate() {
+    assert_eq!(
+        allocations(|| {
+            for _ in 0..1000 {
+                assert_eq!(parse(black_box("42")).unwrap(), 42);
+            }
+        }),
+        0
+    );
+}
+
+#[test]
+fn in_context_of_ok_path_does_not_allocate() {
+    assert_eq!(
+        allocations(|| {
+            for number in 0..1000u32 {
+                let message = format_args!("processing item {}", number);
+                let result = in_context_of(message, || Ok(black_box(number)));
+                assert_eq!(result.unwrap(), number);
+            }
+        }),
+        0
+    );
+}
+
+#[test]
+fn err_path_allocates() {
+    assert!(
+        allocations(|| {
+            parse(black_box("x")).unwrap_err();
+        }) > 0
+    );
+}